@@ -1,7 +1,10 @@
-use chrono::Duration;
+use crate::libs::gitlab_api::gitlab_api::Member;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, Weekday};
 use mongodb::options::ClientOptions;
 use serde::Deserialize;
 use std::env;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
 
@@ -16,6 +19,10 @@ impl Environment {
     pub fn is_test(&self) -> bool {
         matches!(self, Environment::Test)
     }
+
+    pub fn is_development(&self) -> bool {
+        matches!(self, Environment::Development)
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -43,60 +50,454 @@ impl MongoConfiguration {
 #[derive(Debug)]
 pub struct GitlabConfiguration {
     pub domain: String,
+    /// Scheme and host of the gitlab instance, `https://{domain}` unless
+    /// `GITLAB_URL` overrides it (e.g. to point to a local mock server).
+    pub url: String,
     pub access_token: String,
-    pub group_id: String,
+    pub group_ids: Vec<String>,
+    /// Members below this gitlab access level (10 guest, 20 reporter, 30
+    /// developer, ...) are not allowed to log in.
+    pub min_access_level: u32,
     pub client_id: String,
     pub client_secret: String,
     pub redirect_url: String,
+    pub webhook_secret: Option<String>,
+    /// Project where issues created from topics are opened.
+    pub issues_project_id: Option<String>,
+    pub archive_topics_on_issue_close: bool,
+    /// Project whose wiki guild agendas are published to.
+    pub wiki_project_id: Option<String>,
+    pub wiki_export_interval: Option<StdDuration>,
+    pub api_timeout: StdDuration,
+    pub api_max_retries: u32,
+    /// Refreshes removing a larger share of the members are not applied,
+    /// gitlab most likely returned an incomplete list.
+    pub max_removed_members_percent: u32,
 }
 
 impl GitlabConfiguration {
-    pub fn new() -> Self {
-        Self {
-            domain: env::var("GITLAB_DOMAIN")
-                .expect("GITLAB_DOMAIN variable to be available"),
+    pub fn new() -> Result<Self> {
+        let domain = require_var("GITLAB_DOMAIN")?;
+
+        Ok(Self {
+            url: env::var("GITLAB_URL")
+                .unwrap_or_else(|_| format!("https://{domain}"))
+                .trim_end_matches('/')
+                .to_string(),
+
+            domain,
+
+            access_token: require_var("GITLAB_ACCESS_TOKEN")?,
+
+            group_ids: parse_gitlab_group_ids()?,
+
+            min_access_level: parse_var(
+                "GITLAB_MIN_ACCESS_LEVEL",
+                "0",
+                "an integer",
+            )?,
+
+            client_id: require_var("GITLAB_CLIENT_ID")?,
+
+            client_secret: require_var("GITLAB_CLIENT_SECRET")?,
 
-            access_token: env::var("GITLAB_ACCESS_TOKEN")
-                .expect("GITLAB_ACCESS_TOKEN variable to be available"),
+            redirect_url: require_var("GITLAB_REDIRECT_URL")?,
 
-            group_id: env::var("GITLAB_GROUP_ID")
-                .expect("GITLAB_GROUP_ID variable to be available"),
+            webhook_secret: env::var("GITLAB_WEBHOOK_SECRET").ok(),
 
-            client_id: env::var("GITLAB_CLIENT_ID")
-                .expect("GITLAB_CLIENT_ID variable to be available"),
+            issues_project_id: env::var("GITLAB_ISSUES_PROJECT_ID").ok(),
+
+            archive_topics_on_issue_close: parse_var(
+                "GITLAB_ARCHIVE_TOPICS_ON_ISSUE_CLOSE",
+                "false",
+                "a boolean",
+            )?,
+
+            wiki_project_id: env::var("GITLAB_WIKI_PROJECT_ID").ok(),
+
+            wiki_export_interval: env::var(
+                "GITLAB_WIKI_EXPORT_INTERVAL_MINUTES",
+            )
+            .ok()
+            .map(|minutes| {
+                parse_value::<u64>(
+                    "GITLAB_WIKI_EXPORT_INTERVAL_MINUTES",
+                    &minutes,
+                    "an integer",
+                )
+                .map(|minutes| StdDuration::from_secs(minutes * 60))
+            })
+            .transpose()?,
+
+            api_timeout: StdDuration::from_secs(parse_var(
+                "GITLAB_API_TIMEOUT_SECONDS",
+                "10",
+                "an integer",
+            )?),
+
+            api_max_retries: parse_var(
+                "GITLAB_API_MAX_RETRIES",
+                "3",
+                "an integer",
+            )?,
+
+            max_removed_members_percent: parse_var(
+                "GITLAB_MAX_REMOVED_MEMBERS_PERCENT",
+                "50",
+                "an integer",
+            )?,
+        })
+    }
+}
 
-            client_secret: env::var("GITLAB_CLIENT_SECRET")
-                .expect("GITLAB_CLIENT_SECRET variable to be available"),
+fn parse_gitlab_group_ids() -> Result<Vec<String>> {
+    let group_ids = parse_list("GITLAB_GROUP_IDS");
 
-            redirect_url: env::var("GITLAB_REDIRECT_URL")
-                .expect("GITLAB_REDIRECT_URL variable to be available"),
+    if !group_ids.is_empty() {
+        return Ok(group_ids);
+    }
+
+    Ok(vec![env::var("GITLAB_GROUP_ID").context(
+        "GITLAB_GROUP_IDS or GITLAB_GROUP_ID variable to be available",
+    )?])
+}
+
+#[derive(Debug)]
+pub enum MemberDirectoryConfiguration {
+    Gitlab,
+    /// Static roster in a YAML, JSON or LDIF file.
+    File {
+        path: String,
+    },
+}
+
+impl MemberDirectoryConfiguration {
+    pub fn from_env() -> Result<Self> {
+        let directory = env::var("MEMBER_DIRECTORY")
+            .unwrap_or_else(|_| "gitlab".to_string());
+
+        match directory.trim().to_lowercase().as_str() {
+            "gitlab" => Ok(MemberDirectoryConfiguration::Gitlab),
+            "file" => Ok(MemberDirectoryConfiguration::File {
+                path: require_var("MEMBER_DIRECTORY_FILE")?,
+            }),
+            directory => bail!(
+                "MEMBER_DIRECTORY variable to be gitlab or file, got {directory}"
+            ),
         }
     }
+
+    pub fn is_gitlab(&self) -> bool {
+        matches!(self, MemberDirectoryConfiguration::Gitlab)
+    }
+}
+
+/// What happens to the topics and open votes of members who leave the
+/// directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepartedMemberPolicy {
+    /// Topics and votes are left untouched.
+    Keep,
+    /// Open topics are handed to the guild owner and votes are dropped.
+    Reassign,
+    /// Open topics are archived and votes are dropped.
+    Freeze,
+}
+
+impl DepartedMemberPolicy {
+    pub fn from_env() -> Result<Self> {
+        let policy = env::var("DEPARTED_MEMBER_POLICY")
+            .unwrap_or_else(|_| "keep".to_string());
+
+        match policy.trim().to_lowercase().as_str() {
+            "keep" => Ok(DepartedMemberPolicy::Keep),
+            "reassign" => Ok(DepartedMemberPolicy::Reassign),
+            "freeze" => Ok(DepartedMemberPolicy::Freeze),
+            policy => bail!(
+                "DEPARTED_MEMBER_POLICY variable to be keep, reassign or freeze, got {policy}"
+            ),
+        }
+    }
+}
+
+/// How events reach the other instances serving the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTransportKind {
+    /// Events stay inside the process, enough for a single instance.
+    InProcess,
+    /// Events go through a mongo collection followed with change streams.
+    Mongo,
+}
+
+impl EventTransportKind {
+    pub fn from_env() -> Result<Self> {
+        let transport = env::var("EVENTS_TRANSPORT")
+            .unwrap_or_else(|_| "in_process".to_string());
+
+        match transport.trim().to_lowercase().as_str() {
+            "in_process" => Ok(EventTransportKind::InProcess),
+            "mongo" => Ok(EventTransportKind::Mongo),
+            transport => bail!(
+                "EVENTS_TRANSPORT variable to be in_process or mongo, got {transport}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventsConfiguration {
+    pub transport: EventTransportKind,
+    /// Capacity of the app wide broadcast channel. Subscribers that fall
+    /// further behind lose events and are asked to resync.
+    pub channel_capacity: usize,
+    /// How many events are kept per guild so reconnecting clients can
+    /// catch up on what they missed.
+    pub replay_buffer_size: usize,
+}
+
+impl EventsConfiguration {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            transport: EventTransportKind::from_env()?,
+            channel_capacity: parse_var(
+                "EVENTS_CHANNEL_CAPACITY",
+                "256",
+                "an integer",
+            )?,
+            replay_buffer_size: parse_var(
+                "EVENTS_REPLAY_BUFFER_SIZE",
+                "100",
+                "an integer",
+            )?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificationsConfiguration {
+    /// Creators are notified once their topic reaches this many votes.
+    pub votes_threshold: usize,
+}
+
+impl NotificationsConfiguration {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            votes_threshold: parse_var(
+                "NOTIFICATIONS_VOTES_THRESHOLD",
+                "5",
+                "an integer",
+            )?,
+        })
+    }
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailTls {
+    /// Plain text, only meant for local mail catchers.
+    None,
+    StartTls,
+    /// TLS from the start of the connection, usually on port 465.
+    Tls,
+}
+
+impl MailTls {
+    pub fn from_env() -> Result<Self> {
+        let tls =
+            env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+
+        match tls.trim().to_lowercase().as_str() {
+            "none" => Ok(MailTls::None),
+            "starttls" => Ok(MailTls::StartTls),
+            "tls" => Ok(MailTls::Tls),
+            tls => {
+                bail!(
+                    "SMTP_TLS variable to be none, starttls or tls, got {tls}"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfiguration {
+    pub host: String,
+    pub port: u16,
+    pub tls: MailTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    /// Weekly digests go out on this day at `digest_hour` UTC.
+    pub digest_weekday: Weekday,
+    pub digest_hour: u32,
+}
+
+impl MailConfiguration {
+    /// Emails are only sent when `SMTP_HOST` is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(host) = env::var("SMTP_HOST") else {
+            return Ok(None);
+        };
+
+        let tls = MailTls::from_env()?;
+
+        let default_port = match tls {
+            MailTls::None => "25",
+            MailTls::StartTls => "587",
+            MailTls::Tls => "465",
+        };
+
+        let digest_hour = parse_var("DIGEST_HOUR", "8", "an integer")?;
+
+        if digest_hour > 23 {
+            bail!("DIGEST_HOUR variable to be between 0 and 23");
+        }
+
+        Ok(Some(Self {
+            host,
+            port: parse_var("SMTP_PORT", default_port, "an integer")?,
+            tls,
+            username: env::var("SMTP_USERNAME").ok(),
+            password: env::var("SMTP_PASSWORD").ok(),
+            from: require_var("SMTP_FROM")?,
+            digest_weekday: parse_var(
+                "DIGEST_WEEKDAY",
+                "monday",
+                "a day of the week",
+            )?,
+            digest_hour,
+        }))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthKeyAlgorithm {
+    HS256,
+    RS256,
+    EdDSA,
+}
+
+/// A single JWT key. Symmetric keys carry a `secret`, asymmetric ones point
+/// to PEM files. The private key is only needed for the signing key.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthKeyConfiguration {
+    pub kid: String,
+    pub algorithm: AuthKeyAlgorithm,
+    pub secret: Option<String>,
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
 }
 
 #[derive(Debug)]
 pub struct AuthConfiguration {
     pub expire_in_hours: Duration,
-    pub secret: String,
+    pub refresh_after: Duration,
+    pub session_lifetime: Duration,
+    /// Newest key first. Tokens are signed with the first key and verified
+    /// with any of them.
+    pub keys: Vec<AuthKeyConfiguration>,
 }
 
 impl AuthConfiguration {
-    pub fn new() -> Self {
-        Self {
-            expire_in_hours: Duration::hours(
-                env::var("AUTH_TOKEN_VALID_FOR_HOURS")
-                    .expect(
-                        "AUTH_TOKEN_VALID_FOR_HOURS variable to be available",
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            expire_in_hours: Duration::hours(parse_value(
+                "AUTH_TOKEN_VALID_FOR_HOURS",
+                &require_var("AUTH_TOKEN_VALID_FOR_HOURS")?,
+                "an integer",
+            )?),
+
+            refresh_after: Duration::minutes(parse_var(
+                "AUTH_REFRESH_AFTER_MINUTES",
+                "120",
+                "an integer",
+            )?),
+
+            session_lifetime: Duration::hours(parse_var(
+                "AUTH_SESSION_LIFETIME_HOURS",
+                "720",
+                "an integer",
+            )?),
+
+            keys: parse_auth_keys()?,
+        })
+    }
+}
+
+fn parse_auth_keys() -> Result<Vec<AuthKeyConfiguration>> {
+    if let Ok(keys) = env::var("AUTH_KEYS") {
+        let keys: Vec<AuthKeyConfiguration> = serde_json::from_str(&keys)
+            .context("AUTH_KEYS variable to be a JSON list of keys")?;
+
+        if keys.is_empty() {
+            bail!("AUTH_KEYS variable to contain at least one key");
+        }
+
+        return Ok(keys);
+    }
+
+    Ok(vec![AuthKeyConfiguration {
+        kid: "default".to_string(),
+        algorithm: AuthKeyAlgorithm::HS256,
+        secret: Some(
+            env::var("AUTH_SECRET").context(
+                "AUTH_SECRET or AUTH_KEYS variable to be available",
+            )?,
+        ),
+        private_key_path: None,
+        public_key_path: None,
+    }])
+}
+
+#[derive(Debug)]
+pub struct AdminConfiguration {
+    pub user_ids: Vec<usize>,
+    pub usernames: Vec<String>,
+}
+
+impl AdminConfiguration {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            user_ids: parse_list("ADMIN_USER_IDS")
+                .iter()
+                .map(|user_id| {
+                    parse_value(
+                        "ADMIN_USER_IDS",
+                        user_id,
+                        "a list of integers",
                     )
-                    .parse()
-                    .expect(
-                        "AUTH_TOKEN_VALID_FOR_HOURS variable to an integer",
-                    ),
-            ),
+                })
+                .collect::<Result<_>>()?,
+
+            usernames: parse_list("ADMIN_USERNAMES")
+                .into_iter()
+                .map(|username| username.to_lowercase())
+                .collect(),
+        })
+    }
+
+    pub fn is_admin(&self, member: &Member) -> bool {
+        self.user_ids.contains(&member.id)
+            || self.usernames.contains(&member.username.to_lowercase())
+    }
+}
+
+/// Slash commands (`/guild ...`) sent by Mattermost or Slack.
+#[derive(Debug)]
+pub struct ChatConfiguration {
+    /// Verification tokens of the configured slash commands, one per chat.
+    pub command_tokens: Vec<String>,
+}
+
+impl ChatConfiguration {
+    pub fn from_env() -> Option<Self> {
+        let command_tokens = parse_list("SLASH_COMMAND_TOKENS");
 
-            secret: env::var("AUTH_SECRET")
-                .expect("AUTH_SECRET variable to be available"),
+        if command_tokens.is_empty() {
+            return None;
         }
+
+        Some(Self { command_tokens })
     }
 }
 
@@ -104,48 +505,188 @@ impl AuthConfiguration {
 pub struct Configuration {
     pub mongo: Arc<MongoConfiguration>,
     pub app_port: u16,
+    /// Address the app is reachable at, used for links sent outside of it.
+    pub app_url: String,
     pub environment: Environment,
-    pub gitlab: GitlabConfiguration,
+    /// `/dev/login`, opted into with `DEV_LOGIN` and only in development.
+    pub dev_login: bool,
+    pub member_directory: MemberDirectoryConfiguration,
+    pub departed_member_policy: DepartedMemberPolicy,
+    /// Only loaded when gitlab is the member directory.
+    pub gitlab: Option<GitlabConfiguration>,
     pub auth: AuthConfiguration,
+    pub admin: AdminConfiguration,
+    pub events: EventsConfiguration,
+    pub notifications: NotificationsConfiguration,
+    /// Only loaded when an SMTP server is configured.
+    pub mail: Option<MailConfiguration>,
+    /// Only loaded when slash command tokens are configured.
+    pub chat: Option<ChatConfiguration>,
 }
 
 impl Configuration {
-    pub async fn new() -> Self {
+    pub async fn new() -> Result<Self> {
         dotenv::dotenv().ok();
 
-        let environment = parse_env();
+        let environment = parse_env()?;
+
+        let dev_login = parse_dev_login(&environment)?;
+
+        let member_directory = MemberDirectoryConfiguration::from_env()?;
 
         let mongo_db_uri = env::var("MONGO_DB_URI")
             .unwrap_or_else(|_| String::from("mongodb://localhost:27017"));
 
-        Configuration {
+        let app_port = parse_var("APP_PORT", "80", "an integer")?;
+
+        Ok(Configuration {
             mongo: Arc::new(MongoConfiguration {
                 client_options: MongoConfiguration::parse_connection_string(
                     &mongo_db_uri,
                 )
                 .await
-                .expect("Valid mongo_db_uri"),
+                .context(
+                    "MONGO_DB_URI variable to be a mongo connection string",
+                )?,
             }),
-            app_port: env::var("APP_PORT")
-                .unwrap_or_else(|_| "80".to_string())
-                .parse()
-                .expect("APP_PORT variable to an integer"),
+            app_port,
+            app_url: env::var("APP_URL")
+                .unwrap_or_else(|_| format!("http://localhost:{app_port}"))
+                .trim_end_matches('/')
+                .to_string(),
             environment,
-            gitlab: GitlabConfiguration::new(),
-            auth: AuthConfiguration::new(),
-        }
+            dev_login,
+            gitlab: member_directory
+                .is_gitlab()
+                .then(GitlabConfiguration::new)
+                .transpose()?,
+            member_directory,
+            departed_member_policy: DepartedMemberPolicy::from_env()?,
+            auth: AuthConfiguration::new()?,
+            admin: AdminConfiguration::from_env()?,
+            events: EventsConfiguration::from_env()?,
+            notifications: NotificationsConfiguration::from_env()?,
+            mail: MailConfiguration::from_env()?,
+            chat: ChatConfiguration::from_env(),
+        })
     }
 }
 
-fn parse_env() -> Environment {
+fn parse_list(variable: &str) -> Vec<String> {
+    env::var(variable)
+        .unwrap_or_default()
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn require_var(variable: &str) -> Result<String> {
+    env::var(variable)
+        .with_context(|| format!("{variable} variable to be available"))
+}
+
+/// Parses `variable`, falling back to `default` when it isn't set.
+fn parse_var<T: FromStr>(
+    variable: &str,
+    default: &str,
+    expected: &str,
+) -> Result<T> {
+    let value = env::var(variable).unwrap_or_else(|_| default.to_string());
+
+    parse_value(variable, &value, expected)
+}
+
+fn parse_value<T: FromStr>(
+    variable: &str,
+    value: &str,
+    expected: &str,
+) -> Result<T> {
+    value.trim().parse().map_err(|_| {
+        anyhow!("{variable} variable to be {expected}, got {value}")
+    })
+}
+
+fn parse_env() -> Result<Environment> {
     let env_var =
         env::var("ENV").unwrap_or_else(|_| "development".to_string());
 
-    match env_var.trim().to_lowercase().as_str() {
-        "production" => Environment::Production,
-        "prod" => Environment::Production,
-        "test" => Environment::Test,
-        "development" => Environment::Development,
-        _ => Environment::Development,
+    parse_environment(&env_var)
+}
+
+fn parse_environment(value: &str) -> Result<Environment> {
+    match value.trim().to_lowercase().as_str() {
+        "production" => Ok(Environment::Production),
+        "prod" => Ok(Environment::Production),
+        "test" => Ok(Environment::Test),
+        "development" => Ok(Environment::Development),
+        value => bail!(
+            "ENV variable to be production, test or development, got {value}"
+        ),
+    }
+}
+
+fn parse_dev_login(environment: &Environment) -> Result<bool> {
+    let dev_login: bool = env::var("DEV_LOGIN")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .context("DEV_LOGIN variable to be a boolean")?;
+
+    if dev_login && !environment.is_development() {
+        bail!("DEV_LOGIN variable to only be set with ENV=development");
+    }
+
+    Ok(dev_login)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_environment_accepts_known_values() {
+        assert!(matches!(
+            parse_environment(" Production "),
+            Ok(Environment::Production)
+        ));
+        assert!(matches!(
+            parse_environment("prod"),
+            Ok(Environment::Production)
+        ));
+        assert!(matches!(parse_environment("test"), Ok(Environment::Test)));
+        assert!(matches!(
+            parse_environment("development"),
+            Ok(Environment::Development)
+        ));
+    }
+
+    #[test]
+    fn parse_environment_refuses_unknown_values() {
+        assert!(parse_environment("staging").is_err());
+        assert!(parse_environment("").is_err());
+    }
+
+    #[test]
+    fn parse_value_reads_trimmed_values() {
+        assert_eq!(
+            parse_value::<u16>("APP_PORT", " 8080 ", "an integer").unwrap(),
+            8080
+        );
+        assert_eq!(
+            parse_value::<Weekday>("DIGEST_WEEKDAY", "friday", "a day")
+                .unwrap(),
+            Weekday::Fri
+        );
+    }
+
+    #[test]
+    fn parse_value_names_the_variable_on_errors() {
+        let err = parse_value::<u16>("APP_PORT", "eighty", "an integer")
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "APP_PORT variable to be an integer, got eighty"
+        );
     }
 }
@@ -34,7 +34,8 @@ where
         let config = Config::new(10, false);
 
         let form: T = config.deserialize_bytes(&body).map_err(|err| {
-            AppError::from(anyhow::Error::new(err)).into_response()
+            AppError::Validation(format!("Invalid form: {err}"))
+                .into_response()
         })?;
 
         Ok(Form(form))
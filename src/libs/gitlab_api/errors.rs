@@ -0,0 +1,55 @@
+use reqwest::StatusCode;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GitlabApiError {
+    #[error("gitlab responded with {status} to {url}: {body}")]
+    Status { status: StatusCode, url: String, body: String },
+
+    #[error(
+        "gitlab rate limit exceeded for {url}, retry after {retry_after:?}"
+    )]
+    RateLimited { url: String, retry_after: Option<Duration> },
+
+    #[error("request to gitlab failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("failed to decode gitlab response from {url}: {source}")]
+    Decode { url: String, source: serde_json::Error },
+
+    #[error("invalid gitlab url {url}: {source}")]
+    InvalidUrl { url: String, source: url::ParseError },
+}
+
+impl GitlabApiError {
+    /// Whether sending the same request again could succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            GitlabApiError::Status { status, .. } => status.is_server_error(),
+            GitlabApiError::RateLimited { .. } => true,
+            GitlabApiError::Request(err) => {
+                err.is_timeout() || err.is_connect()
+            }
+            GitlabApiError::Decode { .. } => false,
+            GitlabApiError::InvalidUrl { .. } => false,
+        }
+    }
+
+    /// Whether gitlab surely didn't act on the request, so sending it again
+    /// can't apply it twice, e.g. create the same issue again.
+    pub fn is_rejected_before_processing(&self) -> bool {
+        match self {
+            GitlabApiError::RateLimited { .. } => true,
+            GitlabApiError::Request(err) => err.is_connect(),
+            _ => false,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            GitlabApiError::Status { status: StatusCode::NOT_FOUND, .. }
+        )
+    }
+}
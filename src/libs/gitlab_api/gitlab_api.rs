@@ -1,115 +1,486 @@
 use anyhow::Result;
+use chrono::NaiveDate;
 use oauth2::basic::BasicClient;
 use oauth2::reqwest::async_http_client;
 use oauth2::{
     AccessToken, AuthUrl, AuthorizationCode, ClientId, ClientSecret,
     RedirectUrl, TokenResponse, TokenUrl,
 };
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use reqwest::{
+    header::HeaderMap, Client, RequestBuilder, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::warn;
+use url::Url;
+
+use crate::configuration::GitlabConfiguration;
+
+use super::GitlabApiError;
+
+const PAGE_SIZE: usize = 100;
+
+/// Longest wait between two attempts, whatever `Retry-After` asks for.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
 
 pub struct GitlabApi {
     pub domain: String,
+    pub url: String,
     pub private_token: String,
     pub oath: BasicClient,
     http_client: Client,
+    max_retries: u32,
 }
 
 impl GitlabApi {
-    pub fn new(
-        private_token: String,
-        domain: String,
-        client_id: String,
-        client_secret: String,
-        redirect_url: String,
-    ) -> Self {
+    pub fn new(configuration: &GitlabConfiguration) -> Self {
         let oath = BasicClient::new(
-            ClientId::new(client_id),
-            Some(ClientSecret::new(client_secret)),
-            AuthUrl::new(format!("https://{}/oauth/authorize", &domain,))
+            ClientId::new(configuration.client_id.clone()),
+            Some(ClientSecret::new(configuration.client_secret.clone())),
+            AuthUrl::new(format!("{}/oauth/authorize", &configuration.url))
                 .expect("To be able to create auth url"),
             Some(
-                TokenUrl::new(format!("https://{}/oauth/token", &domain,))
+                TokenUrl::new(format!("{}/oauth/token", &configuration.url))
                     .expect("To be able to create token url"),
             ),
         )
         .set_redirect_uri(
-            RedirectUrl::new(redirect_url).expect("Invalid redirect URL"),
+            RedirectUrl::new(configuration.redirect_url.clone())
+                .expect("Invalid redirect URL"),
         );
 
-        Self { domain, private_token, http_client: Client::new(), oath }
+        // requests carry the token in a header that would follow redirects
+        // to any host
+        let redirect_policy = reqwest::redirect::Policy::custom(|attempt| {
+            let is_same_origin = attempt
+                .previous()
+                .first()
+                .is_some_and(|first| is_same_origin(first, attempt.url()));
+
+            match is_same_origin && attempt.previous().len() < 10 {
+                true => attempt.follow(),
+                false => attempt.stop(),
+            }
+        });
+
+        let http_client = Client::builder()
+            .timeout(configuration.api_timeout)
+            .connect_timeout(configuration.api_timeout)
+            .redirect(redirect_policy)
+            .build()
+            .expect("To be able to create gitlab http client");
+
+        Self {
+            domain: configuration.domain.clone(),
+            url: configuration.url.clone(),
+            private_token: configuration.access_token.clone(),
+            http_client,
+            oath,
+            max_retries: configuration.api_max_retries,
+        }
     }
 
     fn get_base_api_url(&self) -> String {
-        format!("https://{}/api/v4", self.domain)
+        format!("{}/api/v4", self.url)
+    }
+
+    fn get_retry_delay(attempt: u32) -> Duration {
+        Duration::from_millis(
+            500u64.saturating_mul(2u64.saturating_pow(attempt)),
+        )
+        .min(Duration::from_secs(30))
+    }
+
+    fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+        headers
+            .get(reqwest::header::RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+            .map(|seconds| Duration::from_secs(seconds).min(MAX_RETRY_DELAY))
+    }
+
+    async fn send_once(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<Response, GitlabApiError> {
+        let response = request.send().await?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let url = response.url().to_string();
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(GitlabApiError::RateLimited {
+                url,
+                retry_after: Self::parse_retry_after(response.headers()),
+            });
+        }
+
+        let body = response.text().await.unwrap_or_default();
+
+        Err(GitlabApiError::Status { status, url, body })
+    }
+
+    /// Sends a request built by `build_request`, retrying rate limited,
+    /// timed out and server error responses with an exponential backoff.
+    /// Requests that are not idempotent are only retried when gitlab surely
+    /// didn't act on them.
+    async fn send<F>(
+        &self,
+        is_idempotent: bool,
+        build_request: F,
+    ) -> Result<Response, GitlabApiError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match self.send_once(build_request()).await {
+                Ok(response) => return Ok(response),
+                Err(err)
+                    if attempt < self.max_retries
+                        && match is_idempotent {
+                            true => err.is_retryable(),
+                            false => err.is_rejected_before_processing(),
+                        } =>
+                {
+                    let delay = match &err {
+                        GitlabApiError::RateLimited {
+                            retry_after: Some(retry_after),
+                            ..
+                        } => *retry_after,
+                        _ => Self::get_retry_delay(attempt),
+                    };
+
+                    warn!("{err}. Retrying in {delay:?}...");
+
+                    sleep(delay).await;
+
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    async fn decode<T: DeserializeOwned>(
+        response: Response,
+    ) -> Result<T, GitlabApiError> {
+        let url = response.url().to_string();
+
+        let body = response.bytes().await?;
+
+        serde_json::from_slice(&body)
+            .map_err(|source| GitlabApiError::Decode { url, source })
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<T, GitlabApiError> {
+        let response = self
+            .send(true, || {
+                self.http_client
+                    .get(url)
+                    .header("PRIVATE-TOKEN", &self.private_token)
+            })
+            .await?;
+
+        Self::decode(response).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        url: &str,
+        body: &B,
+    ) -> Result<T, GitlabApiError> {
+        let response = self
+            .send(false, || {
+                self.http_client
+                    .post(url)
+                    .header("PRIVATE-TOKEN", &self.private_token)
+                    .json(body)
+            })
+            .await?;
+
+        Self::decode(response).await
+    }
+
+    async fn put<B: Serialize, T: DeserializeOwned>(
+        &self,
+        url: &str,
+        body: &B,
+    ) -> Result<T, GitlabApiError> {
+        let response = self
+            .send(true, || {
+                self.http_client
+                    .put(url)
+                    .header("PRIVATE-TOKEN", &self.private_token)
+                    .json(body)
+            })
+            .await?;
+
+        Self::decode(response).await
+    }
+
+    /// Resolves the url of the next page from the `X-Next-Page` header, or
+    /// from the `Link` header used by keyset pagination. Links to another
+    /// origin than `base_url` are not followed, the token would go along.
+    fn get_next_page_url(
+        base_url: &Url,
+        url: &Url,
+        headers: &HeaderMap,
+    ) -> Option<String> {
+        let next_page = headers
+            .get("X-Next-Page")
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+
+        if let Some(next_page) = next_page {
+            let mut url: Url = url.clone();
+
+            let query: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|(key, _)| key != "page")
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(query)
+                .append_pair("page", next_page);
+
+            return Some(url.to_string());
+        }
+
+        let next_url = headers
+            .get(reqwest::header::LINK)
+            .and_then(|value| value.to_str().ok())?
+            .split(',')
+            .find(|link| link.contains("rel=\"next\""))
+            .and_then(|link| {
+                let start = link.find('<')? + 1;
+                let end = link.find('>')?;
+
+                link.get(start..end)
+            })?;
+
+        match Url::parse(next_url) {
+            Ok(next_url) if is_same_origin(base_url, &next_url) => {
+                Some(next_url.to_string())
+            }
+            _ => {
+                warn!("Not following next page link to {next_url}");
+                None
+            }
+        }
     }
 
-    pub async fn get_group_members(
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        url: String,
+    ) -> Result<Vec<T>, GitlabApiError> {
+        let base_url = match Url::parse(&self.url) {
+            Ok(base_url) => base_url,
+            Err(err) => {
+                return Err(GitlabApiError::InvalidUrl {
+                    url: self.url.clone(),
+                    source: err,
+                })
+            }
+        };
+
+        let mut items = Vec::new();
+
+        let mut next_url = Some(url);
+
+        while let Some(url) = next_url {
+            let response = self
+                .send(true, || {
+                    self.http_client
+                        .get(&url)
+                        .header("PRIVATE-TOKEN", &self.private_token)
+                })
+                .await?;
+
+            next_url = Self::get_next_page_url(
+                &base_url,
+                response.url(),
+                response.headers(),
+            );
+
+            let page: Vec<T> = Self::decode(response).await?;
+
+            items.extend(page);
+        }
+
+        Ok(items)
+    }
+
+    pub async fn get_all_group_members(
         &self,
         group_id: &str,
-        page: usize,
-        page_size: usize,
-    ) -> Result<Vec<Member>> {
+    ) -> Result<Vec<Member>, GitlabApiError> {
         let url = format!(
-            "{}/groups/{group_id}/members/all?page={page}&per_page={page_size}&private_token={}",
+            "{}/groups/{group_id}/members/all?per_page={PAGE_SIZE}",
             self.get_base_api_url(),
-            self.private_token
         );
 
-        let members = self
-            .http_client
-            .get(url)
-            .send()
-            .await?
-            .json::<Vec<Member>>()
-            .await?;
-
-        Ok(members)
+        self.get_all_pages(url).await
     }
 
-    pub async fn get_all_group_members(
+    /// Returns the membership of a user in a group, including inherited
+    /// memberships, or `None` when the user is not a member.
+    pub async fn get_group_member(
         &self,
         group_id: &str,
-    ) -> Result<Vec<Member>> {
-        let mut members = Vec::new();
+        user_id: usize,
+    ) -> Result<Option<Member>, GitlabApiError> {
+        let url = format!(
+            "{}/groups/{group_id}/members/all/{user_id}",
+            self.get_base_api_url(),
+        );
+
+        match self.get(&url).await {
+            Ok(member) => Ok(Some(member)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Opens an issue in `project_id`, which is either a numeric id or the
+    /// full path of the project.
+    pub async fn create_issue(
+        &self,
+        project_id: &str,
+        issue: &NewIssue,
+    ) -> Result<Issue, GitlabApiError> {
+        let url = format!(
+            "{}/projects/{}/issues",
+            self.get_base_api_url(),
+            encode_path_segment(project_id),
+        );
+
+        self.post(&url, issue).await
+    }
 
-        let page_size = 100;
+    pub async fn get_wiki_page(
+        &self,
+        project_id: &str,
+        slug: &str,
+    ) -> Result<Option<WikiPage>, GitlabApiError> {
+        let url = format!(
+            "{}/projects/{}/wikis/{}",
+            self.get_base_api_url(),
+            encode_path_segment(project_id),
+            encode_path_segment(slug),
+        );
 
-        let mut current_page = 1;
+        match self.get(&url).await {
+            Ok(page) => Ok(Some(page)),
+            Err(err) if err.is_not_found() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
 
-        loop {
-            let new_members = self
-                .get_group_members(group_id, current_page, page_size)
-                .await?;
+    pub async fn create_wiki_page(
+        &self,
+        project_id: &str,
+        page: &NewWikiPage,
+    ) -> Result<WikiPage, GitlabApiError> {
+        let url = format!(
+            "{}/projects/{}/wikis",
+            self.get_base_api_url(),
+            encode_path_segment(project_id),
+        );
+
+        self.post(&url, page).await
+    }
 
-            current_page += 1;
+    pub async fn update_wiki_page(
+        &self,
+        project_id: &str,
+        slug: &str,
+        page: &NewWikiPage,
+    ) -> Result<WikiPage, GitlabApiError> {
+        let url = format!(
+            "{}/projects/{}/wikis/{}",
+            self.get_base_api_url(),
+            encode_path_segment(project_id),
+            encode_path_segment(slug),
+        );
 
-            let new_members_count = new_members.len();
+        self.put(&url, page).await
+    }
 
-            members.extend(new_members);
+    /// Creates the page in the wiki of `project_id`, or replaces its content
+    /// when a page with the same title already exists.
+    pub async fn publish_wiki_page(
+        &self,
+        project_id: &str,
+        page: &NewWikiPage,
+    ) -> Result<WikiPage, GitlabApiError> {
+        // gitlab derives slugs from titles by replacing spaces with dashes
+        let slug = page.title.replace(' ', "-");
 
-            if new_members_count < page_size {
-                break;
+        match self.get_wiki_page(project_id, &slug).await? {
+            Some(existing_page) => {
+                self.update_wiki_page(project_id, &existing_page.slug, page)
+                    .await
             }
+            None => self.create_wiki_page(project_id, page).await,
         }
+    }
+
+    pub async fn get_user(
+        &self,
+        user_id: usize,
+    ) -> Result<Member, GitlabApiError> {
+        let url = format!("{}/users/{user_id}", self.get_base_api_url());
+
+        self.get(&url).await
+    }
+
+    /// `email` is only returned to administrators, everyone else only sees
+    /// the public email users chose to show on their profile.
+    pub async fn get_user_email(
+        &self,
+        user_id: usize,
+    ) -> Result<Option<String>, GitlabApiError> {
+        let url = format!("{}/users/{user_id}", self.get_base_api_url());
+
+        let user: UserEmails = self.get(&url).await?;
 
-        Ok(members)
+        Ok(user
+            .email
+            .into_iter()
+            .chain(user.public_email)
+            .find(|email| !email.trim().is_empty()))
     }
 
     pub async fn get_user_by_access_token(
         &self,
         access_token: &AccessToken,
-    ) -> Result<Member> {
-        let url = format!(
-            "{}/user?access_token={}",
-            self.get_base_api_url(),
-            access_token.secret()
-        );
+    ) -> Result<Member, GitlabApiError> {
+        let url = format!("{}/user", self.get_base_api_url());
 
-        let member =
-            self.http_client.get(url).send().await?.json::<Member>().await?;
+        let response = self
+            .send(true, || {
+                self.http_client.get(&url).bearer_auth(access_token.secret())
+            })
+            .await?;
 
-        Ok(member)
+        Self::decode(response).await
     }
 
     pub async fn authorize_user_by_access_code(
@@ -128,12 +499,70 @@ impl GitlabApi {
     }
 }
 
+fn is_same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    url::form_urlencoded::byte_serialize(segment.as_bytes()).collect()
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NewIssue {
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Serialize, Deserialize, Eq, Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub id: u64,
+    pub iid: u64,
+    pub project_id: u64,
+    pub title: String,
+    pub state: String,
+    pub web_url: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NewWikiPage {
+    pub title: String,
+    pub content: String,
+    pub format: String,
+}
+
+#[derive(Serialize, Deserialize, Eq, Debug, Clone, PartialEq)]
+pub struct WikiPage {
+    pub slug: String,
+    pub title: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct UserEmails {
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    public_email: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Eq, Debug, Clone, PartialEq)]
 pub struct Member {
     pub id: usize,
     pub username: String,
     pub name: String,
     pub avatar_url: String,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub bot: bool,
+    #[serde(default)]
+    pub access_level: Option<u32>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Set on placeholders of members who left the directory.
+    #[serde(default)]
+    pub is_former_member: bool,
 }
 
 impl Member {
@@ -143,6 +572,770 @@ impl Member {
             username: "default".to_string(),
             name: "Default Test".to_string(),
             avatar_url: "/static/images/user-avatar.png".to_string(),
+            state: None,
+            bot: false,
+            access_level: None,
+            expires_at: None,
+            is_former_member: false,
         }
     }
+
+    /// Fake members seeded in development so multi-user flows can be tried
+    /// without a gitlab instance.
+    pub fn development_members() -> Vec<Member> {
+        let mut members = vec![Member::default()];
+
+        let fake_members = [
+            (1, "alice", "Alice Anderson"),
+            (2, "bob", "Bob Brown"),
+            (3, "carol", "Carol Clark"),
+            (4, "dave", "Dave Davis"),
+        ];
+
+        members.extend(fake_members.into_iter().map(
+            |(id, username, name)| Member {
+                id,
+                username: username.to_string(),
+                name: name.to_string(),
+                ..Member::default()
+            },
+        ));
+
+        members
+    }
+
+    pub fn has_same_profile(&self, other: &Member) -> bool {
+        self.id == other.id
+            && self.username == other.username
+            && self.name == other.name
+            && self.avatar_url == other.avatar_url
+    }
+
+    /// Whether the fields deciding `is_eligible` are the same.
+    pub fn has_same_membership(&self, other: &Member) -> bool {
+        self.state == other.state
+            && self.bot == other.bot
+            && self.access_level == other.access_level
+            && self.expires_at == other.expires_at
+    }
+
+    /// Blocked, bot and expired members, as well as those below
+    /// `min_access_level`, are not allowed in.
+    pub fn is_eligible(
+        &self,
+        min_access_level: u32,
+        today: NaiveDate,
+    ) -> bool {
+        let is_active =
+            self.state.as_deref().is_none_or(|state| state == "active");
+
+        let is_expired = self
+            .expires_at
+            .as_deref()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .is_some_and(|expires_at| expires_at < today);
+
+        let has_access_level = min_access_level == 0
+            || self
+                .access_level
+                .is_some_and(|access_level| access_level >= min_access_level);
+
+        is_active && !self.bot && !is_expired && has_access_level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, LINK, RETRY_AFTER};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+
+        headers
+    }
+
+    fn base_url() -> Url {
+        Url::parse("https://gitlab.example.com").unwrap()
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+    }
+
+    fn active_member() -> Member {
+        Member {
+            id: 7,
+            username: "alice".to_string(),
+            state: Some("active".to_string()),
+            access_level: Some(30),
+            expires_at: Some("2024-03-04".to_string()),
+            ..Member::default()
+        }
+    }
+
+    #[test]
+    fn is_eligible_accepts_active_members() {
+        assert!(active_member().is_eligible(30, today()));
+        assert!(
+            Member { state: None, ..active_member() }.is_eligible(30, today())
+        );
+    }
+
+    #[test]
+    fn is_eligible_refuses_blocked_bots_and_expired_members() {
+        let blocked =
+            Member { state: Some("blocked".to_string()), ..active_member() };
+        let bot = Member { bot: true, ..active_member() };
+        let expired = Member {
+            expires_at: Some("2024-03-03".to_string()),
+            ..active_member()
+        };
+
+        assert!(!blocked.is_eligible(0, today()));
+        assert!(!bot.is_eligible(0, today()));
+        assert!(!expired.is_eligible(0, today()));
+    }
+
+    #[test]
+    fn is_eligible_checks_the_access_level() {
+        let unknown = Member { access_level: None, ..active_member() };
+
+        assert!(!active_member().is_eligible(40, today()));
+        assert!(!unknown.is_eligible(10, today()));
+        assert!(unknown.is_eligible(0, today()));
+    }
+
+    #[test]
+    fn is_eligible_ignores_unparsable_expiry_dates() {
+        let member =
+            Member { expires_at: Some("soon".to_string()), ..active_member() };
+
+        assert!(member.is_eligible(30, today()));
+    }
+
+    #[test]
+    fn parse_retry_after_reads_seconds() {
+        let headers = headers(&[("retry-after", " 7 ")]);
+
+        assert_eq!(
+            GitlabApi::parse_retry_after(&headers),
+            Some(Duration::from_secs(7))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_is_capped() {
+        let headers = headers(&[("retry-after", "86400")]);
+
+        assert_eq!(
+            GitlabApi::parse_retry_after(&headers),
+            Some(MAX_RETRY_DELAY)
+        );
+    }
+
+    #[test]
+    fn parse_retry_after_ignores_missing_and_invalid_values() {
+        assert_eq!(GitlabApi::parse_retry_after(&HeaderMap::new()), None);
+
+        let mut headers = HeaderMap::new();
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+
+        assert_eq!(GitlabApi::parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn get_retry_delay_doubles_up_to_the_limit() {
+        assert_eq!(GitlabApi::get_retry_delay(0), Duration::from_millis(500));
+        assert_eq!(GitlabApi::get_retry_delay(1), Duration::from_secs(1));
+        assert_eq!(GitlabApi::get_retry_delay(3), Duration::from_secs(4));
+        assert_eq!(GitlabApi::get_retry_delay(10), Duration::from_secs(30));
+        assert_eq!(GitlabApi::get_retry_delay(100), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn get_next_page_url_replaces_the_page_parameter() {
+        let url = Url::parse(
+            "https://gitlab.example.com/api/v4/groups/1/members/all?per_page=100&page=2",
+        )
+        .unwrap();
+
+        let headers = headers(&[("x-next-page", "3")]);
+
+        assert_eq!(
+            GitlabApi::get_next_page_url(&base_url(), &url, &headers).as_deref(),
+            Some(
+                "https://gitlab.example.com/api/v4/groups/1/members/all?per_page=100&page=3"
+            )
+        );
+    }
+
+    #[test]
+    fn get_next_page_url_follows_the_next_link() {
+        let url =
+            Url::parse("https://gitlab.example.com/api/v4/projects").unwrap();
+
+        let mut headers = headers(&[("x-next-page", "")]);
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                "<https://gitlab.example.com/api/v4/projects?id_after=42>; rel=\"next\", <https://gitlab.example.com/api/v4/projects>; rel=\"first\"",
+            ),
+        );
+
+        assert_eq!(
+            GitlabApi::get_next_page_url(&base_url(), &url, &headers)
+                .as_deref(),
+            Some("https://gitlab.example.com/api/v4/projects?id_after=42")
+        );
+    }
+
+    #[test]
+    fn get_next_page_url_stops_on_the_last_page() {
+        let url =
+            Url::parse("https://gitlab.example.com/api/v4/projects?page=4")
+                .unwrap();
+
+        let mut headers = headers(&[("x-next-page", "")]);
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                "<https://gitlab.example.com/api/v4/projects?page=1>; rel=\"first\"",
+            ),
+        );
+
+        assert_eq!(
+            GitlabApi::get_next_page_url(&base_url(), &url, &headers),
+            None
+        );
+        assert_eq!(
+            GitlabApi::get_next_page_url(&base_url(), &url, &HeaderMap::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn get_next_page_url_does_not_leave_the_gitlab_origin() {
+        let url =
+            Url::parse("https://gitlab.example.com/api/v4/projects").unwrap();
+
+        let links = [
+            "<https://attacker.example.com/api/v4/projects?id_after=42>; rel=\"next\"",
+            "<http://gitlab.example.com/api/v4/projects?id_after=42>; rel=\"next\"",
+            "<https://gitlab.example.com:8443/api/v4/projects?id_after=42>; rel=\"next\"",
+            "<https://gitlab.example.com.attacker.example/api/v4/projects>; rel=\"next\"",
+            "</api/v4/projects?id_after=42>; rel=\"next\"",
+        ];
+
+        for link in links {
+            let mut headers = HeaderMap::new();
+
+            headers.insert(LINK, HeaderValue::from_static(link));
+
+            assert_eq!(
+                GitlabApi::get_next_page_url(&base_url(), &url, &headers),
+                None,
+                "{link} should not be followed"
+            );
+        }
+    }
+
+    #[test]
+    fn get_next_page_url_follows_links_on_the_default_port() {
+        let url =
+            Url::parse("https://gitlab.example.com/api/v4/projects").unwrap();
+
+        let headers = headers(&[(
+            "link",
+            "<https://gitlab.example.com:443/api/v4/projects?id_after=42>; rel=\"next\"",
+        )]);
+
+        assert_eq!(
+            GitlabApi::get_next_page_url(&base_url(), &url, &headers)
+                .as_deref(),
+            Some("https://gitlab.example.com/api/v4/projects?id_after=42")
+        );
+    }
+
+    mod mock_server {
+        use std::{
+            collections::VecDeque,
+            sync::{Arc, Mutex},
+        };
+
+        use axum::{
+            body::Body,
+            extract::State,
+            http::{HeaderMap, Method, Uri},
+            response::Response,
+            Router,
+        };
+
+        use super::super::*;
+
+        pub struct MockResponse {
+            status: u16,
+            headers: Vec<(&'static str, String)>,
+            body: String,
+        }
+
+        impl MockResponse {
+            pub fn new(status: u16, body: &str) -> Self {
+                Self { status, headers: Vec::new(), body: body.to_string() }
+            }
+
+            pub fn header(mut self, name: &'static str, value: &str) -> Self {
+                self.headers.push((name, value.to_string()));
+                self
+            }
+        }
+
+        #[derive(Clone, Debug)]
+        pub struct ReceivedRequest {
+            pub method: Method,
+            pub uri: Uri,
+            pub headers: HeaderMap,
+        }
+
+        #[derive(Clone, Default)]
+        struct MockState {
+            responses: Arc<Mutex<VecDeque<MockResponse>>>,
+            requests: Arc<Mutex<Vec<ReceivedRequest>>>,
+        }
+
+        /// A local http server answering requests with `responses` in
+        /// order, 500 once they run out.
+        pub struct MockGitlab {
+            pub url: String,
+            state: MockState,
+        }
+
+        impl MockGitlab {
+            pub async fn start(responses: Vec<MockResponse>) -> Self {
+                let state = MockState {
+                    responses: Arc::new(Mutex::new(responses.into())),
+                    ..MockState::default()
+                };
+
+                let router =
+                    Router::new().fallback(respond).with_state(state.clone());
+
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                    .await
+                    .unwrap();
+
+                let url = format!("http://{}", listener.local_addr().unwrap());
+
+                tokio::spawn(async move {
+                    axum::serve(listener, router).await.unwrap()
+                });
+
+                Self { url, state }
+            }
+
+            pub fn state_push(&self, responses: Vec<MockResponse>) {
+                self.state.responses.lock().unwrap().extend(responses);
+            }
+
+            pub fn get_requests(&self) -> Vec<ReceivedRequest> {
+                self.state.requests.lock().unwrap().clone()
+            }
+
+            pub fn api(&self, max_retries: u32) -> GitlabApi {
+                get_api(&self.url, max_retries)
+            }
+        }
+
+        pub fn get_api(url: &str, max_retries: u32) -> GitlabApi {
+            GitlabApi::new(&GitlabConfiguration {
+                domain: "localhost".to_string(),
+                url: url.to_string(),
+                access_token: "service token".to_string(),
+                group_ids: vec!["1".to_string()],
+                min_access_level: 0,
+                client_id: "client id".to_string(),
+                client_secret: "client secret".to_string(),
+                redirect_url: "http://localhost/auth/callback".to_string(),
+                webhook_secret: None,
+                issues_project_id: None,
+                archive_topics_on_issue_close: false,
+                wiki_project_id: None,
+                wiki_export_interval: None,
+                api_timeout: Duration::from_secs(5),
+                api_max_retries: max_retries,
+                max_removed_members_percent: 50,
+            })
+        }
+
+        async fn respond(
+            State(state): State<MockState>,
+            method: Method,
+            uri: Uri,
+            headers: HeaderMap,
+        ) -> Response {
+            state.requests.lock().unwrap().push(ReceivedRequest {
+                method,
+                uri,
+                headers,
+            });
+
+            let response =
+                state.responses.lock().unwrap().pop_front().unwrap_or_else(
+                    || MockResponse::new(500, "no response left"),
+                );
+
+            let mut builder = Response::builder()
+                .status(response.status)
+                .header("content-type", "application/json");
+
+            for (name, value) in response.headers {
+                builder = builder.header(name, value);
+            }
+
+            builder.body(Body::from(response.body)).unwrap()
+        }
+    }
+
+    use mock_server::{get_api, MockGitlab, MockResponse};
+
+    const USER: &str = r#"{"id": 7, "username": "alice", "name": "Alice Anderson", "avatar_url": ""}"#;
+    const ISSUE: &str = r#"{"id": 100, "iid": 3, "project_id": 5, "title": "Rust", "state": "opened", "web_url": "https://gitlab.example.com/group/project/-/issues/3"}"#;
+
+    fn new_issue() -> NewIssue {
+        NewIssue {
+            title: "Rust".to_string(),
+            description: "Topic".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_requests_are_sent_with_the_token() {
+        let gitlab =
+            MockGitlab::start(vec![MockResponse::new(200, USER)]).await;
+
+        let user = gitlab.api(0).get_user(7).await.unwrap();
+
+        let requests = gitlab.get_requests();
+
+        assert_eq!(user.username, "alice");
+        assert_eq!(requests[0].uri.path(), "/api/v4/users/7");
+        assert_eq!(requests[0].headers["private-token"], "service token");
+    }
+
+    #[tokio::test]
+    async fn get_requests_are_retried_on_server_errors() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(503, "unavailable"),
+            MockResponse::new(200, USER),
+        ])
+        .await;
+
+        let user = gitlab.api(2).get_user(7).await.unwrap();
+
+        assert_eq!(user.id, 7);
+        assert_eq!(gitlab.get_requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_requests_wait_as_long_as_retry_after_asks() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(429, "slow down").header("retry-after", "0"),
+            MockResponse::new(200, USER),
+        ])
+        .await;
+
+        let started_at = std::time::Instant::now();
+
+        gitlab.api(2).get_user(7).await.unwrap();
+
+        // the backoff of the first retry would be 500ms
+        assert!(started_at.elapsed() < Duration::from_millis(400));
+        assert_eq!(gitlab.get_requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_after_max_retries() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(429, "slow down").header("retry-after", "0"),
+            MockResponse::new(429, "slow down").header("retry-after", "0"),
+            MockResponse::new(200, USER),
+        ])
+        .await;
+
+        let err = gitlab.api(1).get_user(7).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            GitlabApiError::RateLimited { retry_after: Some(delay), .. }
+                if delay == Duration::ZERO
+        ));
+        assert_eq!(gitlab.get_requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let gitlab =
+            MockGitlab::start(vec![MockResponse::new(403, "forbidden")]).await;
+
+        let err = gitlab.api(3).get_user(7).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            GitlabApiError::Status { status: StatusCode::FORBIDDEN, ref body, .. }
+                if body == "forbidden"
+        ));
+        assert!(!err.is_retryable());
+        assert_eq!(gitlab.get_requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn post_requests_are_not_retried_on_server_errors() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(502, "bad gateway"),
+            MockResponse::new(201, ISSUE),
+        ])
+        .await;
+
+        let err = gitlab
+            .api(3)
+            .create_issue("group/project", &new_issue())
+            .await
+            .unwrap_err();
+
+        let requests = gitlab.get_requests();
+
+        // gitlab may have created the issue before failing
+        assert!(matches!(
+            err,
+            GitlabApiError::Status { status: StatusCode::BAD_GATEWAY, .. }
+        ));
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, reqwest::Method::POST);
+        assert_eq!(
+            requests[0].uri.path(),
+            "/api/v4/projects/group%2Fproject/issues"
+        );
+    }
+
+    #[tokio::test]
+    async fn post_requests_are_retried_when_rate_limited() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(429, "slow down").header("retry-after", "0"),
+            MockResponse::new(201, ISSUE),
+        ])
+        .await;
+
+        let issue =
+            gitlab.api(3).create_issue("5", &new_issue()).await.unwrap();
+
+        assert_eq!(issue.iid, 3);
+        assert_eq!(gitlab.get_requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_rejected_before_processing() {
+        // a port nothing listens on anymore
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        drop(listener);
+
+        let err = get_api(&url, 0)
+            .create_issue("5", &new_issue())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, GitlabApiError::Request(_)));
+        assert!(err.is_retryable());
+        assert!(err.is_rejected_before_processing());
+    }
+
+    #[tokio::test]
+    async fn missing_members_are_none() {
+        let gitlab = MockGitlab::start(vec![MockResponse::new(
+            404,
+            r#"{"message": "404 Not found"}"#,
+        )])
+        .await;
+
+        let member = gitlab.api(3).get_group_member("1", 7).await.unwrap();
+
+        assert_eq!(member, None);
+        assert_eq!(gitlab.get_requests().len(), 1);
+    }
+
+    const WIKI_PAGE: &str =
+        r#"{"slug": "Guilds/Rust-(1)/Agenda", "title": "Agenda"}"#;
+
+    fn new_wiki_page() -> NewWikiPage {
+        NewWikiPage {
+            title: "Guilds/Rust (1)/Agenda".to_string(),
+            content: "# Rust agenda".to_string(),
+            format: "markdown".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_wiki_pages_are_none() {
+        let gitlab = MockGitlab::start(vec![MockResponse::new(
+            404,
+            r#"{"message": "404 Wiki Page Not Found"}"#,
+        )])
+        .await;
+
+        let page = gitlab
+            .api(3)
+            .get_wiki_page("group/project", "Guilds/Rust-(1)/Agenda")
+            .await
+            .unwrap();
+
+        let requests = gitlab.get_requests();
+
+        assert_eq!(page, None);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].uri.path(),
+            "/api/v4/projects/group%2Fproject/wikis/Guilds%2FRust-%281%29%2FAgenda"
+        );
+    }
+
+    #[tokio::test]
+    async fn new_wiki_pages_are_created() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(
+                404,
+                r#"{"message": "404 Wiki Page Not Found"}"#,
+            ),
+            MockResponse::new(201, WIKI_PAGE),
+        ])
+        .await;
+
+        let page = gitlab
+            .api(3)
+            .publish_wiki_page("5", &new_wiki_page())
+            .await
+            .unwrap();
+
+        let requests = gitlab.get_requests();
+
+        assert_eq!(page.slug, "Guilds/Rust-(1)/Agenda");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, reqwest::Method::GET);
+        assert_eq!(requests[1].method, reqwest::Method::POST);
+        assert_eq!(requests[1].uri.path(), "/api/v4/projects/5/wikis");
+    }
+
+    #[tokio::test]
+    async fn existing_wiki_pages_are_updated() {
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(200, WIKI_PAGE),
+            MockResponse::new(200, WIKI_PAGE),
+        ])
+        .await;
+
+        gitlab.api(3).publish_wiki_page("5", &new_wiki_page()).await.unwrap();
+
+        let requests = gitlab.get_requests();
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].method, reqwest::Method::PUT);
+        assert_eq!(
+            requests[1].uri.path(),
+            "/api/v4/projects/5/wikis/Guilds%2FRust-%281%29%2FAgenda"
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_wiki_page_lookups_are_not_mistaken_for_missing_pages() {
+        let gitlab =
+            MockGitlab::start(vec![MockResponse::new(403, "forbidden")]).await;
+
+        let err = gitlab
+            .api(3)
+            .publish_wiki_page("5", &new_wiki_page())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            GitlabApiError::Status { status: StatusCode::FORBIDDEN, .. }
+        ));
+        assert_eq!(gitlab.get_requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unexpected_bodies_are_decode_errors() {
+        let gitlab =
+            MockGitlab::start(vec![MockResponse::new(200, "<html>")]).await;
+
+        let err = gitlab.api(3).get_user(7).await.unwrap_err();
+
+        assert!(matches!(err, GitlabApiError::Decode { .. }));
+        assert!(!err.is_retryable());
+        assert_eq!(gitlab.get_requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn all_pages_are_fetched() {
+        let first_page = format!("[{USER}]");
+        let second_page = r#"[{"id": 8, "username": "bob", "name": "Bob Brown", "avatar_url": ""}]"#;
+
+        let gitlab = MockGitlab::start(vec![
+            MockResponse::new(200, &first_page).header("x-next-page", "2"),
+            MockResponse::new(200, second_page).header("x-next-page", ""),
+        ])
+        .await;
+
+        let members = gitlab.api(0).get_all_group_members("1").await.unwrap();
+
+        let requests = gitlab.get_requests();
+
+        assert_eq!(
+            members.iter().map(|member| member.id).collect::<Vec<_>>(),
+            vec![7, 8]
+        );
+        assert_eq!(requests[1].uri.query(), Some("per_page=100&page=2"));
+    }
+
+    #[tokio::test]
+    async fn next_links_to_other_hosts_are_not_followed() {
+        let first_page = format!("[{USER}]");
+
+        let gitlab = MockGitlab::start(vec![]).await;
+
+        // the mock answers in order, so the link of the first page is set
+        // once its url is known
+        gitlab.state_push(vec![
+            MockResponse::new(200, &first_page).header(
+                "link",
+                &format!(
+                    "<{}/api/v4/groups/1/members/all?id_after=7>; rel=\"next\"",
+                    gitlab.url
+                ),
+            ),
+            MockResponse::new(200, &first_page).header(
+                "link",
+                "<http://attacker.example.com/api/v4/groups/1/members/all?id_after=8>; rel=\"next\"",
+            ),
+        ]);
+
+        let members = gitlab.api(0).get_all_group_members("1").await.unwrap();
+
+        let requests = gitlab.get_requests();
+
+        assert_eq!(members.len(), 2);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].uri.query(), Some("id_after=7"));
+    }
 }
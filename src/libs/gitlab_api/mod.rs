@@ -1,3 +1,5 @@
+pub mod errors;
 pub mod gitlab_api;
 
+pub use errors::GitlabApiError;
 pub use gitlab_api::GitlabApi;
@@ -12,6 +12,12 @@ pub trait HealthChecker {
 
     fn get_health(&self) -> bool;
 
+    /// Whether the app can't serve requests while the dependency is down.
+    /// Optional dependencies are only reported.
+    fn is_required(&self) -> bool {
+        true
+    }
+
     fn persist_new_health_status(&self, is_healthy: bool);
 
     async fn check_is_healthy(&self) -> Result<()>;
@@ -49,7 +55,11 @@ pub trait HealthChecker {
     }
 
     fn to_dependency(&self) -> Dependency {
-        Dependency { name: self.get_service_name(), up: self.get_health() }
+        Dependency {
+            name: self.get_service_name(),
+            up: self.get_health(),
+            required: self.is_required(),
+        }
     }
 }
 
@@ -57,4 +67,5 @@ pub trait HealthChecker {
 pub struct Dependency {
     pub name: String,
     pub up: bool,
+    pub required: bool,
 }
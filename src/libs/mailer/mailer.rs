@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{
+    configuration::{MailConfiguration, MailTls},
+    libs::health_checker::HealthChecker,
+};
+
+/// Sends emails over SMTP, with an HTML body and a plain text alternative.
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    app_url: String,
+    is_healthy: AtomicBool,
+}
+
+impl Mailer {
+    pub fn new(
+        configuration: &MailConfiguration,
+        app_url: &str,
+    ) -> Result<Self> {
+        let mut builder = match configuration.tls {
+            MailTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                    &configuration.host,
+                )
+            }
+            MailTls::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(
+                    &configuration.host,
+                )?
+            }
+            MailTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(
+                &configuration.host,
+            )?,
+        }
+        .port(configuration.port);
+
+        if let (Some(username), Some(password)) =
+            (&configuration.username, &configuration.password)
+        {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.clone(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: configuration
+                .from
+                .parse()
+                .with_context(|| "SMTP_FROM is not a valid mailbox")?,
+            app_url: app_url.to_string(),
+            is_healthy: AtomicBool::new(false),
+        })
+    }
+
+    /// Absolute url of a path of the app, for links in emails.
+    pub fn get_url(&self, path: &str) -> String {
+        format!("{}{path}", self.app_url)
+    }
+
+    pub async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        html_body: String,
+        text_body: String,
+    ) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to
+                .parse()
+                .with_context(|| format!("Invalid email address {to}"))?)
+            .subject(subject)
+            .multipart(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_PLAIN)
+                            .body(text_body),
+                    )
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_HTML)
+                            .body(html_body),
+                    ),
+            )?;
+
+        self.transport
+            .send(message)
+            .await
+            .with_context(|| format!("Failed to send email to {to}"))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HealthChecker for Mailer {
+    fn get_service_name(&self) -> String {
+        "SMTP".to_string()
+    }
+
+    fn get_health(&self) -> bool {
+        self.is_healthy.load(Ordering::Relaxed)
+    }
+
+    /// Emails are sent in the background, nothing else depends on them.
+    fn is_required(&self) -> bool {
+        false
+    }
+
+    fn persist_new_health_status(&self, is_healthy: bool) {
+        self.is_healthy.store(is_healthy, Ordering::Relaxed);
+    }
+
+    async fn check_is_healthy(&self) -> Result<()> {
+        let is_connected = self
+            .transport
+            .test_connection()
+            .await
+            .context("Failed to connect to the SMTP server")?;
+
+        if !is_connected {
+            bail!("The SMTP server refused the connection")
+        }
+
+        Ok(())
+    }
+}
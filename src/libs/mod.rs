@@ -2,6 +2,7 @@ pub mod axum;
 pub mod gitlab_api;
 pub mod health_checker;
 pub mod htmx;
+pub mod mailer;
 pub mod migration;
 pub mod mongo;
 pub mod serialization;
@@ -57,3 +57,35 @@ pub mod chrono_date {
         Ok(utc_datetime)
     }
 }
+
+pub mod chrono_date_option {
+    use chrono::{DateTime, Utc};
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(
+        date: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => super::chrono_date::serialize(date, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::chrono_date")] DateTime<Utc>);
+
+        let wrapper = Option::<Wrapper>::deserialize(deserializer)?;
+
+        Ok(wrapper.map(|Wrapper(date)| date))
+    }
+}
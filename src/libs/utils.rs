@@ -29,3 +29,48 @@ where
 
     return Ok(original_value);
 }
+
+/// Compares secrets without leaking where they differ through timing.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Slack and Mattermost treat `&`, `<` and `>` as control characters.
+pub fn escape_slack_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// `<url|label>` link with an escaped label. A pipe in the label is swapped
+/// for a broken bar, clients disagree on which pipe ends the url.
+pub fn format_slack_link(url: &str, label: &str) -> String {
+    format!("<{url}|{}>", escape_slack_text(label).replace('|', "¦"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slack_control_characters_are_escaped() {
+        assert_eq!(
+            escape_slack_text("<!channel> & <@U123>"),
+            "&lt;!channel&gt; &amp; &lt;@U123&gt;"
+        );
+        assert_eq!(escape_slack_text("Rust | Go"), "Rust | Go");
+    }
+
+    #[test]
+    fn slack_link_labels_cant_break_the_link() {
+        assert_eq!(
+            format_slack_link(
+                "https://guildhub.example.com/guilds/1",
+                "A|B>C"
+            ),
+            "<https://guildhub.example.com/guilds/1|A¦B&gt;C>"
+        );
+    }
+}
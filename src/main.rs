@@ -9,7 +9,7 @@ pub mod modules;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let app: Arc<App> = Arc::new(App::new().await);
+    let app: Arc<App> = Arc::new(App::new().await?);
 
     app.run_migrations().await?;
 
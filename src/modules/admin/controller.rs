@@ -0,0 +1,186 @@
+use crate::modules::{
+    app::{
+        app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
+        ToastLevel,
+    },
+    guild::GuildIdParameter,
+    topic::{
+        constants::TOPICS_LIMIT,
+        types::{PaginationParameters, TopicStatus},
+    },
+};
+use askama_axum::IntoResponse;
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue},
+    Form,
+};
+use std::sync::Arc;
+
+use super::{
+    AdminArchivedTopicsTemplate, AdminDependenciesTemplate,
+    AdminGuildsTemplate, AdminTemplate, AdminTopicParameters,
+    ReassignOwnerFormDTO,
+};
+
+fn toast_headers(
+    level: ToastLevel,
+    message: &str,
+) -> Result<HeaderMap, AppError> {
+    let event =
+        HxTriggerEvent::ShowToast { level, message: message.to_string() };
+
+    let event = event.to_header_value()?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(mime::TEXT_HTML.as_ref())?,
+    );
+    headers.insert("HX-Trigger", event);
+
+    Ok(headers)
+}
+
+pub async fn get_admin_page(
+    Authenticated(user): Authenticated,
+) -> impl IntoResponse {
+    AdminTemplate { user }
+}
+
+pub async fn get_dependencies(
+    State(app): State<Arc<App>>,
+) -> impl IntoResponse {
+    AdminDependenciesTemplate {
+        dependencies: app
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.to_dependency())
+            .collect(),
+    }
+}
+
+pub async fn get_guilds_list(
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guilds = app.guilds_service.get_all_guilds().await?;
+
+    let mut members = app.member_directory.get_all_members().await;
+
+    members.sort_by(|a, b| a.username.cmp(&b.username));
+
+    Ok(AdminGuildsTemplate { guilds, members })
+}
+
+pub async fn reassign_guild_owner(
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildIdParameter>,
+    Form(form): Form<ReassignOwnerFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .reassign_owner(&parameters.guild_id, form.member_id)
+        .await?;
+
+    let headers = toast_headers(
+        ToastLevel::Info,
+        &format!(
+            "@{} now owns {}",
+            guild.created_by_user.username,
+            guild.name.trim()
+        ),
+    )?;
+
+    Ok(headers)
+}
+
+pub async fn delete_guild(
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildIdParameter>,
+) -> Result<impl IntoResponse, AppError> {
+    app.guilds_service.delete_guild(&parameters.guild_id, None).await?;
+
+    toast_headers(ToastLevel::Info, "Guild was deleted")
+}
+
+pub async fn get_archived_topics(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildIdParameter>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = match app
+        .guilds_service
+        .get_guild_by_id(&parameters.guild_id)
+        .await?
+    {
+        Some(guild) => guild,
+        None => {
+            return Err(AppError::NotFound("Failed to find guild".to_string()))
+        }
+    };
+
+    let topics = app
+        .topics_service
+        .get_topics_by_guild_id(
+            user.id,
+            &parameters.guild_id,
+            PaginationParameters { skip: 0, limit: TOPICS_LIMIT },
+            guild,
+            TopicStatus::Archived,
+        )
+        .await?;
+
+    Ok(AdminArchivedTopicsTemplate { guild_id: parameters.guild_id, topics })
+}
+
+pub async fn restore_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<AdminTopicParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = match app
+        .guilds_service
+        .get_guild_by_id(&parameters.guild_id)
+        .await?
+    {
+        Some(guild) => guild,
+        None => {
+            return Err(AppError::NotFound("Failed to find guild".to_string()))
+        }
+    };
+
+    app.topics_service
+        .change_topic_status(
+            &parameters.topic_id,
+            user.id,
+            TopicStatus::Created,
+            &guild,
+        )
+        .await?;
+
+    toast_headers(ToastLevel::Info, "Topic was restored")
+}
+
+pub async fn delete_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<AdminTopicParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = match app
+        .guilds_service
+        .get_guild_by_id(&parameters.guild_id)
+        .await?
+    {
+        Some(guild) => guild,
+        None => {
+            return Err(AppError::NotFound("Failed to find guild".to_string()))
+        }
+    };
+
+    app.topics_service
+        .delete_topic_as_admin(&parameters.topic_id, user.id, &guild)
+        .await?;
+
+    toast_headers(ToastLevel::Info, "Topic was deleted")
+}
@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod types;
+
+pub use controller::*;
+pub use types::*;
@@ -0,0 +1,44 @@
+use askama_axum::Template;
+use serde::Deserialize;
+
+use crate::{
+    libs::{gitlab_api::gitlab_api::Member, health_checker::Dependency},
+    modules::{guild::Guild, topic::types::TopicPersonalized},
+};
+
+#[derive(Template)]
+#[template(path = "pages/admin/admin.html")]
+pub struct AdminTemplate {
+    pub user: Member,
+}
+
+#[derive(Template)]
+#[template(path = "components/admin/admin-dependencies.html")]
+pub struct AdminDependenciesTemplate {
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Template)]
+#[template(path = "components/admin/admin-guilds.html")]
+pub struct AdminGuildsTemplate {
+    pub guilds: Vec<Guild>,
+    pub members: Vec<Member>,
+}
+
+#[derive(Template)]
+#[template(path = "components/admin/admin-archived-topics.html")]
+pub struct AdminArchivedTopicsTemplate {
+    pub guild_id: String,
+    pub topics: Vec<TopicPersonalized>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReassignOwnerFormDTO {
+    pub member_id: usize,
+}
+
+#[derive(Deserialize)]
+pub struct AdminTopicParameters {
+    pub guild_id: String,
+    pub topic_id: String,
+}
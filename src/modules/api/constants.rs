@@ -0,0 +1,2 @@
+pub const API_DEFAULT_PAGE_LIMIT: usize = 20;
+pub const API_MAX_PAGE_LIMIT: usize = 100;
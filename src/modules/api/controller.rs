@@ -0,0 +1,504 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::WithRejection;
+use utoipa::OpenApi;
+use validator::Validate;
+
+use crate::{
+    libs::gitlab_api::gitlab_api::Member,
+    modules::{
+        app::{app::App, user_extractor::Authenticated},
+        guild::{Guild, GuildFormDTO},
+        topic::types::{TopicFormDTO, TopicPersonalized},
+    },
+};
+
+use super::{
+    openapi::ApiDoc, ApiError, ApiGuild, ApiGuildIdParameter, ApiGuildRequest,
+    ApiPage, ApiTopic, ApiTopicParameters, ApiTopicRequest,
+    ApiTopicStatusRequest, PageQuery, TopicsQuery,
+};
+
+type ApiJson<T> = WithRejection<Json<T>, ApiError>;
+type ApiQuery<T> = WithRejection<Query<T>, ApiError>;
+type ApiPath<T> = WithRejection<Path<T>, ApiError>;
+
+async fn get_member_guild(
+    app: &App,
+    user: &Member,
+    guild_id: &str,
+) -> Result<Guild, ApiError> {
+    app.guilds_service
+        .get_guild(user.clone(), guild_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Guild was not found"))
+}
+
+async fn get_owned_guild(
+    app: &App,
+    user: &Member,
+    guild_id: &str,
+) -> Result<Guild, ApiError> {
+    let guild = get_member_guild(app, user, guild_id).await?;
+
+    if guild.created_by_user.id != user.id {
+        return Err(ApiError::forbidden(
+            "Only the owner can manage the guild",
+        ));
+    }
+
+    Ok(guild)
+}
+
+async fn get_guild_topic(
+    app: &App,
+    user: &Member,
+    guild: &Guild,
+    topic_id: &str,
+) -> Result<TopicPersonalized, ApiError> {
+    match app.topics_service.get_topic(topic_id, user.id, guild).await? {
+        Some(topic) if topic.guild_id == guild.id => Ok(topic),
+        _ => Err(ApiError::not_found("Topic was not found")),
+    }
+}
+
+/// Guilds the authenticated member belongs to, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds",
+    tag = "guilds",
+    params(PageQuery),
+    responses(
+        (status = 200, body = GuildsPage),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_guilds(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Query(query), _): ApiQuery<PageQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = query.get_limit();
+
+    let guilds = app
+        .guilds_service
+        .get_guilds_page(user.id, query.get_before_id()?, limit + 1)
+        .await?;
+
+    let guilds: Vec<ApiGuild> =
+        guilds.into_iter().map(ApiGuild::from).collect();
+
+    Ok(Json(ApiPage::new(guilds, limit, |guild| &guild.id)?))
+}
+
+/// Creates a guild owned by the authenticated member.
+#[utoipa::path(
+    post,
+    path = "/api/v1/guilds",
+    tag = "guilds",
+    request_body = ApiGuildRequest,
+    responses(
+        (status = 201, body = ApiGuild),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 422, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_guild(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Json(request), _): ApiJson<ApiGuildRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let form = GuildFormDTO::from(request);
+
+    form.validate().map_err(ApiError::validation)?;
+
+    let guild = app.guilds_service.create_new_guild(form, user).await?;
+
+    Ok((StatusCode::CREATED, Json(ApiGuild::from(guild))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds/{guild_id}",
+    tag = "guilds",
+    params(("guild_id" = String, Path, description = "Id of the guild")),
+    responses(
+        (status = 200, body = ApiGuild),
+        (status = 401, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_guild(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiGuildIdParameter>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    Ok(Json(ApiGuild::from(guild)))
+}
+
+/// Renames the guild and replaces its members, only for the owner.
+#[utoipa::path(
+    put,
+    path = "/api/v1/guilds/{guild_id}",
+    tag = "guilds",
+    params(("guild_id" = String, Path, description = "Id of the guild")),
+    request_body = ApiGuildRequest,
+    responses(
+        (status = 200, body = ApiGuild),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 403, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+        (status = 422, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_guild(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiGuildIdParameter>,
+    WithRejection(Json(request), _): ApiJson<ApiGuildRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    let form = GuildFormDTO::from(request);
+
+    form.validate().map_err(ApiError::validation)?;
+
+    let guild = app.guilds_service.update_guild(guild.id, form, user).await?;
+
+    Ok(Json(ApiGuild::from(guild)))
+}
+
+/// Deletes the guild with its topics, only for the owner.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/guilds/{guild_id}",
+    tag = "guilds",
+    params(("guild_id" = String, Path, description = "Id of the guild")),
+    responses(
+        (status = 204, description = "Guild was deleted"),
+        (status = 401, body = ApiErrorBody),
+        (status = 403, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_guild(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiGuildIdParameter>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    app.guilds_service.delete_guild(&guild.id, Some(user.id)).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Topics of the guild, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds/{guild_id}/topics",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), TopicsQuery),
+    responses(
+        (status = 200, body = TopicsPage),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_topics(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiGuildIdParameter>,
+    WithRejection(Query(query), _): ApiQuery<TopicsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let page = query.get_page();
+    let limit = page.get_limit();
+
+    let topics = app
+        .topics_service
+        .get_topics_page(
+            user.id,
+            &guild,
+            query.status,
+            page.get_before_id()?,
+            limit + 1,
+        )
+        .await?;
+
+    let topics: Vec<ApiTopic> =
+        topics.into_iter().map(ApiTopic::from).collect();
+
+    Ok(Json(ApiPage::new(topics, limit, |topic| &topic.id)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/guilds/{guild_id}/topics",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild")),
+    request_body = ApiTopicRequest,
+    responses(
+        (status = 201, body = ApiTopic),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+        (status = 422, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiGuildIdParameter>,
+    WithRejection(Json(request), _): ApiJson<ApiTopicRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let form = TopicFormDTO::from(request);
+
+    form.validate().map_err(ApiError::validation)?;
+
+    let topic = app.topics_service.create_topic(form, &guild, user.id).await?;
+
+    Ok((StatusCode::CREATED, Json(ApiTopic::from(topic))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/guilds/{guild_id}/topics/{topic_id}",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), ("topic_id" = String, Path, description = "Id of the topic")),
+    responses(
+        (status = 200, body = ApiTopic),
+        (status = 401, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiTopicParameters>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let topic =
+        get_guild_topic(&app, &user, &guild, &parameters.topic_id).await?;
+
+    Ok(Json(ApiTopic::from(topic)))
+}
+
+/// Edits the topic and resets its votes, like editing it in the guild page.
+#[utoipa::path(
+    put,
+    path = "/api/v1/guilds/{guild_id}/topics/{topic_id}",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), ("topic_id" = String, Path, description = "Id of the topic")),
+    request_body = ApiTopicRequest,
+    responses(
+        (status = 200, body = ApiTopic),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 403, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+        (status = 422, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiTopicParameters>,
+    WithRejection(Json(request), _): ApiJson<ApiTopicRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let topic =
+        get_guild_topic(&app, &user, &guild, &parameters.topic_id).await?;
+
+    if !topic.can_edit {
+        return Err(ApiError::forbidden("You can not edit this topic"));
+    }
+
+    let form = TopicFormDTO::from(request);
+
+    form.validate().map_err(ApiError::validation)?;
+
+    let topic = app
+        .topics_service
+        .update_topic(form, &topic.id, user.id, &guild)
+        .await?;
+
+    Ok(Json(ApiTopic::from(topic)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/guilds/{guild_id}/topics/{topic_id}",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), ("topic_id" = String, Path, description = "Id of the topic")),
+    responses(
+        (status = 204, description = "Topic was deleted"),
+        (status = 401, body = ApiErrorBody),
+        (status = 403, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiTopicParameters>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let topic =
+        get_guild_topic(&app, &user, &guild, &parameters.topic_id).await?;
+
+    if !topic.can_delete {
+        return Err(ApiError::forbidden("You can not delete this topic"));
+    }
+
+    // the guild owner may delete topics of other members
+    match topic.created_by_user.id == user.id {
+        true => {
+            app.topics_service.delete_topic(&topic.id, user.id, &guild).await?
+        }
+        false => {
+            app.topics_service
+                .delete_topic_as_admin(&topic.id, user.id, &guild)
+                .await?
+        }
+    };
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Votes for the topic, moving the previous vote of the member in the guild.
+#[utoipa::path(
+    post,
+    path = "/api/v1/guilds/{guild_id}/topics/{topic_id}/vote",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), ("topic_id" = String, Path, description = "Id of the topic")),
+    responses(
+        (status = 200, body = ApiTopic),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn vote_for_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiTopicParameters>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let topic =
+        get_guild_topic(&app, &user, &guild, &parameters.topic_id).await?;
+
+    if topic.is_status_archived {
+        return Err(ApiError::bad_request("Archived topics can not be voted"));
+    }
+
+    let result =
+        app.topics_service.upvote_topic(&guild, topic.id, user.id).await?;
+
+    Ok(Json(ApiTopic::from(result.topic)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/guilds/{guild_id}/topics/{topic_id}/vote",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), ("topic_id" = String, Path, description = "Id of the topic")),
+    responses(
+        (status = 200, body = ApiTopic),
+        (status = 401, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_vote_from_topic(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiTopicParameters>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let topic =
+        get_guild_topic(&app, &user, &guild, &parameters.topic_id).await?;
+
+    let topic = app
+        .topics_service
+        .remove_vote_from_topic(&guild, &topic.id, user.id)
+        .await?;
+
+    Ok(Json(ApiTopic::from(topic)))
+}
+
+/// Archives or reopens the topic, only for the guild owner.
+#[utoipa::path(
+    put,
+    path = "/api/v1/guilds/{guild_id}/topics/{topic_id}/status",
+    tag = "topics",
+    params(("guild_id" = String, Path, description = "Id of the guild"), ("topic_id" = String, Path, description = "Id of the topic")),
+    request_body = ApiTopicStatusRequest,
+    responses(
+        (status = 200, body = ApiTopic),
+        (status = 400, body = ApiErrorBody),
+        (status = 401, body = ApiErrorBody),
+        (status = 403, body = ApiErrorBody),
+        (status = 404, body = ApiErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn change_topic_status(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    WithRejection(Path(parameters), _): ApiPath<ApiTopicParameters>,
+    WithRejection(Json(request), _): ApiJson<ApiTopicStatusRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let guild = get_member_guild(&app, &user, &parameters.guild_id).await?;
+
+    let topic =
+        get_guild_topic(&app, &user, &guild, &parameters.topic_id).await?;
+
+    if !topic.can_change_status {
+        return Err(ApiError::forbidden(
+            "You can not change the status of this topic",
+        ));
+    }
+
+    let topic = app
+        .topics_service
+        .change_topic_status(&topic.id, user.id, request.status, &guild)
+        .await?;
+
+    Ok(Json(ApiTopic::from(topic)))
+}
+
+pub async fn get_openapi_document() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
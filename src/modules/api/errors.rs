@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::error;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+use crate::{
+    libs::validator::validator_errors_to_hashmap, modules::app::AppError,
+};
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiErrorBody {
+    pub error: ApiErrorDetails,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiErrorDetails {
+    /// Stable identifier of the error, e.g. `not_found`.
+    pub code: String,
+    pub message: String,
+    /// Error message of each invalid field.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub fields: HashMap<String, String>,
+}
+
+/// Error of the JSON API, rendered as an `ApiErrorBody`.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub fields: HashMap<String, String>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: String) -> Self {
+        Self { status, code, message, fields: HashMap::new() }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message.into())
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "A valid bearer token is required".to_string(),
+        )
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message.into())
+    }
+
+    pub fn validation(errors: ValidationErrors) -> Self {
+        Self {
+            fields: validator_errors_to_hashmap(Some(errors)),
+            ..Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                "Some fields are invalid".to_string(),
+            )
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ApiErrorBody {
+            error: ApiErrorDetails {
+                code: self.code.to_string(),
+                message: self.message,
+                fields: self.fields,
+            },
+        };
+
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        AppError::from(err).into()
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(err: AppError) -> Self {
+        if let AppError::Internal(err) = &err {
+            error!("{err:?}");
+        }
+
+        Self::new(err.get_status_code(), err.get_code(), err.get_message())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(_: PathRejection) -> Self {
+        Self::not_found("Resource was not found")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use axum::body::to_bytes;
+    use serde_json::{json, Value};
+    use validator::ValidationError;
+
+    use super::*;
+
+    async fn read_body(error: ApiError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn app_errors_keep_their_status_code_and_message() {
+        let (status, body) =
+            read_body(AppError::Conflict("Already voted".to_string()).into())
+                .await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            body,
+            json!({"error": {"code": "conflict", "message": "Already voted"}})
+        );
+    }
+
+    #[tokio::test]
+    async fn internal_errors_are_not_leaked() {
+        let (status, body) =
+            read_body(anyhow!("connection to mongo refused").into()).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"]["code"], "internal_error");
+        assert!(!body["error"]["message"].as_str().unwrap().contains("mongo"));
+    }
+
+    #[tokio::test]
+    async fn validation_errors_list_the_invalid_fields() {
+        let mut errors = ValidationErrors::new();
+
+        errors.add(
+            "name",
+            ValidationError::new("length")
+                .with_message("Name is too short".into()),
+        );
+
+        let (status, body) = read_body(ApiError::validation(errors)).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["fields"]["name"], "Name is too short");
+    }
+}
@@ -0,0 +1,10 @@
+pub mod constants;
+pub mod controller;
+pub mod errors;
+pub mod openapi;
+pub mod types;
+
+pub use controller::*;
+pub use errors::*;
+pub use openapi::*;
+pub use types::*;
@@ -0,0 +1,70 @@
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::modules::topic::types::TopicStatus;
+
+use super::{
+    controller, ApiErrorBody, ApiErrorDetails, ApiGuild, ApiGuildRequest,
+    ApiMember, ApiTopic, ApiTopicPermissions, ApiTopicRequest,
+    ApiTopicStatusRequest, GuildsPage, TopicsPage,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "guildhub API",
+        description = "Authenticate with the session token as a bearer token."
+    ),
+    paths(
+        controller::get_guilds,
+        controller::create_guild,
+        controller::get_guild,
+        controller::update_guild,
+        controller::delete_guild,
+        controller::get_topics,
+        controller::create_topic,
+        controller::get_topic,
+        controller::update_topic,
+        controller::delete_topic,
+        controller::vote_for_topic,
+        controller::remove_vote_from_topic,
+        controller::change_topic_status,
+    ),
+    components(schemas(
+        ApiMember,
+        ApiGuild,
+        ApiTopic,
+        ApiTopicPermissions,
+        GuildsPage,
+        TopicsPage,
+        ApiGuildRequest,
+        ApiTopicRequest,
+        ApiTopicStatusRequest,
+        ApiErrorBody,
+        ApiErrorDetails,
+        TopicStatus,
+    )),
+    modifiers(&BearerSecurity),
+    tags((name = "guilds"), (name = "topics"))
+)]
+pub struct ApiDoc;
+
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+        }
+    }
+}
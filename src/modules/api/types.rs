@@ -0,0 +1,321 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    libs::gitlab_api::gitlab_api::Member,
+    modules::{
+        guild::{Guild, GuildFormDTO},
+        topic::types::{TopicFormDTO, TopicPersonalized, TopicStatus},
+    },
+};
+
+use super::{
+    constants::{API_DEFAULT_PAGE_LIMIT, API_MAX_PAGE_LIMIT},
+    ApiError,
+};
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiMember {
+    pub id: usize,
+    pub username: String,
+    pub name: String,
+    pub avatar_url: String,
+    /// Placeholder of a member who left the directory.
+    pub is_former_member: bool,
+}
+
+impl From<Member> for ApiMember {
+    fn from(member: Member) -> ApiMember {
+        ApiMember {
+            id: member.id,
+            username: member.username,
+            name: member.name,
+            avatar_url: member.avatar_url,
+            is_former_member: member.is_former_member,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiGuild {
+    pub id: String,
+    pub name: String,
+    pub owner: ApiMember,
+    pub members: Vec<ApiMember>,
+    pub topics_count: usize,
+    pub updated_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Guild> for ApiGuild {
+    fn from(guild: Guild) -> ApiGuild {
+        ApiGuild {
+            id: guild.id,
+            name: guild.name,
+            owner: guild.created_by_user.into(),
+            members: guild.members.into_iter().map(ApiMember::from).collect(),
+            topics_count: guild.topics_count,
+            updated_at: guild.updated_at,
+            created_at: guild.created_at,
+        }
+    }
+}
+
+/// What the authenticated member is allowed to do with a topic.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiTopicPermissions {
+    pub can_edit: bool,
+    pub can_delete: bool,
+    pub can_change_status: bool,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ApiTopic {
+    pub id: String,
+    pub guild_id: String,
+    pub text: String,
+    pub status: TopicStatus,
+    pub author: ApiMember,
+    pub will_be_presented_by_the_creator: bool,
+    pub presenter: Option<ApiMember>,
+    pub votes_count: usize,
+    /// Whether the authenticated member voted for the topic.
+    pub is_voted: bool,
+    pub permissions: ApiTopicPermissions,
+    pub updated_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<TopicPersonalized> for ApiTopic {
+    fn from(topic: TopicPersonalized) -> ApiTopic {
+        ApiTopic {
+            id: topic.id,
+            guild_id: topic.guild_id,
+            text: topic.text,
+            status: topic.status,
+            author: topic.created_by_user.into(),
+            will_be_presented_by_the_creator: topic
+                .will_be_presented_by_the_creator,
+            presenter: topic.presented_by_user.map(ApiMember::from),
+            votes_count: topic.upvoted_by_users.len(),
+            is_voted: topic.is_upvoted_by_current_user,
+            permissions: ApiTopicPermissions {
+                can_edit: topic.can_edit,
+                can_delete: topic.can_delete,
+                can_change_status: topic.can_change_status,
+            },
+            updated_at: topic.updated_at,
+            created_at: topic.created_at,
+        }
+    }
+}
+
+/// A page of results, newest first.
+#[derive(Serialize, ToSchema, Debug)]
+#[aliases(GuildsPage = ApiPage<ApiGuild>, TopicsPage = ApiPage<ApiTopic>)]
+pub struct ApiPage<T> {
+    pub data: Vec<T>,
+    /// Pass it as `cursor` to get the next page, null on the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> ApiPage<T> {
+    /// Expects one item more than the limit to know if there is a next page.
+    pub fn new(
+        mut data: Vec<T>,
+        limit: usize,
+        get_id: impl Fn(&T) -> &str,
+    ) -> Result<Self, ApiError> {
+        let next_cursor = match data.len() > limit {
+            true => {
+                data.truncate(limit);
+
+                match data.last() {
+                    Some(item) => Some(encode_cursor(get_id(item))?),
+                    None => None,
+                }
+            }
+            false => None,
+        };
+
+        Ok(Self { data, next_cursor })
+    }
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Number of items per page, 20 by default and at most 100.
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+impl PageQuery {
+    pub fn get_limit(&self) -> usize {
+        self.limit
+            .unwrap_or(API_DEFAULT_PAGE_LIMIT)
+            .clamp(1, API_MAX_PAGE_LIMIT)
+    }
+
+    pub fn get_before_id(&self) -> Result<Option<ObjectId>, ApiError> {
+        self.cursor.as_deref().map(decode_cursor).transpose()
+    }
+}
+
+#[derive(Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct TopicsQuery {
+    /// Only topics with this status, all topics by default.
+    pub status: Option<TopicStatus>,
+    /// Number of items per page, 20 by default and at most 100.
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+impl TopicsQuery {
+    pub fn get_page(&self) -> PageQuery {
+        PageQuery { limit: self.limit, cursor: self.cursor.clone() }
+    }
+}
+
+fn encode_cursor(id: &str) -> Result<String, ApiError> {
+    let id = ObjectId::parse_str(id).map_err(anyhow::Error::from)?;
+
+    Ok(URL_SAFE_NO_PAD.encode(id.bytes()))
+}
+
+fn decode_cursor(cursor: &str) -> Result<ObjectId, ApiError> {
+    let bytes: [u8; 12] = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ApiError::bad_request("Invalid cursor"))?;
+
+    Ok(ObjectId::from_bytes(bytes))
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct ApiGuildRequest {
+    /// Between 2 and 200 characters.
+    pub name: String,
+    /// Members besides the owner.
+    #[serde(default)]
+    pub member_ids: Vec<usize>,
+}
+
+impl From<ApiGuildRequest> for GuildFormDTO {
+    fn from(request: ApiGuildRequest) -> GuildFormDTO {
+        GuildFormDTO {
+            name: request.name,
+            member_ids: request.member_ids,
+            member_search_term: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct ApiTopicRequest {
+    /// Between 2 and 200 characters.
+    pub text: String,
+    #[serde(default)]
+    pub will_be_presented_by_the_creator: bool,
+}
+
+impl From<ApiTopicRequest> for TopicFormDTO {
+    fn from(request: ApiTopicRequest) -> TopicFormDTO {
+        TopicFormDTO {
+            text: request.text,
+            will_be_presented_by_the_creator: Some(
+                request.will_be_presented_by_the_creator,
+            ),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct ApiTopicStatusRequest {
+    pub status: TopicStatus,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiGuildIdParameter {
+    pub guild_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiTopicParameters {
+    pub guild_id: String,
+    pub topic_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_query(limit: Option<usize>, cursor: Option<&str>) -> PageQuery {
+        PageQuery { limit, cursor: cursor.map(str::to_string) }
+    }
+
+    fn ids(count: usize) -> Vec<String> {
+        (0..count).map(|_| ObjectId::new().to_hex()).collect()
+    }
+
+    #[test]
+    fn limits_are_kept_within_bounds() {
+        assert_eq!(page_query(None, None).get_limit(), API_DEFAULT_PAGE_LIMIT);
+        assert_eq!(page_query(Some(0), None).get_limit(), 1);
+        assert_eq!(page_query(Some(5), None).get_limit(), 5);
+        assert_eq!(
+            page_query(Some(API_MAX_PAGE_LIMIT + 1), None).get_limit(),
+            API_MAX_PAGE_LIMIT
+        );
+    }
+
+    #[test]
+    fn the_extra_item_only_tells_there_is_a_next_page() {
+        let ids = ids(3);
+
+        let page = ApiPage::new(ids.clone(), 2, |id| id.as_str()).unwrap();
+
+        assert_eq!(page.data, ids[..2]);
+
+        let cursor = page.next_cursor.unwrap();
+        let before_id = page_query(None, Some(&cursor)).get_before_id();
+
+        assert_eq!(
+            before_id.unwrap(),
+            Some(ObjectId::parse_str(&ids[1]).unwrap())
+        );
+    }
+
+    #[test]
+    fn the_last_page_has_no_cursor() {
+        let page = ApiPage::new(ids(2), 2, |id| id.as_str()).unwrap();
+
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(page.next_cursor, None);
+
+        let page =
+            ApiPage::<String>::new(vec![], 2, |id| id.as_str()).unwrap();
+
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn invalid_cursors_are_bad_requests() {
+        assert!(page_query(None, None).get_before_id().unwrap().is_none());
+
+        for cursor in ["not base64!", "c2hvcnQ", ""] {
+            let err =
+                page_query(None, Some(cursor)).get_before_id().unwrap_err();
+
+            assert_eq!(err.status, axum::http::StatusCode::BAD_REQUEST);
+            assert_eq!(err.message, "Invalid cursor");
+        }
+    }
+}
@@ -1,46 +1,97 @@
-use super::middlewares::{optional_auth, require_auth};
-use super::{controller, Event};
-use crate::configuration::{Configuration, Environment};
+use super::middlewares::{
+    optional_auth, render_errors, require_admin, require_api_auth,
+    require_auth,
+};
+use super::{
+    controller, EventBus, EventTransport, InProcessEventTransport,
+    MongoEventTransport,
+};
+use crate::configuration::{
+    Configuration, Environment, EventTransportKind,
+    MemberDirectoryConfiguration,
+};
 use crate::libs::gitlab_api::gitlab_api::Member;
 use crate::libs::gitlab_api::GitlabApi;
 use crate::libs::health_checker::HealthChecker;
+use crate::libs::mailer::Mailer;
 use crate::libs::migration::Migration;
 use crate::libs::mongo::database::MongoDatabase;
+use crate::modules::admin;
+use crate::modules::api;
 use crate::modules::auth::AuthService;
-use crate::modules::gitlab::GitlabService;
+use crate::modules::chat::{self, ChatService};
+use crate::modules::digest::{self, DigestService, DigestsRepository};
+use crate::modules::feed::{self, FeedTokensRepository, FeedsService};
+use crate::modules::gitlab::{
+    self, constants::MEMBERS_REFRESH_INTERVAL, GitlabService,
+};
 use crate::modules::guild::{self, GuildsRepository, GuildsService};
+use crate::modules::meeting::{self, MeetingsRepository, MeetingsService};
+use crate::modules::member::{
+    FileMemberDirectory, MemberDirectory, MemberEvent, MembersRepository,
+    MembersService,
+};
+use crate::modules::notification::{
+    self, NotificationsRepository, NotificationsService,
+};
+use crate::modules::presence::{self, PresenceService};
+use crate::modules::session::{self, SessionsRepository, SessionsService};
 use crate::modules::topic::migrations::add_topic_status::AddTopicStatusMigration;
 use crate::modules::topic::{self, TopicsRepository, TopicsService};
+use crate::modules::transfer::{self, TransferService};
+use crate::modules::webhook::{self, WebhooksRepository, WebhooksService};
+use crate::modules::wiki::{self, WikiService};
 use anyhow::Result;
 use axum::middleware;
 use axum::routing::{delete, get, post, put};
 use axum::Router;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::broadcast::error::RecvError;
 use tower_http::{services::ServeDir, trace::TraceLayer};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
 pub struct App {
-    pub events_channel: (Sender<Event>, Receiver<Event>),
+    pub event_bus: Arc<EventBus>,
     pub configuration: Arc<Configuration>,
     pub database: Arc<MongoDatabase>,
-    pub gitlab_service: Arc<GitlabService>,
+    /// Only available when gitlab is the member directory.
+    pub gitlab_service: Option<Arc<GitlabService>>,
+    pub member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    pub members_repository: Arc<MembersRepository>,
     pub guilds_repository: Arc<GuildsRepository>,
     pub guilds_service: Arc<GuildsService>,
     pub topics_repository: Arc<TopicsRepository>,
     pub topics_service: Arc<TopicsService>,
+    pub notifications_repository: Arc<NotificationsRepository>,
+    pub notifications_service: Arc<NotificationsService>,
+    pub sessions_repository: Arc<SessionsRepository>,
+    pub sessions_service: Arc<SessionsService>,
+    pub webhooks_repository: Arc<WebhooksRepository>,
+    pub webhooks_service: Arc<WebhooksService>,
+    pub presence_service: Arc<PresenceService>,
+    pub meetings_repository: Arc<MeetingsRepository>,
+    pub meetings_service: Arc<MeetingsService>,
+    pub transfer_service: Arc<TransferService>,
+    pub feed_tokens_repository: Arc<FeedTokensRepository>,
+    pub feeds_service: Arc<FeedsService>,
+    /// Only available when slash command tokens are configured.
+    pub chat_service: Option<Arc<ChatService>>,
+    /// Only available when an SMTP server is configured.
+    pub digest_service: Option<Arc<DigestService>>,
+    /// Only available when a gitlab wiki project is configured.
+    pub wiki_service: Option<Arc<WikiService>>,
     pub auth_service: Arc<AuthService>,
     pub dependencies: Arc<Vec<Box<Arc<dyn HealthChecker + Send + Sync>>>>,
     pub migrations: Arc<Vec<Box<Arc<dyn Migration + Send + Sync>>>>,
 }
 
 impl App {
-    pub async fn new() -> Self {
-        let configuration = Arc::new(Configuration::new().await);
+    pub async fn new() -> Result<Self> {
+        let configuration = Arc::new(Configuration::new().await?);
 
-        App::init(configuration.clone()).await
+        Ok(App::init(configuration.clone()).await)
     }
 
     pub async fn init(configuration: Arc<Configuration>) -> Self {
@@ -56,11 +107,27 @@ impl App {
             }
         };
 
-        let events_channel = broadcast::channel::<Event>(10);
-
         let database =
             Arc::new(MongoDatabase::new(configuration.mongo.clone()));
 
+        let event_transport: Arc<dyn EventTransport + Send + Sync> =
+            match configuration.events.transport {
+                EventTransportKind::InProcess => {
+                    Arc::new(InProcessEventTransport::new(
+                        configuration.events.channel_capacity,
+                    ))
+                }
+                EventTransportKind::Mongo => {
+                    Arc::new(MongoEventTransport::new(database.clone()).await)
+                }
+            };
+
+        let event_bus =
+            Arc::new(EventBus::new(&configuration.events, event_transport));
+
+        let event_bus_ref = event_bus.clone();
+        tokio::spawn(async move { event_bus_ref.listen().await });
+
         let database_ping_ref = Arc::clone(&database);
 
         tokio::spawn(async move {
@@ -69,48 +136,70 @@ impl App {
                 .await
         });
 
-        let auth_service = Arc::new(AuthService::new(
-            configuration.auth.expire_in_hours,
-            configuration.auth.secret.clone(),
-        ));
-
-        let gitlab = GitlabApi::new(
-            configuration.gitlab.access_token.clone(),
-            configuration.gitlab.domain.clone(),
-            configuration.gitlab.client_id.clone(),
-            configuration.gitlab.client_secret.clone(),
-            configuration.gitlab.redirect_url.clone(),
+        let auth_service = Arc::new(
+            AuthService::new(&configuration.auth)
+                .expect("Failed to load auth keys"),
         );
 
-        let gitlab_service = Arc::new(GitlabService::new(
-            gitlab,
-            configuration.gitlab.group_id.clone(),
-            Duration::from_secs(60 * 10),
-        ));
-
-        match configuration.environment {
-            Environment::Development => {
-                gitlab_service
-                    .insert_member_into_cache(Member::default())
-                    .await
+        let members_repository =
+            Arc::new(MembersRepository::new(database.clone()).await);
+
+        let (gitlab_service, member_directory): (
+            Option<Arc<GitlabService>>,
+            Arc<dyn MemberDirectory + Send + Sync>,
+        ) = match &configuration.member_directory {
+            MemberDirectoryConfiguration::Gitlab => {
+                let gitlab_service = App::init_gitlab_service(
+                    &configuration,
+                    members_repository.clone(),
+                )
+                .await;
+
+                (Some(gitlab_service.clone()), gitlab_service)
             }
-            _ => {
-                match gitlab_service.refresh_members_cache().await {
-                    Err(err) => {
-                        panic!("Failed to load members cache: {err}");
-                    }
-                    _ => {
-                        info!("In memory cache for gitlab members is ready to go 🔥")
-                    }
-                }
+            MemberDirectoryConfiguration::File { path } => {
+                let file_directory = FileMemberDirectory::from_file(path)
+                    .expect("Failed to load member directory file");
+
+                (None, Arc::new(file_directory))
             }
-        }
+        };
+
+        info!(
+            "Using {} member directory",
+            member_directory.get_directory_name()
+        );
 
         let topics_repository =
             Arc::new(TopicsRepository::new(database.clone()).await);
+        let members_service = Arc::new(MembersService::new(
+            member_directory.clone(),
+            members_repository.clone(),
+        ));
+
+        let mailer = configuration.mail.as_ref().map(|mail_configuration| {
+            Arc::new(
+                Mailer::new(mail_configuration, &configuration.app_url)
+                    .expect("Failed to set up the SMTP mailer"),
+            )
+        });
+
+        let notifications_repository =
+            Arc::new(NotificationsRepository::new(database.clone()).await);
+        let notifications_service = Arc::new(NotificationsService::new(
+            notifications_repository.clone(),
+            member_directory.clone(),
+            mailer.clone(),
+            configuration.notifications.votes_threshold,
+        ));
+
         let topics_service = Arc::new(TopicsService::new(
-            gitlab_service.clone(),
+            members_service.clone(),
+            notifications_service.clone(),
             topics_repository.clone(),
+            gitlab_service.as_ref().is_some_and(|gitlab_service| {
+                gitlab_service.is_issues_project_configured()
+            }),
         ));
 
         let guilds_repository =
@@ -118,53 +207,292 @@ impl App {
         let guilds_service = Arc::new(GuildsService::new(
             topics_service.clone(),
             guilds_repository.clone(),
-            gitlab_service.clone(),
+            member_directory.clone(),
+            members_service.clone(),
+            notifications_service.clone(),
+        ));
+
+        let sessions_repository =
+            Arc::new(SessionsRepository::new(database.clone()).await);
+        let sessions_service =
+            Arc::new(SessionsService::new(sessions_repository.clone()));
+
+        let webhooks_repository =
+            Arc::new(WebhooksRepository::new(database.clone()).await);
+        let webhooks_service = Arc::new(WebhooksService::new(
+            webhooks_repository.clone(),
+            guilds_service.clone(),
+            member_directory.clone(),
+            configuration.app_url.clone(),
         ));
 
-        let gitlab_service_ref = gitlab_service.clone();
+        let webhooks_service_ref = webhooks_service.clone();
+        let (
+            topic_events_receiver,
+            guild_events_receiver,
+            session_events_receiver,
+        ) = (
+            topics_service.events_channel.0.subscribe(),
+            guilds_service.events_channel.0.subscribe(),
+            sessions_service.events_channel.0.subscribe(),
+        );
+
         tokio::spawn(async move {
-            gitlab_service_ref
-                .refresh_cache_loop(Duration::from_secs(60 * 5))
+            webhooks_service_ref
+                .listen(
+                    topic_events_receiver,
+                    guild_events_receiver,
+                    session_events_receiver,
+                )
                 .await
         });
 
-        let dependencies: Arc<Vec<Box<Arc<dyn HealthChecker + Send + Sync>>>> =
-            Arc::new(vec![Box::new(database.clone())]);
+        tokio::spawn(webhooks_service.clone().run_queue());
 
-        let app_events_sender = events_channel.0.clone();
+        let presence_service = Arc::new(PresenceService::default());
 
-        let guilds_service_ref = guilds_service.clone();
+        let meetings_repository =
+            Arc::new(MeetingsRepository::new(database.clone()).await);
+        let meetings_service = Arc::new(MeetingsService::new(
+            meetings_repository.clone(),
+            topics_service.clone(),
+        ));
 
-        tokio::spawn(async move {
-            let mut guild_events_receiver =
-                guilds_service_ref.events_channel.0.subscribe();
+        let transfer_service = Arc::new(TransferService::new(
+            guilds_repository.clone(),
+            guilds_service.clone(),
+            topics_repository.clone(),
+            member_directory.clone(),
+        ));
 
-            loop {
-                match guild_events_receiver.recv().await {
-                    Ok(event) => {
-                        let _ = app_events_sender.send(event.into());
+        let feed_tokens_repository =
+            Arc::new(FeedTokensRepository::new(database.clone()).await);
+        let feeds_service = Arc::new(FeedsService::new(
+            feed_tokens_repository.clone(),
+            guilds_service.clone(),
+            topics_service.clone(),
+            sessions_service.clone(),
+            member_directory.clone(),
+            configuration.app_url.clone(),
+        ));
+
+        let chat_service = configuration.chat.as_ref().map(|_| {
+            Arc::new(ChatService::new(
+                guilds_service.clone(),
+                topics_service.clone(),
+                member_directory.clone(),
+                configuration.app_url.clone(),
+            ))
+        });
+
+        let digest_service = match mailer.clone() {
+            Some(mailer) => Some(Arc::new(DigestService::new(
+                mailer,
+                Arc::new(DigestsRepository::new(database.clone()).await),
+                member_directory.clone(),
+                guilds_service.clone(),
+                topics_service.clone(),
+                sessions_service.clone(),
+                notifications_service.clone(),
+            ))),
+            None => None,
+        };
+
+        if let (Some(digest_service), Some(mail_configuration)) =
+            (&digest_service, &configuration.mail)
+        {
+            let digest_service_ref = digest_service.clone();
+            let (weekday, hour) = (
+                mail_configuration.digest_weekday,
+                mail_configuration.digest_hour,
+            );
+
+            tokio::spawn(async move {
+                digest_service_ref.send_digests_in_loop(weekday, hour).await
+            });
+        }
+
+        let wiki_service = gitlab_service
+            .as_ref()
+            .filter(|gitlab_service| {
+                gitlab_service.is_wiki_project_configured()
+            })
+            .map(|gitlab_service| {
+                Arc::new(WikiService::new(
+                    gitlab_service.clone(),
+                    guilds_service.clone(),
+                    topics_service.clone(),
+                ))
+            });
+
+        let wiki_export_interval = configuration
+            .gitlab
+            .as_ref()
+            .and_then(|gitlab| gitlab.wiki_export_interval);
+
+        if let (Some(wiki_service), Some(interval)) =
+            (&wiki_service, wiki_export_interval)
+        {
+            let wiki_service_ref = wiki_service.clone();
+            tokio::spawn(async move {
+                wiki_service_ref.export_agendas_in_loop(interval).await
+            });
+        }
+
+        let departed_member_policy = configuration.departed_member_policy;
+
+        match &gitlab_service {
+            Some(gitlab_service) => {
+                let guilds_service_ref = guilds_service.clone();
+                let mut member_events_receiver =
+                    gitlab_service.events_channel.0.subscribe();
+
+                tokio::spawn(async move {
+                    loop {
+                        match member_events_receiver.recv().await {
+                            Ok(MemberEvent::Remove(user_id)) => {
+                                if let Err(err) = guilds_service_ref
+                                    .handle_departed_member(
+                                        user_id,
+                                        departed_member_policy,
+                                    )
+                                    .await
+                                {
+                                    error!("Failed to handle departed member {user_id}: {err}");
+                                }
+                            }
+                            Err(RecvError::Lagged(count)) => {
+                                warn!("Missed {count} member events, departed members are caught up on the next refresh");
+                            }
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                });
+
+                // refreshed only now so members who left while the app was
+                // down go through the departed member policy. The seeded dev
+                // members stand in for gitlab, which is never asked.
+                if !configuration.dev_login {
+                    match gitlab_service.refresh_members_cache().await {
+                        Err(err) => {
+                            warn!("Failed to load members from gitlab, serving persisted members until it recovers: {err}");
+                        }
+                        _ => {
+                            info!("In memory cache for gitlab members is ready to go 🔥")
+                        }
                     }
-                    Err(err) => error!("{err}"),
+
+                    let gitlab_service_ref = gitlab_service.clone();
+                    tokio::spawn(async move {
+                        gitlab_service_ref
+                            .refresh_cache_loop(MEMBERS_REFRESH_INTERVAL)
+                            .await
+                    });
                 }
             }
+            None => match members_service.snapshot_directory().await {
+                Ok(removed_ids) => {
+                    for user_id in removed_ids {
+                        if let Err(err) = guilds_service
+                            .handle_departed_member(
+                                user_id,
+                                departed_member_policy,
+                            )
+                            .await
+                        {
+                            error!("Failed to handle departed member {user_id}: {err}");
+                        }
+                    }
+                }
+                Err(err) => {
+                    warn!("Failed to persist member directory snapshot: {err}")
+                }
+            },
+        }
+
+        let mut dependencies: Vec<Box<Arc<dyn HealthChecker + Send + Sync>>> =
+            vec![Box::new(database.clone())];
+
+        // the dev login never asks gitlab, it would always look down
+        if let Some(gitlab_service) =
+            gitlab_service.as_ref().filter(|_| !configuration.dev_login)
+        {
+            let gitlab_ping_ref = Arc::clone(gitlab_service);
+
+            tokio::spawn(async move {
+                gitlab_ping_ref
+                    .refresh_is_healthy_in_loop(Duration::from_secs(30))
+                    .await
+            });
+
+            dependencies.push(Box::new(gitlab_service.clone()));
+        }
+
+        if let Some(mailer) = mailer {
+            let mailer_ping_ref = Arc::clone(&mailer);
+
+            tokio::spawn(async move {
+                mailer_ping_ref
+                    .refresh_is_healthy_in_loop(Duration::from_secs(60))
+                    .await
+            });
+
+            dependencies.push(Box::new(mailer));
+        }
+
+        let dependencies = Arc::new(dependencies);
+
+        let event_bus_ref = event_bus.clone();
+        let guild_events_receiver =
+            guilds_service.events_channel.0.subscribe();
+
+        tokio::spawn(async move {
+            event_bus_ref.forward("guild", guild_events_receiver).await
         });
 
-        let app_events_sender = events_channel.0.clone();
+        let event_bus_ref = event_bus.clone();
+        let topic_events_receiver =
+            topics_service.events_channel.0.subscribe();
+
+        tokio::spawn(async move {
+            event_bus_ref.forward("topic", topic_events_receiver).await
+        });
 
-        let topics_service_ref = topics_service.clone();
+        let event_bus_ref = event_bus.clone();
+        let notification_events_receiver =
+            notifications_service.events_channel.0.subscribe();
 
         tokio::spawn(async move {
-            let mut topic_events_receiver =
-                topics_service_ref.events_channel.0.subscribe();
+            event_bus_ref
+                .forward("notification", notification_events_receiver)
+                .await
+        });
 
-            loop {
-                match topic_events_receiver.recv().await {
-                    Ok(event) => {
-                        let _ = app_events_sender.send(event.into());
-                    }
-                    Err(err) => error!("{err}"),
-                }
-            }
+        let event_bus_ref = event_bus.clone();
+        let presence_events_receiver =
+            presence_service.events_channel.0.subscribe();
+
+        tokio::spawn(async move {
+            event_bus_ref.forward("presence", presence_events_receiver).await
+        });
+
+        let presence_service_ref = presence_service.clone();
+        tokio::spawn(
+            async move { presence_service_ref.announce_in_loop().await },
+        );
+
+        let presence_service_ref = presence_service.clone();
+        let app_events_receiver = event_bus.subscribe();
+        tokio::spawn(async move {
+            presence_service_ref.listen(app_events_receiver).await
+        });
+
+        let event_bus_ref = event_bus.clone();
+        let meeting_events_receiver =
+            meetings_service.events_channel.0.subscribe();
+
+        tokio::spawn(async move {
+            event_bus_ref.forward("meeting", meeting_events_receiver).await
         });
 
         let migrations: Arc<Vec<Box<Arc<dyn Migration + Send + Sync>>>> =
@@ -172,17 +500,73 @@ impl App {
 
         App {
             migrations,
-            events_channel,
+            event_bus,
             configuration,
             database,
             dependencies,
             gitlab_service,
+            member_directory,
+            members_repository,
             auth_service,
             guilds_repository,
             guilds_service,
             topics_repository,
             topics_service,
+            notifications_repository,
+            notifications_service,
+            sessions_repository,
+            sessions_service,
+            webhooks_repository,
+            webhooks_service,
+            presence_service,
+            meetings_repository,
+            meetings_service,
+            transfer_service,
+            feed_tokens_repository,
+            feeds_service,
+            chat_service,
+            digest_service,
+            wiki_service,
+        }
+    }
+
+    async fn init_gitlab_service(
+        configuration: &Configuration,
+        members_repository: Arc<MembersRepository>,
+    ) -> Arc<GitlabService> {
+        let gitlab_configuration = configuration
+            .gitlab
+            .as_ref()
+            .expect("Gitlab configuration to be available");
+
+        let gitlab = GitlabApi::new(gitlab_configuration);
+
+        let gitlab_service = Arc::new(GitlabService::new(
+            gitlab,
+            gitlab_configuration,
+            // the seeded dev members are never refreshed, so they must not
+            // expire either
+            (!configuration.dev_login).then(|| Duration::from_secs(60 * 10)),
+            members_repository,
+        ));
+
+        // the seeded members are the ones the dev login page offers
+        if configuration.dev_login {
+            for member in Member::development_members() {
+                gitlab_service.insert_member_into_cache(member).await
+            }
+        } else {
+            match gitlab_service.warm_cache_from_database().await {
+                Ok(members_count) => {
+                    info!("Loaded {members_count} members from database")
+                }
+                Err(err) => {
+                    warn!("Failed to load members from database: {err}")
+                }
+            }
         }
+
+        gitlab_service
     }
 
     pub async fn run_migrations(&self) -> Result<()> {
@@ -210,6 +594,8 @@ impl App {
             .route("/", post(guild::create_guild))
             .route("/list", get(guild::get_guilds_list))
             .route("/create", get(guild::get_create_guild_form))
+            .route("/import", get(transfer::get_import_guild_page))
+            .route("/import", post(transfer::import_guild))
             .route("/draft", post(guild::post_guild_form_draft))
             .route("/draft/members/:member_id", delete(guild::remove_member))
             .route("/draft/members/:member_id", post(guild::insert_new_member))
@@ -217,6 +603,11 @@ impl App {
             .route("/:guild_id", delete(guild::delete_guild))
             .route("/:guild_id", put(guild::update_guild))
             .route("/:guild_id/events", get(guild::subscribe_to_events))
+            .route("/:guild_id/viewers", get(presence::get_guild_viewers))
+            .route("/:guild_id/meeting", get(meeting::get_guild_meeting))
+            .route("/:guild_id/meeting", post(meeting::start_meeting))
+            .route("/:guild_id/meeting", delete(meeting::end_meeting))
+            .route("/:guild_id/meeting/step", post(meeting::step_meeting))
             .route("/:guild_id/draft", post(guild::post_guild_form_draft))
             .route(
                 "/:guild_id/draft/members/:member_id",
@@ -257,6 +648,34 @@ impl App {
                 post(topic::unarchive_topic),
             )
             .route("/:guild_id/topics/:topic_id", delete(topic::delete_topic))
+            .route("/:guild_id/wiki", post(wiki::export_guild_to_wiki))
+            .route("/:guild_id/export", get(transfer::export_guild))
+            .route("/:guild_id/sessions", get(session::get_guild_sessions))
+            .route("/:guild_id/sessions", post(session::schedule_session))
+            .route(
+                "/:guild_id/sessions/:session_id",
+                delete(session::cancel_session),
+            )
+            .route("/:guild_id/feeds", get(feed::get_guild_feeds))
+            .route("/:guild_id/feeds/token", post(feed::reset_feed_token))
+            .route("/:guild_id/webhooks", get(webhook::get_guild_webhooks))
+            .route("/:guild_id/webhooks", post(webhook::create_webhook))
+            .route(
+                "/:guild_id/webhooks/:webhook_id",
+                delete(webhook::delete_webhook),
+            )
+            .route(
+                "/:guild_id/webhooks/:webhook_id/deliveries",
+                get(webhook::get_webhook_deliveries),
+            )
+            .route(
+                "/:guild_id/topics/:topic_id/issue",
+                post(topic::create_gitlab_issue),
+            )
+            .route(
+                "/:guild_id/topics/:topic_id/volunteer",
+                post(topic::volunteer_to_present),
+            )
             .route(
                 "/:guild_id/topics/:topic_id/vote",
                 delete(topic::remove_vote_from_topic),
@@ -266,26 +685,140 @@ impl App {
                 require_auth,
             ));
 
-        let public_router = Router::new()
+        let notification_router = Router::new()
+            .route("/", get(notification::get_notifications_page))
+            .route("/events", get(notification::subscribe_to_notifications))
+            .route("/read", post(notification::read_all_notifications))
+            .route(
+                "/:notification_id/read",
+                post(notification::read_notification),
+            )
+            .route(
+                "/preferences/:kind",
+                post(notification::toggle_notification_preference),
+            )
+            .route(
+                "/preferences/:kind/email",
+                post(notification::toggle_notification_email_preference),
+            )
+            .route("/digest", get(digest::get_digest_preview))
+            .route(
+                "/digest/preference",
+                post(notification::toggle_digest_preference),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app.clone(),
+                require_auth,
+            ));
+
+        let admin_router = Router::new()
+            .route("/", get(admin::get_admin_page))
+            .route("/dependencies", get(admin::get_dependencies))
+            .route("/guilds", get(admin::get_guilds_list))
+            .route("/guilds/:guild_id", delete(admin::delete_guild))
+            .route(
+                "/guilds/:guild_id/owner",
+                post(admin::reassign_guild_owner),
+            )
+            .route("/guilds/:guild_id/topics", get(admin::get_archived_topics))
+            .route(
+                "/guilds/:guild_id/topics/:topic_id",
+                delete(admin::delete_topic),
+            )
+            .route(
+                "/guilds/:guild_id/topics/:topic_id/restore",
+                post(admin::restore_topic),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app.clone(),
+                require_admin,
+            ));
+
+        let api_router = Router::new()
+            .route("/guilds", get(api::get_guilds))
+            .route("/guilds", post(api::create_guild))
+            .route("/guilds/:guild_id", get(api::get_guild))
+            .route("/guilds/:guild_id", put(api::update_guild))
+            .route("/guilds/:guild_id", delete(api::delete_guild))
+            .route("/guilds/:guild_id/topics", get(api::get_topics))
+            .route("/guilds/:guild_id/topics", post(api::create_topic))
+            .route("/guilds/:guild_id/topics/:topic_id", get(api::get_topic))
+            .route(
+                "/guilds/:guild_id/topics/:topic_id",
+                put(api::update_topic),
+            )
+            .route(
+                "/guilds/:guild_id/topics/:topic_id",
+                delete(api::delete_topic),
+            )
+            .route(
+                "/guilds/:guild_id/topics/:topic_id/vote",
+                post(api::vote_for_topic),
+            )
+            .route(
+                "/guilds/:guild_id/topics/:topic_id/vote",
+                delete(api::remove_vote_from_topic),
+            )
+            .route(
+                "/guilds/:guild_id/topics/:topic_id/status",
+                put(api::change_topic_status),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                app.clone(),
+                require_api_auth,
+            ))
+            .route("/openapi.json", get(api::get_openapi_document));
+
+        let mut public_router = Router::new()
             .route("/", get(controller::index))
             .route("/health", get(controller::health))
+            .route("/metrics", get(controller::metrics))
             .route("/login", get(controller::login))
+            .route("/login", post(controller::password_login))
             .route("/login", delete(controller::logout))
-            .route("/gitlab_auth", get(controller::gitlab_auth));
+            .route("/gitlab_auth", get(controller::gitlab_auth))
+            .route("/webhooks/gitlab", post(gitlab::gitlab_webhook))
+            .route("/chat/commands", post(chat::handle_slash_command))
+            .route(
+                "/feeds/:token/sessions.ics",
+                get(feed::get_sessions_calendar),
+            )
+            .route(
+                "/feeds/:token/guilds/:guild_id/sessions.ics",
+                get(feed::get_guild_sessions_calendar),
+            )
+            .route(
+                "/feeds/:token/guilds/:guild_id/topics.atom",
+                get(feed::get_guild_topics_feed),
+            );
+
+        if app.configuration.dev_login {
+            public_router = public_router
+                .route("/dev/login", get(controller::get_dev_login_page))
+                .route("/dev/login/:member_id", post(controller::dev_login));
+        }
 
         Router::new()
             .merge(public_router)
             .nest("/guilds", guild_router)
+            .nest("/notifications", notification_router)
+            .nest("/admin", admin_router)
+            .nest("/api/v1", api_router)
             .nest_service("/static", ServeDir::new("static"))
+            .fallback(controller::not_found)
             .route_layer(middleware::from_fn_with_state(
                 app.clone(),
                 optional_auth,
             ))
             .with_state(app)
+            .layer(middleware::from_fn(render_errors))
             .layer(TraceLayer::new_for_http())
     }
 
     pub fn is_healthy(&self) -> bool {
-        return self.dependencies.iter().all(|service| service.get_health());
+        self.dependencies
+            .iter()
+            .filter(|service| service.is_required())
+            .all(|service| service.get_health())
     }
 }
@@ -1,14 +1,19 @@
 use super::{app::App, user_extractor::MaybeAuthenticated, AppError};
-use crate::libs::health_checker::Dependency;
+use crate::libs::{
+    gitlab_api::gitlab_api::Member, health_checker::Dependency,
+};
 
-use axum::http::{header::SET_COOKIE, HeaderValue};
+use axum::http::{
+    header::{self, SET_COOKIE},
+    HeaderValue,
+};
 
 use askama::Template;
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Redirect},
-    Json,
+    Form, Json,
 };
 use axum_extra::extract::cookie::Cookie;
 use cookie::{time::OffsetDateTime, CookieBuilder};
@@ -41,6 +46,13 @@ pub async fn health(State(app): State<Arc<App>>) -> impl IntoResponse {
     return response;
 }
 
+pub async fn metrics(State(app): State<Arc<App>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        app.event_bus.render_metrics(),
+    )
+}
+
 pub async fn index(
     MaybeAuthenticated(user): MaybeAuthenticated,
 ) -> impl IntoResponse {
@@ -56,9 +68,7 @@ pub async fn logout(
 ) -> Result<impl IntoResponse, AppError> {
     let cookie = build_auth_cookie("").expires(OffsetDateTime::now_utc());
 
-    let mut response =
-        LoginTemplate { gitlab_oath_url: app.gitlab_service.get_oath_url() }
-            .into_response();
+    let mut response = LoginTemplate::new(&app, None).into_response();
 
     let cookie = HeaderValue::from_str(&cookie.to_string())?;
 
@@ -70,7 +80,26 @@ pub async fn logout(
 #[derive(Template)]
 #[template(path = "pages/login.html")]
 struct LoginTemplate {
-    pub gitlab_oath_url: String,
+    pub gitlab_oath_url: Option<String>,
+    pub supports_password_login: bool,
+    pub is_development: bool,
+    pub error: Option<String>,
+}
+
+impl LoginTemplate {
+    fn new(app: &App, error: Option<String>) -> Self {
+        Self {
+            gitlab_oath_url: app
+                .gitlab_service
+                .as_ref()
+                .map(|gitlab_service| gitlab_service.get_oath_url()),
+            supports_password_login: app
+                .member_directory
+                .supports_password_login(),
+            is_development: app.configuration.dev_login,
+            error,
+        }
+    }
 }
 
 pub async fn login(
@@ -81,21 +110,120 @@ pub async fn login(
         return Redirect::temporary("/guilds").into_response();
     }
 
-    LoginTemplate { gitlab_oath_url: app.gitlab_service.get_oath_url() }
-        .into_response()
+    LoginTemplate::new(&app, None).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PasswordLoginFormDTO {
+    username: String,
+    password: String,
+}
+
+pub async fn password_login(
+    State(app): State<Arc<App>>,
+    Form(form): Form<PasswordLoginFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    let member = match app
+        .member_directory
+        .authenticate(&form.username, &form.password)
+        .await
+    {
+        Some(member) => member,
+        None => {
+            warn!("Failed password login for {:?}", form.username);
+
+            return Ok((
+                StatusCode::UNAUTHORIZED,
+                LoginTemplate::new(
+                    &app,
+                    Some("Invalid username or password".to_string()),
+                ),
+            )
+                .into_response());
+        }
+    };
+
+    let token = app.auth_service.create_token(member.id)?;
+
+    let cookie = build_auth_cookie(&token);
+
+    let mut response = Redirect::to("/guilds").into_response();
+
+    response
+        .headers_mut()
+        .insert(SET_COOKIE, HeaderValue::from_str(&cookie.to_string())?);
+
+    Ok(response)
+}
+
+#[derive(Template)]
+#[template(path = "pages/dev-login.html")]
+struct DevLoginTemplate {
+    pub members: Vec<Member>,
+}
+
+pub async fn get_dev_login_page(
+    State(app): State<Arc<App>>,
+) -> impl IntoResponse {
+    if !app.configuration.dev_login {
+        return (StatusCode::NOT_FOUND, NotFoundTemplate {}).into_response();
+    }
+
+    let mut members = app.member_directory.get_all_members().await;
+
+    members.sort_by_key(|member| member.id);
+
+    DevLoginTemplate { members }.into_response()
+}
+
+#[derive(Deserialize)]
+pub struct DevLoginParameters {
+    member_id: usize,
+}
+
+pub async fn dev_login(
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<DevLoginParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    if !app.configuration.dev_login {
+        return Ok(
+            (StatusCode::NOT_FOUND, NotFoundTemplate {}).into_response()
+        );
+    }
+
+    let member =
+        match app.member_directory.get_member(&parameters.member_id).await {
+            Some(member) => member,
+            None => return Ok(Redirect::to("/dev/login").into_response()),
+        };
+
+    let token = app.auth_service.create_token(member.id)?;
+
+    let cookie = build_auth_cookie(&token);
+
+    let mut response = Redirect::to("/guilds").into_response();
+
+    response
+        .headers_mut()
+        .insert(SET_COOKIE, HeaderValue::from_str(&cookie.to_string())?);
+
+    Ok(response)
 }
 
 #[derive(Template)]
 #[template(path = "pages/not_found.html")]
-struct NotFoundTemplate;
+pub struct NotFoundTemplate;
 
-pub async fn not_found() -> impl IntoResponse {
-    NotFoundTemplate {}
+pub async fn not_found() -> AppError {
+    AppError::NotFound("Page was not found".to_string())
 }
 
 #[derive(Template)]
 #[template(path = "pages/internal_server_error.html")]
-struct InternalError;
+pub struct ErrorTemplate {
+    pub title: String,
+    pub message: String,
+}
 
 #[derive(Deserialize)]
 pub struct GitlabAuthRequest {
@@ -114,15 +242,15 @@ pub async fn gitlab_auth(
     let code = AuthorizationCode::new(params.code.clone());
     let _state = CsrfToken::new(params.state.clone());
 
-    match app
-        .gitlab_service
-        .gitlab_api
-        .authorize_user_by_access_code(code)
-        .await
-    {
+    let gitlab_service = match &app.gitlab_service {
+        Some(gitlab_service) => gitlab_service,
+        None => return Ok(Redirect::temporary("/login").into_response()),
+    };
+
+    match gitlab_service.gitlab_api.authorize_user_by_access_code(code).await {
         Ok(user) => {
             if let Some(user) =
-                app.gitlab_service.get_cached_member(&user.id).await
+                gitlab_service.get_cached_member(&user.id).await
             {
                 if let Ok(token) = app.auth_service.create_token(user.id) {
                     let cookie = build_auth_cookie(&token);
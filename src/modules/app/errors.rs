@@ -1,21 +1,99 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use tracing::error;
+use bson::oid::ObjectId;
+use tracing::{error, warn};
+
+/// Failure of a request. Services return it inside `anyhow::Error`, e.g.
+/// `bail!(AppError::NotFound(..))`, and it is recovered by downcasting, any
+/// other error being internal.
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    Forbidden(String),
+    Validation(String),
+    Conflict(String),
+    Unavailable(String),
+    Internal(anyhow::Error),
+}
+
+impl AppError {
+    pub fn get_status_code(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable identifier of the error, e.g. `not_found`.
+    pub fn get_code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Validation(_) => "validation_failed",
+            AppError::Conflict(_) => "conflict",
+            AppError::Unavailable(_) => "unavailable",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Message safe to show to the user, internal details are only logged.
+    pub fn get_message(&self) -> String {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Forbidden(message)
+            | AppError::Validation(message)
+            | AppError::Conflict(message)
+            | AppError::Unavailable(message) => message.clone(),
+            AppError::Internal(_) => "Something went wrong".to_string(),
+        }
+    }
+}
 
-pub struct AppError(pub anyhow::Error);
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Internal(err) => write!(f, "{err}"),
+            _ => write!(f, "{}", self.get_message()),
+        }
+    }
+}
+
+/// Attached to error responses, `render_errors` turns it into a page, a toast
+/// or a JSON body depending on who made the request.
+#[derive(Clone, Debug)]
+pub struct AppErrorDetails {
+    pub code: &'static str,
+    pub message: String,
+}
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        error!("{:?}", self.0);
+        match &self {
+            AppError::Internal(err) => error!("{err:?}"),
+            AppError::Unavailable(message) => warn!("{message}"),
+            _ => {}
+        }
+
+        let details = AppErrorDetails {
+            code: self.get_code(),
+            message: self.get_message(),
+        };
 
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            // todo: html template
-            "Something went wrong",
-        )
-            .into_response()
+        let mut response =
+            (self.get_status_code(), details.message.clone()).into_response();
+
+        response.extensions_mut().insert(details);
+
+        response
     }
 }
 
@@ -24,6 +102,106 @@ where
     E: Into<anyhow::Error>,
 {
     fn from(err: E) -> Self {
-        Self(err.into())
+        match err.into().downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(err) => Self::Internal(err),
+        }
+    }
+}
+
+/// Parses an id taken from the request. A malformed id can't belong to any
+/// `what` (guild, topic, ...), so it is reported as not found.
+pub fn parse_object_id(id: &str, what: &str) -> Result<ObjectId> {
+    ObjectId::from_str(id).map_err(|_| {
+        anyhow!(AppError::NotFound(format!("Failed to find {what} {id}")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+
+    use super::*;
+
+    #[test]
+    fn variants_map_to_status_codes() {
+        let cases = [
+            (AppError::NotFound(String::new()), StatusCode::NOT_FOUND),
+            (AppError::Forbidden(String::new()), StatusCode::FORBIDDEN),
+            (
+                AppError::Validation(String::new()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (AppError::Conflict(String::new()), StatusCode::CONFLICT),
+            (
+                AppError::Unavailable(String::new()),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                AppError::Internal(anyhow!("boom")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (err, status) in cases {
+            assert_eq!(err.get_status_code(), status);
+        }
+    }
+
+    #[test]
+    fn internal_errors_hide_their_message() {
+        let err = AppError::Internal(anyhow!("connection refused"));
+
+        assert_eq!(err.get_code(), "internal_error");
+        assert_eq!(err.get_message(), "Something went wrong");
+        assert_eq!(err.to_string(), "connection refused");
+    }
+
+    #[test]
+    fn app_errors_are_recovered_from_anyhow() {
+        fn fail() -> Result<()> {
+            bail!(AppError::Conflict("Already voted".to_string()))
+        }
+
+        let err: AppError = fail().unwrap_err().into();
+
+        assert!(
+            matches!(err, AppError::Conflict(message) if message == "Already voted")
+        );
+    }
+
+    #[test]
+    fn other_errors_become_internal() {
+        let err: AppError = anyhow!("boom").into();
+
+        assert!(matches!(err, AppError::Internal(_)));
+    }
+
+    #[test]
+    fn responses_carry_the_error_details() {
+        let response = AppError::NotFound("Failed to find guild".to_string())
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let details = response.extensions().get::<AppErrorDetails>().unwrap();
+
+        assert_eq!(details.code, "not_found");
+        assert_eq!(details.message, "Failed to find guild");
+    }
+
+    #[test]
+    fn parse_object_id_reports_malformed_ids_as_not_found() {
+        let id = ObjectId::new();
+
+        assert_eq!(parse_object_id(&id.to_hex(), "guild").unwrap(), id);
+
+        let err: AppError =
+            parse_object_id("nope", "guild").unwrap_err().into();
+
+        assert!(matches!(
+            err,
+            AppError::NotFound(message) if message == "Failed to find guild nope"
+        ));
     }
 }
@@ -0,0 +1,295 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+use tracing::{error, info, warn};
+
+use crate::configuration::EventsConfiguration;
+
+use super::{Event, EventLog, EventTransport, Replay, SequencedEvent};
+
+#[derive(Default)]
+struct EventBusMetrics {
+    published_events: AtomicU64,
+    /// Service events lost before they reached the bus.
+    dropped_forwarded_events: AtomicU64,
+    /// Bus events lost by slow SSE subscribers.
+    dropped_subscriber_events: AtomicU64,
+    /// Events the transport failed to publish.
+    dropped_transport_events: AtomicU64,
+    resyncs: AtomicU64,
+}
+
+/// Fans the events of every service out to the SSE subscribers. Service
+/// events are published through the `EventTransport`, which delivers the
+/// events of every instance back. Delivered events are numbered and buffered
+/// by the `EventLog` so clients can catch up, and whenever something is lost
+/// on the way the affected clients are told to resync instead.
+pub struct EventBus {
+    transport: Arc<dyn EventTransport + Send + Sync>,
+    sender: Sender<SequencedEvent>,
+    log: Mutex<EventLog>,
+    metrics: EventBusMetrics,
+}
+
+impl EventBus {
+    pub fn new(
+        configuration: &EventsConfiguration,
+        transport: Arc<dyn EventTransport + Send + Sync>,
+    ) -> Self {
+        let (sender, _) = broadcast::channel::<SequencedEvent>(
+            configuration.channel_capacity,
+        );
+
+        Self {
+            transport,
+            sender,
+            log: Mutex::new(EventLog::new(configuration.replay_buffer_size)),
+            metrics: EventBusMetrics::default(),
+        }
+    }
+
+    /// Numbers, buffers and broadcasts the event under the same lock so
+    /// subscribers see ids in order.
+    fn deliver(&self, event: Event) {
+        let mut log = self.log.lock().unwrap();
+
+        let sequenced_event = log.append(event);
+
+        self.metrics.published_events.fetch_add(1, Ordering::Relaxed);
+
+        // no subscribers is not an error, the event stays in the log
+        let _ = self.sender.send(sequenced_event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<SequencedEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribes along with the id of the latest event, read under the lock
+    /// events are delivered with so every later event is in the receiver.
+    pub fn subscribe_from(&self) -> (Receiver<SequencedEvent>, u64) {
+        let log = self.log.lock().unwrap();
+
+        (self.sender.subscribe(), log.get_last_id())
+    }
+
+    pub fn get_last_id(&self) -> u64 {
+        self.log.lock().unwrap().get_last_id()
+    }
+
+    pub fn replay(&self, guild_id: &str, last_id: u64) -> Replay {
+        self.log.lock().unwrap().replay(guild_id, last_id)
+    }
+
+    /// Delivers the events coming from the transport until it stops.
+    pub async fn listen(&self) {
+        let transport_name = self.transport.get_transport_name();
+
+        info!("Listening to events of the {transport_name} transport");
+
+        match self.transport.listen(&|event| self.deliver(event)).await {
+            Ok(()) => info!("The {transport_name} event transport stopped"),
+            Err(err) => {
+                error!("The {transport_name} event transport failed: {err}")
+            }
+        }
+    }
+
+    async fn publish(&self, event: Event) {
+        if let Err(err) = self.transport.publish(event).await {
+            error!("Failed to publish event: {err}");
+
+            self.metrics
+                .dropped_transport_events
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Publishes the events of a service channel until it is closed. Lost
+    /// events can belong to any guild, so every client has to resync.
+    pub async fn forward<T>(&self, source: &str, mut receiver: Receiver<T>)
+    where
+        T: Into<Event> + Clone,
+    {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => self.publish(event.into()).await,
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Forwarding of {source} events lagged behind, {count} events were dropped");
+
+                    self.metrics
+                        .dropped_forwarded_events
+                        .fetch_add(count, Ordering::Relaxed);
+                    self.metrics.resyncs.fetch_add(1, Ordering::Relaxed);
+
+                    self.publish(Event::Resync).await;
+                }
+                Err(RecvError::Closed) => {
+                    info!("The {source} events channel was closed, stopping forwarding");
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn record_subscriber_lag(&self, count: u64) {
+        self.metrics
+            .dropped_subscriber_events
+            .fetch_add(count, Ordering::Relaxed);
+        self.metrics.resyncs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Metrics in the prometheus text format.
+    pub fn render_metrics(&self) -> String {
+        let metrics = &self.metrics;
+        let mut output = String::new();
+
+        let _ = writeln!(
+            output,
+            "# HELP guildhub_events_published_total Events published on the event bus.\n\
+             # TYPE guildhub_events_published_total counter\n\
+             guildhub_events_published_total {}",
+            metrics.published_events.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            output,
+            "# HELP guildhub_events_dropped_total Events lost because a receiver lagged behind.\n\
+             # TYPE guildhub_events_dropped_total counter\n\
+             guildhub_events_dropped_total{{stage=\"forwarding\"}} {}\n\
+             guildhub_events_dropped_total{{stage=\"transport\"}} {}\n\
+             guildhub_events_dropped_total{{stage=\"subscriber\"}} {}",
+            metrics.dropped_forwarded_events.load(Ordering::Relaxed),
+            metrics.dropped_transport_events.load(Ordering::Relaxed),
+            metrics.dropped_subscriber_events.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            output,
+            "# HELP guildhub_events_resyncs_total Times clients were told to reload their state.\n\
+             # TYPE guildhub_events_resyncs_total counter\n\
+             guildhub_events_resyncs_total {}",
+            metrics.resyncs.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            output,
+            "# HELP guildhub_events_subscribers Open event subscriptions.\n\
+             # TYPE guildhub_events_subscribers gauge\n\
+             guildhub_events_subscribers {}",
+            self.sender.receiver_count()
+        );
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::{
+        configuration::EventTransportKind,
+        modules::{app::InProcessEventTransport, presence::PresenceEvent},
+    };
+
+    fn guild_event(guild_id: &str) -> Event {
+        Event::Presence(PresenceEvent::Change {
+            guild_id: guild_id.to_string(),
+            instance_id: "instance".to_string(),
+            revision: 1,
+            announced_at: chrono::Utc::now(),
+            viewers: vec![],
+        })
+    }
+
+    fn event_bus() -> Arc<EventBus> {
+        let configuration = EventsConfiguration {
+            transport: EventTransportKind::InProcess,
+            channel_capacity: 16,
+            replay_buffer_size: 16,
+        };
+
+        Arc::new(EventBus::new(
+            &configuration,
+            Arc::new(InProcessEventTransport::new(16)),
+        ))
+    }
+
+    #[tokio::test]
+    async fn lagging_forwarding_makes_clients_refresh() {
+        let bus = event_bus();
+        let mut subscriber = bus.subscribe();
+        let last_id = bus.get_last_id();
+
+        let (sender, receiver) = broadcast::channel::<Event>(2);
+
+        for _ in 0..3 {
+            sender.send(guild_event("a")).unwrap();
+        }
+
+        drop(sender);
+
+        bus.forward("test", receiver).await;
+
+        tokio::spawn({
+            let bus = bus.clone();
+            async move { bus.listen().await }
+        });
+
+        let mut delivered = vec![];
+
+        for _ in 0..3 {
+            let event = timeout(Duration::from_secs(1), subscriber.recv())
+                .await
+                .unwrap()
+                .unwrap();
+
+            delivered.push(event);
+        }
+
+        assert!(matches!(delivered[0].event, Event::Resync));
+        assert!(matches!(delivered[1].event, Event::Presence(_)));
+        assert!(matches!(delivered[2].event, Event::Presence(_)));
+
+        // events from before the resync are gone for good
+        assert!(matches!(
+            bus.replay("a", last_id),
+            Replay::FullRefresh { last_id } if last_id == delivered[2].id
+        ));
+
+        match bus.replay("a", delivered[0].id) {
+            Replay::Events(events) => assert_eq!(
+                events.iter().map(|event| event.id).collect::<Vec<_>>(),
+                vec![delivered[1].id, delivered[2].id]
+            ),
+            Replay::FullRefresh { .. } => panic!("expected events"),
+        }
+
+        let metrics = bus.render_metrics();
+
+        assert!(metrics.contains(
+            "guildhub_events_dropped_total{stage=\"forwarding\"} 1"
+        ));
+        assert!(metrics.contains("guildhub_events_resyncs_total 1"));
+    }
+
+    #[test]
+    fn subscriber_lag_is_counted_as_a_resync() {
+        let bus = event_bus();
+
+        bus.record_subscriber_lag(4);
+
+        let metrics = bus.render_metrics();
+
+        assert!(metrics.contains(
+            "guildhub_events_dropped_total{stage=\"subscriber\"} 4"
+        ));
+        assert!(metrics.contains("guildhub_events_resyncs_total 1"));
+    }
+}
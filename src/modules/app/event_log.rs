@@ -0,0 +1,219 @@
+use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+
+use super::Event;
+
+#[derive(Clone, Debug)]
+pub struct SequencedEvent {
+    pub id: u64,
+    pub event: Event,
+}
+
+pub enum Replay {
+    Events(Vec<SequencedEvent>),
+    /// Some of the missed events are no longer buffered, the client has to
+    /// reload its state. `last_id` is where it can resume from afterwards.
+    FullRefresh {
+        last_id: u64,
+    },
+}
+
+#[derive(Default)]
+struct GuildEvents {
+    events: VecDeque<SequencedEvent>,
+    /// Highest id that was dropped from the buffer.
+    evicted_up_to: u64,
+}
+
+/// Numbers app events and keeps the latest ones of every guild so SSE
+/// clients can resume from `Last-Event-ID` after a reconnect.
+pub struct EventLog {
+    /// Ids start from the boot time, which keeps them increasing across
+    /// restarts and tells ids of a previous run apart.
+    first_id: u64,
+    last_id: u64,
+    /// Id of the latest `Event::Resync`, nothing before it can be replayed.
+    resync_id: u64,
+    capacity: usize,
+    guilds: HashMap<String, GuildEvents>,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        let first_id = Utc::now().timestamp_micros() as u64;
+
+        Self {
+            first_id,
+            last_id: first_id - 1,
+            resync_id: 0,
+            capacity,
+            guilds: HashMap::new(),
+        }
+    }
+
+    pub fn append(&mut self, event: Event) -> SequencedEvent {
+        self.last_id += 1;
+
+        let sequenced_event = SequencedEvent { id: self.last_id, event };
+
+        if let Event::Resync = sequenced_event.event {
+            self.resync_id = sequenced_event.id;
+            self.guilds.clear();
+        }
+
+        if let Some(guild_id) = sequenced_event.event.get_guild_id() {
+            let guild_events = self.guilds.entry(guild_id).or_default();
+
+            guild_events.events.push_back(sequenced_event.clone());
+
+            while guild_events.events.len() > self.capacity {
+                if let Some(evicted) = guild_events.events.pop_front() {
+                    guild_events.evicted_up_to = evicted.id;
+                }
+            }
+        }
+
+        sequenced_event
+    }
+
+    pub fn get_last_id(&self) -> u64 {
+        self.last_id
+    }
+
+    /// Events of the guild that happened after `last_id`.
+    pub fn replay(&self, guild_id: &str, last_id: u64) -> Replay {
+        let full_refresh = Replay::FullRefresh { last_id: self.last_id };
+
+        // unknown ids come from a previous run or a different instance
+        if last_id < self.first_id || last_id > self.last_id {
+            return full_refresh;
+        }
+
+        if last_id < self.resync_id {
+            return full_refresh;
+        }
+
+        let Some(guild_events) = self.guilds.get(guild_id) else {
+            return Replay::Events(vec![]);
+        };
+
+        if last_id < guild_events.evicted_up_to {
+            return full_refresh;
+        }
+
+        Replay::Events(
+            guild_events
+                .events
+                .iter()
+                .filter(|event| event.id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::presence::PresenceEvent;
+
+    fn guild_event(guild_id: &str) -> Event {
+        Event::Presence(PresenceEvent::Change {
+            guild_id: guild_id.to_string(),
+            instance_id: "instance".to_string(),
+            revision: 1,
+            announced_at: chrono::Utc::now(),
+            viewers: vec![],
+        })
+    }
+
+    fn get_replayed_ids(replay: Replay) -> Vec<u64> {
+        match replay {
+            Replay::Events(events) => {
+                events.into_iter().map(|event| event.id).collect()
+            }
+            Replay::FullRefresh { .. } => panic!("expected events"),
+        }
+    }
+
+    #[test]
+    fn ids_keep_increasing() {
+        let mut log = EventLog::new(10);
+
+        let first = log.append(guild_event("a")).id;
+        let second = log.append(guild_event("b")).id;
+
+        assert_eq!(second, first + 1);
+        assert_eq!(log.get_last_id(), second);
+    }
+
+    #[test]
+    fn replays_the_events_of_the_guild_after_the_last_id() {
+        let mut log = EventLog::new(10);
+
+        let first = log.append(guild_event("a")).id;
+        let second = log.append(guild_event("a")).id;
+        log.append(guild_event("b"));
+        let fourth = log.append(guild_event("a")).id;
+
+        assert_eq!(
+            get_replayed_ids(log.replay("a", first)),
+            vec![second, fourth]
+        );
+        assert_eq!(
+            get_replayed_ids(log.replay("a", fourth)),
+            Vec::<u64>::new()
+        );
+        assert_eq!(
+            get_replayed_ids(log.replay("c", first)),
+            Vec::<u64>::new()
+        );
+    }
+
+    #[test]
+    fn asks_for_a_full_refresh_once_missed_events_are_evicted() {
+        let mut log = EventLog::new(2);
+
+        let first = log.append(guild_event("a")).id;
+        let second = log.append(guild_event("a")).id;
+        let third = log.append(guild_event("a")).id;
+        let fourth = log.append(guild_event("a")).id;
+
+        assert!(matches!(
+            log.replay("a", first),
+            Replay::FullRefresh { last_id } if last_id == fourth
+        ));
+        assert_eq!(
+            get_replayed_ids(log.replay("a", second)),
+            vec![third, fourth]
+        );
+    }
+
+    #[test]
+    fn asks_for_a_full_refresh_for_unknown_ids() {
+        let mut log = EventLog::new(10);
+
+        let first = log.append(guild_event("a")).id;
+
+        assert!(matches!(
+            log.replay("a", first - 10),
+            Replay::FullRefresh { .. }
+        ));
+        assert!(matches!(
+            log.replay("a", first + 10),
+            Replay::FullRefresh { .. }
+        ));
+    }
+
+    #[test]
+    fn asks_for_a_full_refresh_across_a_resync() {
+        let mut log = EventLog::new(10);
+
+        let first = log.append(guild_event("a")).id;
+        let resync = log.append(Event::Resync).id;
+        let third = log.append(guild_event("a")).id;
+
+        assert!(matches!(log.replay("a", first), Replay::FullRefresh { .. }));
+        assert_eq!(get_replayed_ids(log.replay("a", resync)), vec![third]);
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::{
+    broadcast::{self, error::RecvError, Receiver, Sender},
+    Mutex,
+};
+use tracing::warn;
+
+use super::Event;
+
+/// Carries app events between the instances serving the app.
+#[async_trait]
+pub trait EventTransport {
+    fn get_transport_name(&self) -> String;
+
+    async fn publish(&self, event: Event) -> Result<()>;
+
+    /// Hands every event published by any instance, this one included, to
+    /// `deliver` for as long as the transport is running.
+    async fn listen(
+        &self,
+        deliver: &(dyn Fn(Event) + Send + Sync),
+    ) -> Result<()>;
+}
+
+/// Transport for a single instance, events never leave the process.
+pub struct InProcessEventTransport {
+    sender: Sender<Event>,
+    receiver: Mutex<Receiver<Event>>,
+}
+
+impl InProcessEventTransport {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = broadcast::channel::<Event>(capacity);
+
+        Self { sender, receiver: Mutex::new(receiver) }
+    }
+}
+
+#[async_trait]
+impl EventTransport for InProcessEventTransport {
+    fn get_transport_name(&self) -> String {
+        "in_process".to_string()
+    }
+
+    async fn publish(&self, event: Event) -> Result<()> {
+        self.sender.send(event)?;
+
+        Ok(())
+    }
+
+    async fn listen(
+        &self,
+        deliver: &(dyn Fn(Event) + Send + Sync),
+    ) -> Result<()> {
+        let mut receiver = self.receiver.lock().await;
+
+        loop {
+            match receiver.recv().await {
+                Ok(event) => deliver(event),
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Event transport lagged behind by {count} events");
+
+                    deliver(Event::Resync);
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
+use anyhow::Result;
+use axum::http::HeaderValue;
 use serde::{Deserialize, Serialize};
 
-use crate::modules::{guild::GuildEvent, topic::types::TopicEvent};
+use crate::modules::{
+    guild::GuildEvent, meeting::MeetingEvent, notification::NotificationEvent,
+    presence::PresenceEvent, topic::types::TopicEvent,
+};
 
 #[derive(Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -16,8 +21,105 @@ pub enum HxTriggerEvent {
     ShowToast { level: ToastLevel, message: String },
 }
 
+impl HxTriggerEvent {
+    /// Value of the `HX-Trigger` header. Headers only carry ASCII, so other
+    /// characters are escaped as `\uXXXX`, which htmx decodes back.
+    pub fn to_header_value(&self) -> Result<HeaderValue> {
+        let event = escape_non_ascii(&serde_json::to_string(self)?);
+
+        Ok(HeaderValue::from_str(&event)?)
+    }
+}
+
+/// Escapes the non-ASCII characters of a JSON document, they can only be
+/// found in its strings.
+fn escape_non_ascii(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+
+    for char in json.chars() {
+        if char.is_ascii() {
+            escaped.push(char);
+            continue;
+        }
+
+        let mut buffer = [0; 2];
+
+        for unit in char.encode_utf16(&mut buffer) {
+            escaped.push_str(&format!("\\u{unit:04x}"));
+        }
+    }
+
+    escaped
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub enum Event {
     Topic(TopicEvent),
     Guild(GuildEvent),
+    Notification(NotificationEvent),
+    Presence(PresenceEvent),
+    Meeting(MeetingEvent),
+    /// Events were lost on the way, every client has to reload its state.
+    Resync,
+}
+
+impl Event {
+    pub fn get_guild_id(&self) -> Option<String> {
+        match self {
+            Event::Guild(GuildEvent::Create(_))
+            | Event::Notification(_)
+            | Event::Resync => None,
+            Event::Guild(GuildEvent::Update(guild)) => Some(guild.id.clone()),
+            Event::Guild(GuildEvent::Delete(guild_id)) => {
+                Some(guild_id.clone())
+            }
+            Event::Topic(
+                TopicEvent::Create(topic)
+                | TopicEvent::Update(topic)
+                | TopicEvent::Delete(topic)
+                | TopicEvent::StatusChange(topic),
+            ) => Some(topic.guild_id.clone()),
+            Event::Topic(TopicEvent::OrderChange { guild_id, .. }) => {
+                Some(guild_id.clone())
+            }
+            Event::Presence(PresenceEvent::Change { guild_id, .. })
+            | Event::Meeting(MeetingEvent::Change { guild_id, .. }) => {
+                Some(guild_id.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toast(message: &str) -> HxTriggerEvent {
+        HxTriggerEvent::ShowToast {
+            level: ToastLevel::Info,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn to_header_value_keeps_ascii_messages() {
+        let value = toast("Guild was saved").to_header_value().unwrap();
+
+        assert_eq!(
+            value.to_str().unwrap(),
+            r#"{"show_toast":{"level":"info","message":"Guild was saved"}}"#
+        );
+    }
+
+    #[test]
+    fn to_header_value_escapes_non_ascii_messages() {
+        let value = toast("Café 🎉 was saved").to_header_value().unwrap();
+        let value = value.to_str().unwrap();
+
+        assert!(value.contains(r"Caf\u00e9 \ud83c\udf89 was saved"));
+
+        let event: serde_json::Value = serde_json::from_str(value).unwrap();
+
+        assert_eq!(event["show_toast"]["message"], "Café 🎉 was saved");
+    }
 }
@@ -1,5 +1,11 @@
 pub mod optional_auth;
+pub mod render_errors;
+pub mod require_admin;
+pub mod require_api_auth;
 pub mod require_auth;
 
 pub use optional_auth::*;
+pub use render_errors::*;
+pub use require_admin::*;
+pub use require_api_auth::*;
 pub use require_auth::*;
@@ -1,7 +1,7 @@
 use crate::libs::gitlab_api::gitlab_api::Member;
 use crate::modules::app::controller::build_auth_cookie;
 use axum::extract::{Request, State};
-use axum::http::header::SET_COOKIE;
+use axum::http::header::{AUTHORIZATION, SET_COOKIE};
 use axum::http::HeaderValue;
 use axum::middleware::Next;
 use axum::response::Response;
@@ -17,7 +17,18 @@ pub async fn optional_auth(
     mut request: Request,
     next: Next,
 ) -> Response {
-    let token = cookies.get("token").map(|c| c.value().to_string());
+    // API clients send the token as a bearer token instead of a cookie
+    let bearer_token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+
+    let is_bearer_token = bearer_token.is_some();
+
+    let token = bearer_token
+        .or_else(|| cookies.get("token").map(|c| c.value().to_string()));
 
     if token.is_none() {
         return next.run(request).await;
@@ -31,8 +42,7 @@ pub async fn optional_auth(
         }
     };
 
-    let member =
-        state.gitlab_service.get_cached_member(&token_claims.sub).await;
+    let member = state.member_directory.get_member(&token_claims.sub).await;
 
     if member.is_none() {
         return next.run(request).await;
@@ -42,6 +52,10 @@ pub async fn optional_auth(
 
     let mut response = next.run(request).await;
 
+    if is_bearer_token {
+        return response;
+    }
+
     match state.auth_service.refresh_token_if_needed(token_claims) {
         Ok(Some(new_token)) => {
             let cookie = build_auth_cookie(&new_token);
@@ -0,0 +1,218 @@
+use crate::modules::api::{ApiErrorBody, ApiErrorDetails};
+use crate::modules::app::controller::{ErrorTemplate, NotFoundTemplate};
+use crate::modules::app::{AppErrorDetails, HxTriggerEvent, ToastLevel};
+use askama_axum::IntoResponse;
+use axum::extract::Request;
+use axum::http::header::ACCEPT;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Json;
+use std::collections::HashMap;
+use tracing::error;
+
+enum ErrorFormat {
+    Page,
+    Toast,
+    Json,
+}
+
+fn get_error_format(request: &Request) -> ErrorFormat {
+    let headers = request.headers();
+
+    if headers.contains_key("HX-Request") {
+        return ErrorFormat::Toast;
+    }
+
+    let accepts_json = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("application/json"));
+
+    if accepts_json || request.uri().path().starts_with("/api/") {
+        return ErrorFormat::Json;
+    }
+
+    ErrorFormat::Page
+}
+
+/// Renders `AppError` responses for whoever made the request: an error page
+/// for browsers, a toast for htmx and a JSON body for API clients.
+pub async fn render_errors(request: Request, next: Next) -> Response {
+    let format = get_error_format(&request);
+
+    let response = next.run(request).await;
+
+    let details = match response.extensions().get::<AppErrorDetails>() {
+        Some(details) => details.clone(),
+        None => return response,
+    };
+
+    render_error(format, details, response)
+}
+
+fn render_error(
+    format: ErrorFormat,
+    details: AppErrorDetails,
+    response: Response,
+) -> Response {
+    let status = response.status();
+
+    match format {
+        ErrorFormat::Page if status == StatusCode::NOT_FOUND => {
+            (status, NotFoundTemplate {}).into_response()
+        }
+        ErrorFormat::Page => {
+            let template = ErrorTemplate {
+                title: status
+                    .canonical_reason()
+                    .unwrap_or("Error")
+                    .to_string(),
+                message: details.message,
+            };
+
+            (status, template).into_response()
+        }
+        // htmx leaves the page as is on errors, the toast tells what happened
+        ErrorFormat::Toast => {
+            let event = HxTriggerEvent::ShowToast {
+                level: ToastLevel::Error,
+                message: details.message,
+            };
+
+            let event = match event.to_header_value() {
+                Ok(event) => event,
+                Err(err) => {
+                    error!("Failed to build error toast: {err}");
+                    return response;
+                }
+            };
+
+            let mut response = response;
+
+            response.headers_mut().insert("HX-Trigger", event);
+
+            response
+        }
+        ErrorFormat::Json => {
+            let body = ApiErrorBody {
+                error: ApiErrorDetails {
+                    code: details.code.to_string(),
+                    message: details.message,
+                    fields: HashMap::new(),
+                },
+            };
+
+            (status, Json(body)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use serde_json::Value;
+
+    use crate::modules::app::AppError;
+
+    use super::*;
+
+    fn request(headers: &[(&str, &str)], path: &str) -> Request {
+        let mut request = Request::builder().uri(path);
+
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        request.body(Body::empty()).unwrap()
+    }
+
+    fn render(format: ErrorFormat, err: AppError) -> Response {
+        let response = err.into_response();
+        let details =
+            response.extensions().get::<AppErrorDetails>().unwrap().clone();
+
+        render_error(format, details, response)
+    }
+
+    async fn read_body(response: Response) -> String {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn htmx_requests_get_toasts() {
+        let request = request(&[("HX-Request", "true")], "/api/v1/guilds");
+
+        assert!(matches!(get_error_format(&request), ErrorFormat::Toast));
+    }
+
+    #[test]
+    fn api_requests_get_json() {
+        let by_path = request(&[], "/api/v1/guilds");
+        let by_accept = request(&[("accept", "application/json")], "/guilds");
+
+        assert!(matches!(get_error_format(&by_path), ErrorFormat::Json));
+        assert!(matches!(get_error_format(&by_accept), ErrorFormat::Json));
+    }
+
+    #[test]
+    fn browsers_get_pages() {
+        let request = request(&[("accept", "text/html")], "/guilds");
+
+        assert!(matches!(get_error_format(&request), ErrorFormat::Page));
+    }
+
+    #[tokio::test]
+    async fn toasts_keep_the_status_and_carry_the_message() {
+        let response = render(
+            ErrorFormat::Toast,
+            AppError::Conflict("Topic was voted for already".to_string()),
+        );
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let event: Value = serde_json::from_str(
+            response.headers()["HX-Trigger"].to_str().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(event["show_toast"]["level"], "error");
+        assert_eq!(
+            event["show_toast"]["message"],
+            "Topic was voted for already"
+        );
+    }
+
+    #[tokio::test]
+    async fn json_bodies_carry_the_code_and_message() {
+        let response = render(
+            ErrorFormat::Json,
+            AppError::Validation("Text is too long".to_string()),
+        );
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: Value =
+            serde_json::from_str(&read_body(response).await).unwrap();
+
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["message"], "Text is too long");
+    }
+
+    #[tokio::test]
+    async fn pages_hide_internal_errors() {
+        let response = render(
+            ErrorFormat::Page,
+            AppError::Internal(anyhow::anyhow!("connection refused")),
+        );
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = read_body(response).await;
+
+        assert!(body.contains("Something went wrong"));
+        assert!(!body.contains("connection refused"));
+    }
+}
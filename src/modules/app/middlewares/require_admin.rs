@@ -0,0 +1,22 @@
+use crate::modules::app::app::App;
+use crate::modules::app::user_extractor::MaybeAuthenticated;
+use askama_axum::IntoResponse;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{Redirect, Response};
+use std::sync::Arc;
+
+pub async fn require_admin(
+    State(app): State<Arc<App>>,
+    MaybeAuthenticated(member): MaybeAuthenticated,
+    request: Request,
+    next: Next,
+) -> Response {
+    match member {
+        Some(member) if app.configuration.admin.is_admin(&member) => {
+            next.run(request).await
+        }
+        Some(_) => Redirect::to("/guilds").into_response(),
+        None => Redirect::to("/login").into_response(),
+    }
+}
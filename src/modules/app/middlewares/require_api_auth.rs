@@ -0,0 +1,18 @@
+use crate::modules::api::ApiError;
+use crate::modules::app::user_extractor::MaybeAuthenticated;
+use askama_axum::IntoResponse;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+
+pub async fn require_api_auth(
+    MaybeAuthenticated(member): MaybeAuthenticated,
+    request: Request,
+    next: Next,
+) -> Response {
+    if member.is_none() {
+        return ApiError::unauthorized().into_response();
+    }
+
+    next.run(request).await
+}
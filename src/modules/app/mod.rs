@@ -1,10 +1,18 @@
 pub mod app;
 pub mod controller;
 pub mod errors;
+pub mod event_bus;
+pub mod event_log;
+pub mod event_transport;
 pub mod events;
+pub mod mongo_event_transport;
 pub mod user_extractor;
 
+pub use event_bus::*;
+pub use event_log::*;
+pub use event_transport::*;
 pub use events::*;
+pub use mongo_event_transport::*;
 pub mod middlewares;
 
 pub use errors::*;
@@ -0,0 +1,243 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use bson::{doc, oid::ObjectId, Document};
+use futures_util::TryStreamExt;
+use mongodb::{
+    bson::DateTime,
+    change_stream::event::ResumeToken,
+    options::{ChangeStreamOptions, IndexOptions},
+    Collection, IndexModel,
+};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::time::sleep;
+use tracing::{error, warn};
+
+use crate::libs::mongo::MongoDatabase;
+
+use super::{Event, EventTransport};
+
+/// Published events only have to outlive the delay of the change streams.
+const EVENT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Where the change stream picks up after a reconnect. Events published
+/// while it was down are only recovered through a resume token, without one
+/// clients have to resync.
+#[derive(Default)]
+struct StreamPosition {
+    resume_token: Option<ResumeToken>,
+    has_been_connected: bool,
+}
+
+impl StreamPosition {
+    /// Takes note of a newly opened stream, returns whether events may have
+    /// been missed since the previous one.
+    fn connect(&mut self, resume_token: Option<ResumeToken>) -> bool {
+        let has_missed_events =
+            self.has_been_connected && self.resume_token.is_none();
+
+        self.has_been_connected = true;
+        self.advance(resume_token);
+
+        has_missed_events
+    }
+
+    fn advance(&mut self, resume_token: Option<ResumeToken>) {
+        if resume_token.is_some() {
+            self.resume_token = resume_token;
+        }
+    }
+
+    /// The token can't be resumed from anymore, e.g. the oplog moved past
+    /// it, so the next stream starts from now.
+    fn forget_resume_token(&mut self) {
+        self.resume_token = None;
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EventDocument {
+    pub _id: ObjectId,
+    pub event: Event,
+    pub created_at: DateTime,
+}
+
+/// Transport for several instances sharing a database. Events are inserted
+/// into a collection and every instance follows it with a change stream,
+/// which needs mongo to run as a replica set.
+pub struct MongoEventTransport {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+}
+
+impl MongoEventTransport {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let transport = MongoEventTransport {
+            database,
+            collection_name: String::from("events"),
+        };
+
+        let _ = transport.set_indexes().await;
+
+        transport
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let options = IndexOptions::builder()
+            .name("created_at".to_string())
+            .expire_after(EVENT_TTL)
+            .build();
+
+        let indexes = vec![IndexModel::builder()
+            .keys(doc! {"created_at":1})
+            .options(options)
+            .build()];
+
+        self.database
+            .create_indexes::<EventDocument>(&self.collection_name, indexes)
+            .await
+    }
+
+    /// Follows the collection until the change stream ends or fails. The
+    /// position survives between calls so reconnecting doesn't miss
+    /// anything the oplog still has.
+    async fn watch(
+        &self,
+        position: &mut StreamPosition,
+        deliver: &(dyn Fn(Event) + Send + Sync),
+    ) -> Result<()> {
+        let database = self.database.get_database_client()?;
+
+        // raw documents, so a single unreadable event doesn't break the stream
+        let collection: Collection<Document> =
+            database.collection(&self.collection_name);
+
+        let options = ChangeStreamOptions::builder()
+            .resume_after(position.resume_token.clone())
+            .build();
+
+        let mut change_stream = match collection
+            .watch(vec![doc! {"$match": {"operationType": "insert"}}], options)
+            .await
+        {
+            Ok(change_stream) => change_stream,
+            Err(err) if position.resume_token.is_some() => {
+                warn!("Failed to resume the events change stream, clients have to resync: {err}");
+
+                position.forget_resume_token();
+
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if position.connect(change_stream.resume_token()) {
+            deliver(Event::Resync);
+        }
+
+        while let Some(change) = change_stream.try_next().await? {
+            if let Some(document) = change.full_document {
+                match bson::from_document::<EventDocument>(document) {
+                    Ok(document) => deliver(document.event),
+                    Err(err) => error!("Failed to read event document: {err}"),
+                }
+            }
+
+            position.advance(change_stream.resume_token());
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventTransport for MongoEventTransport {
+    fn get_transport_name(&self) -> String {
+        "mongo".to_string()
+    }
+
+    async fn publish(&self, event: Event) -> Result<()> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<EventDocument> =
+            database.collection(&self.collection_name);
+
+        collection
+            .insert_one(
+                EventDocument {
+                    _id: ObjectId::new(),
+                    event,
+                    created_at: DateTime::now(),
+                },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn listen(
+        &self,
+        deliver: &(dyn Fn(Event) + Send + Sync),
+    ) -> Result<()> {
+        let mut position = StreamPosition::default();
+
+        loop {
+            if let Err(err) = self.watch(&mut position, deliver).await {
+                warn!("Events change stream failed, reconnecting: {err}");
+
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resume_token(data: &str) -> Option<ResumeToken> {
+        Some(bson::from_document(doc! {"_data": data}).unwrap())
+    }
+
+    #[test]
+    fn the_first_stream_has_nothing_to_catch_up_on() {
+        let mut position = StreamPosition::default();
+
+        assert!(!position.connect(None));
+        assert_eq!(position.resume_token, None);
+    }
+
+    #[test]
+    fn streams_resume_after_the_latest_token() {
+        let mut position = StreamPosition::default();
+
+        assert!(!position.connect(resume_token("a")));
+
+        position.advance(resume_token("b"));
+        position.advance(None);
+
+        assert_eq!(position.resume_token, resume_token("b"));
+        assert!(!position.connect(resume_token("b")));
+    }
+
+    #[test]
+    fn streams_that_cant_resume_make_clients_resync_once() {
+        let mut position = StreamPosition::default();
+
+        assert!(!position.connect(resume_token("a")));
+
+        position.forget_resume_token();
+
+        assert!(position.connect(resume_token("b")));
+        assert!(!position.connect(resume_token("c")));
+    }
+
+    #[test]
+    fn streams_without_a_token_make_clients_resync_on_reconnect() {
+        let mut position = StreamPosition::default();
+
+        assert!(!position.connect(None));
+        assert!(position.connect(None));
+    }
+}
@@ -1,38 +1,180 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{
-    decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey,
+    Header, Validation,
 };
 use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::configuration::{
+    AuthConfiguration, AuthKeyAlgorithm, AuthKeyConfiguration,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenClaims {
     pub sub: usize,
     pub iat: usize,
     pub exp: usize,
+    /// When the user originally logged in. Preserved across refreshes so
+    /// the session can't be extended forever.
+    #[serde(default)]
+    pub auth_time: usize,
+}
+
+impl TokenClaims {
+    fn session_started_at(&self) -> usize {
+        if self.auth_time == 0 {
+            return self.iat;
+        }
+
+        self.auth_time
+    }
+}
+
+struct AuthKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+}
+
+impl AuthKey {
+    fn from_configuration(
+        configuration: &AuthKeyConfiguration,
+    ) -> Result<Self> {
+        let read_pem = |path: &Option<String>| -> Result<Option<Vec<u8>>> {
+            match path {
+                Some(path) => Ok(Some(fs::read(path).with_context(|| {
+                    format!("Failed to read auth key file {path}")
+                })?)),
+                None => Ok(None),
+            }
+        };
+
+        let (algorithm, encoding_key, decoding_key) = match configuration
+            .algorithm
+        {
+            AuthKeyAlgorithm::HS256 => {
+                let secret =
+                    configuration.secret.as_ref().ok_or_else(|| {
+                        anyhow!(
+                            "Auth key {} is missing a secret",
+                            configuration.kid
+                        )
+                    })?;
+
+                (
+                    Algorithm::HS256,
+                    Some(EncodingKey::from_secret(secret.as_bytes())),
+                    DecodingKey::from_secret(secret.as_bytes()),
+                )
+            }
+            AuthKeyAlgorithm::RS256 | AuthKeyAlgorithm::EdDSA => {
+                let public_key = read_pem(&configuration.public_key_path)?
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Auth key {} is missing a public key",
+                            configuration.kid
+                        )
+                    })?;
+
+                let private_key = read_pem(&configuration.private_key_path)?;
+
+                if configuration.algorithm == AuthKeyAlgorithm::RS256 {
+                    (
+                        Algorithm::RS256,
+                        private_key
+                            .map(|pem| EncodingKey::from_rsa_pem(&pem))
+                            .transpose()?,
+                        DecodingKey::from_rsa_pem(&public_key)?,
+                    )
+                } else {
+                    (
+                        Algorithm::EdDSA,
+                        private_key
+                            .map(|pem| EncodingKey::from_ed_pem(&pem))
+                            .transpose()?,
+                        DecodingKey::from_ed_pem(&public_key)?,
+                    )
+                }
+            }
+        };
+
+        Ok(Self {
+            kid: configuration.kid.clone(),
+            algorithm,
+            encoding_key,
+            decoding_key,
+        })
+    }
 }
 
 pub struct AuthService {
     expire_in: Duration,
-    secret: String,
+    refresh_after: Duration,
+    session_lifetime: Duration,
+    keys: Vec<AuthKey>,
 }
 
 impl AuthService {
-    pub fn new(expire_in: Duration, secret: String) -> Self {
-        Self { expire_in, secret }
+    pub fn new(configuration: &AuthConfiguration) -> Result<Self> {
+        let keys = configuration
+            .keys
+            .iter()
+            .map(AuthKey::from_configuration)
+            .collect::<Result<Vec<AuthKey>>>()?;
+
+        match keys.first() {
+            Some(key) if key.encoding_key.is_some() => {}
+            Some(key) => bail!(
+                "Auth key {} is used for signing but has no private key",
+                key.kid
+            ),
+            None => bail!("At least one auth key is required"),
+        }
+
+        Ok(Self {
+            expire_in: configuration.expire_in_hours,
+            refresh_after: configuration.refresh_after,
+            session_lifetime: configuration.session_lifetime,
+            keys,
+        })
     }
 
     pub fn create_token(&self, user_id: usize) -> Result<String> {
+        let now = Utc::now().timestamp() as usize;
+
+        self.create_session_token(user_id, now)
+    }
+
+    fn create_session_token(
+        &self,
+        user_id: usize,
+        auth_time: usize,
+    ) -> Result<String> {
+        let signing_key = &self.keys[0];
+
         let now = Utc::now();
         let iat = now.timestamp() as usize;
-        let exp = (now + self.expire_in).timestamp() as usize;
-        let claims: TokenClaims = TokenClaims { sub: user_id, exp, iat };
+        let session_ends_at =
+            auth_time + self.session_lifetime.num_seconds() as usize;
+        let exp =
+            ((now + self.expire_in).timestamp() as usize).min(session_ends_at);
+        let claims: TokenClaims =
+            TokenClaims { sub: user_id, exp, iat, auth_time };
+
+        let mut header = Header::new(signing_key.algorithm);
+        header.kid = Some(signing_key.kid.clone());
+
+        let encoding_key = match &signing_key.encoding_key {
+            Some(encoding_key) => encoding_key,
+            None => {
+                bail!("Signing key {} has no private key", signing_key.kid)
+            }
+        };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.secret.as_bytes()),
-        )?;
+        let token = encode(&header, &claims, encoding_key)?;
 
         Ok(token)
     }
@@ -41,13 +183,49 @@ impl AuthService {
         &self,
         token: T,
     ) -> Result<TokenClaims> {
-        let decoded = decode::<TokenClaims>(
-            &token.into(),
-            &DecodingKey::from_secret(self.secret.as_bytes()),
-            &Validation::new(Algorithm::HS256),
-        )?;
+        let token = token.into();
+
+        let header = decode_header(&token)?;
+
+        // Tokens issued before key rotation existed carry no `kid`, so every
+        // key with a matching algorithm is a candidate.
+        let candidates = self.keys.iter().filter(|key| {
+            key.algorithm == header.alg
+                && header.kid.as_ref().is_none_or(|kid| kid == &key.kid)
+        });
+
+        let mut last_error = None;
 
-        Ok(decoded.claims)
+        for key in candidates {
+            match decode::<TokenClaims>(
+                &token,
+                &key.decoding_key,
+                &Validation::new(key.algorithm),
+            ) {
+                Ok(decoded) => {
+                    let claims = decoded.claims;
+
+                    if self.is_session_expired(&claims) {
+                        bail!("Session of user {} has expired", claims.sub);
+                    }
+
+                    return Ok(claims);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        match last_error {
+            Some(err) => Err(err.into()),
+            None => bail!("No auth key matches token header {header:?}"),
+        }
+    }
+
+    fn is_session_expired(&self, claims: &TokenClaims) -> bool {
+        let session_ends_at = claims.session_started_at()
+            + self.session_lifetime.num_seconds() as usize;
+
+        Utc::now().timestamp() as usize >= session_ends_at
     }
 
     pub fn refresh_token_if_needed(
@@ -62,10 +240,14 @@ impl AuthService {
         let now = Utc::now();
         let diff = now - issued_at;
 
-        let should_refresh = diff.num_hours() > 2;
+        let should_refresh =
+            diff > self.refresh_after && !self.is_session_expired(&claims);
 
         if should_refresh {
-            let new_token = self.create_token(claims.sub)?;
+            let new_token = self.create_session_token(
+                claims.sub,
+                claims.session_started_at(),
+            )?;
 
             return Ok(Some(new_token));
         }
@@ -73,3 +255,210 @@ impl AuthService {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(kid: &str, secret: &str) -> AuthKeyConfiguration {
+        AuthKeyConfiguration {
+            kid: kid.to_string(),
+            algorithm: AuthKeyAlgorithm::HS256,
+            secret: Some(secret.to_string()),
+            private_key_path: None,
+            public_key_path: None,
+        }
+    }
+
+    fn service(keys: Vec<AuthKeyConfiguration>) -> AuthService {
+        AuthService::new(&AuthConfiguration {
+            expire_in_hours: Duration::hours(1),
+            refresh_after: Duration::minutes(10),
+            session_lifetime: Duration::hours(8),
+            keys,
+        })
+        .unwrap()
+    }
+
+    fn encode_claims(
+        kid: Option<&str>,
+        secret: &str,
+        claims: &TokenClaims,
+    ) -> String {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = kid.map(str::to_string);
+
+        encode(&header, claims, &EncodingKey::from_secret(secret.as_bytes()))
+            .unwrap()
+    }
+
+    fn hours_ago(hours: i64) -> usize {
+        (Utc::now() - Duration::hours(hours)).timestamp() as usize
+    }
+
+    #[test]
+    fn new_requires_a_key() {
+        assert!(AuthService::new(&AuthConfiguration {
+            expire_in_hours: Duration::hours(1),
+            refresh_after: Duration::minutes(10),
+            session_lifetime: Duration::hours(8),
+            keys: Vec::new(),
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn tokens_are_signed_with_the_first_key() {
+        let service =
+            service(vec![key("new", "new secret"), key("old", "old secret")]);
+
+        let token = service.create_token(7).unwrap();
+
+        assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some("new"));
+        assert_eq!(service.decode_token(token).unwrap().sub, 7);
+    }
+
+    #[test]
+    fn old_kid_tokens_verify_during_rotation() {
+        let before_rotation = service(vec![key("old", "old secret")]);
+        let during_rotation =
+            service(vec![key("new", "new secret"), key("old", "old secret")]);
+
+        let token = before_rotation.create_token(7).unwrap();
+
+        assert_eq!(during_rotation.decode_token(token).unwrap().sub, 7);
+    }
+
+    #[test]
+    fn tokens_of_removed_keys_are_rejected() {
+        let before_rotation = service(vec![key("old", "old secret")]);
+        let after_rotation = service(vec![key("new", "new secret")]);
+
+        let token = before_rotation.create_token(7).unwrap();
+
+        assert!(after_rotation.decode_token(token).is_err());
+    }
+
+    #[test]
+    fn unknown_kid_is_rejected_even_with_a_known_secret() {
+        let service = service(vec![key("current", "secret")]);
+
+        let token = encode_claims(
+            Some("unknown"),
+            "secret",
+            &TokenClaims {
+                sub: 7,
+                iat: hours_ago(0),
+                exp: hours_ago(-1),
+                auth_time: hours_ago(0),
+            },
+        );
+
+        assert!(service.decode_token(token).is_err());
+    }
+
+    #[test]
+    fn tokens_without_kid_are_checked_against_every_key() {
+        let service =
+            service(vec![key("new", "new secret"), key("old", "old secret")]);
+
+        let token = encode_claims(
+            None,
+            "old secret",
+            &TokenClaims {
+                sub: 7,
+                iat: hours_ago(0),
+                exp: hours_ago(-1),
+                auth_time: 0,
+            },
+        );
+
+        assert_eq!(service.decode_token(token).unwrap().sub, 7);
+    }
+
+    #[test]
+    fn tokens_expire_at_the_end_of_the_session() {
+        let service = service(vec![key("current", "secret")]);
+
+        // the session ends in 30 minutes, before the usual hour of validity
+        let auth_time = (Utc::now() - Duration::hours(8)
+            + Duration::minutes(30))
+        .timestamp() as usize;
+
+        let token = service.create_session_token(7, auth_time).unwrap();
+        let claims = service.decode_token(token).unwrap();
+
+        assert_eq!(claims.auth_time, auth_time);
+        assert_eq!(
+            claims.exp,
+            auth_time + Duration::hours(8).num_seconds() as usize
+        );
+    }
+
+    #[test]
+    fn sessions_older_than_the_lifetime_are_refused() {
+        let service = service(vec![key("current", "secret")]);
+
+        // refreshed recently, so not expired itself, but logged in 9 hours ago
+        let token = encode_claims(
+            Some("current"),
+            "secret",
+            &TokenClaims {
+                sub: 7,
+                iat: hours_ago(0),
+                exp: hours_ago(-1),
+                auth_time: hours_ago(9),
+            },
+        );
+
+        assert!(service.decode_token(token).is_err());
+    }
+
+    #[test]
+    fn refresh_keeps_the_login_time() {
+        let service = service(vec![key("current", "secret")]);
+
+        let auth_time = hours_ago(3);
+
+        let claims = TokenClaims {
+            sub: 7,
+            iat: hours_ago(1),
+            exp: hours_ago(0) + 60,
+            auth_time,
+        };
+
+        let token = service.refresh_token_if_needed(claims).unwrap().unwrap();
+        let refreshed = service.decode_token(token).unwrap();
+
+        assert_eq!(refreshed.auth_time, auth_time);
+        assert!(refreshed.iat > hours_ago(1));
+    }
+
+    #[test]
+    fn refresh_is_refused_after_the_session_lifetime() {
+        let service = service(vec![key("current", "secret")]);
+
+        let claims = TokenClaims {
+            sub: 7,
+            iat: hours_ago(1),
+            exp: hours_ago(0) + 60,
+            auth_time: hours_ago(9),
+        };
+
+        assert!(service.refresh_token_if_needed(claims).unwrap().is_none());
+    }
+
+    #[test]
+    fn recent_tokens_are_not_refreshed() {
+        let service = service(vec![key("current", "secret")]);
+
+        let claims = TokenClaims {
+            sub: 7,
+            iat: hours_ago(0),
+            exp: hours_ago(-1),
+            auth_time: hours_ago(0),
+        };
+
+        assert!(service.refresh_token_if_needed(claims).unwrap().is_none());
+    }
+}
@@ -0,0 +1,2 @@
+/// Number of topics listed by `/guild top`.
+pub const CHAT_TOP_TOPICS_LIMIT: usize = 5;
@@ -0,0 +1,52 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+use tracing::{error, warn};
+
+use crate::{
+    libs::{axum::Form, utils::constant_time_eq},
+    modules::app::{app::App, AppError},
+};
+
+use super::{SlashCommandRequest, SlashCommandResponse};
+
+pub async fn handle_slash_command(
+    State(app): State<Arc<App>>,
+    Form(request): Form<SlashCommandRequest>,
+) -> Result<Response, AppError> {
+    let (chat, chat_service) =
+        match (&app.configuration.chat, &app.chat_service) {
+            (Some(chat), Some(chat_service)) => (chat, chat_service),
+            _ => return Ok(StatusCode::NOT_FOUND.into_response()),
+        };
+
+    let is_token_valid = chat.command_tokens.iter().any(|token| {
+        constant_time_eq(request.token.as_bytes(), token.as_bytes())
+    });
+
+    if !is_token_valid {
+        warn!("Rejected slash command with invalid token");
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    }
+
+    let text = match chat_service
+        .handle_command(&request.user_name, &request.command, &request.text)
+        .await
+    {
+        Ok(text) => text,
+        Err(err) => {
+            error!(
+                "Failed to handle slash command of {}: {err}",
+                request.user_name
+            );
+
+            "Something went wrong, try again in a moment".to_string()
+        }
+    };
+
+    Ok(Json(SlashCommandResponse::ephemeral(text)).into_response())
+}
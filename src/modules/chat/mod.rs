@@ -0,0 +1,8 @@
+pub mod constants;
+pub mod controller;
+pub mod service;
+pub mod types;
+
+pub use controller::*;
+pub use service::ChatService;
+pub use types::*;
@@ -0,0 +1,412 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use bson::oid::ObjectId;
+use validator::Validate;
+
+use crate::{
+    libs::{
+        gitlab_api::gitlab_api::Member,
+        utils::{escape_slack_text, format_slack_link},
+    },
+    modules::{
+        guild::{Guild, GuildsService},
+        member::MemberDirectory,
+        topic::{
+            quote_topic_text,
+            types::{
+                PaginationParameters, TopicFormDTO, TopicStatus,
+                VoteTopicResult,
+            },
+            TopicsService,
+        },
+    },
+};
+
+use super::{constants::CHAT_TOP_TOPICS_LIMIT, ChatCommand};
+
+/// Answers `/guild ...` slash commands on behalf of the member who ran them.
+pub struct ChatService {
+    guilds_service: Arc<GuildsService>,
+    topics_service: Arc<TopicsService>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    app_url: String,
+}
+
+impl ChatService {
+    pub fn new(
+        guilds_service: Arc<GuildsService>,
+        topics_service: Arc<TopicsService>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        app_url: String,
+    ) -> Self {
+        Self { guilds_service, topics_service, member_directory, app_url }
+    }
+
+    /// Returns the reply for the member, errors are only returned when
+    /// something went wrong on our side.
+    pub async fn handle_command(
+        &self,
+        username: &str,
+        command: &str,
+        text: &str,
+    ) -> Result<String> {
+        let command_name = match command.trim() {
+            "" => "/guild",
+            command => command,
+        };
+
+        let member = match self.get_member_by_username(username).await {
+            Some(member) => member,
+            None => {
+                return Ok(format!(
+                    "There is no guildhub member with the username {}, chat and guildhub usernames have to match",
+                    escape_slack_text(username)
+                ))
+            }
+        };
+
+        match ChatCommand::parse(text) {
+            ChatCommand::Top(arguments) => {
+                self.get_top_topics(&member, &arguments, command_name).await
+            }
+            ChatCommand::Propose(arguments) => {
+                self.propose_topic(&member, &arguments, command_name).await
+            }
+            ChatCommand::Vote(arguments) => {
+                self.vote_for_topic(&member, &arguments).await
+            }
+            ChatCommand::Help => Ok(get_help(command_name)),
+        }
+    }
+
+    async fn get_member_by_username(&self, username: &str) -> Option<Member> {
+        let username = username.trim().trim_start_matches('@');
+
+        self.member_directory.get_all_members().await.into_iter().find(
+            |member| {
+                !member.is_former_member
+                    && member.username.eq_ignore_ascii_case(username)
+            },
+        )
+    }
+
+    fn get_guild_link(&self, guild: &Guild) -> String {
+        format_slack_link(
+            &format!("{}/guilds/{}", self.app_url, guild.id),
+            &guild.name,
+        )
+    }
+
+    async fn get_top_topics(
+        &self,
+        member: &Member,
+        arguments: &str,
+        command_name: &str,
+    ) -> Result<String> {
+        let guilds = self.guilds_service.get_guilds(member.id).await?;
+
+        let guild = match split_guild_name(&guilds, arguments) {
+            Some((guild, "")) => guild,
+            _ => return Ok(get_unknown_guild_message(&guilds, arguments)),
+        };
+
+        let topics = self
+            .topics_service
+            .get_topics_by_guild_id(
+                member.id,
+                &guild.id,
+                PaginationParameters { limit: CHAT_TOP_TOPICS_LIMIT, skip: 0 },
+                guild.clone(),
+                TopicStatus::Created,
+            )
+            .await?;
+
+        if topics.is_empty() {
+            return Ok(format!(
+                "{} has no topics yet, propose one with `{command_name} propose {} <text>`",
+                self.get_guild_link(guild),
+                escape_slack_text(&guild.name)
+            ));
+        }
+
+        let lines = topics
+            .iter()
+            .enumerate()
+            .map(|(index, topic)| {
+                format!(
+                    "{}. {} · {} votes{} · `{}`",
+                    index + 1,
+                    quote_topic(&topic.text),
+                    topic.upvoted_by_users.len(),
+                    if topic.is_upvoted_by_current_user {
+                        " (yours)"
+                    } else {
+                        ""
+                    },
+                    topic.id
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(format!(
+            "Top topics of {}:\n{lines}\nVote with `{command_name} vote <topic id>`",
+            self.get_guild_link(guild)
+        ))
+    }
+
+    async fn propose_topic(
+        &self,
+        member: &Member,
+        arguments: &str,
+        command_name: &str,
+    ) -> Result<String> {
+        let guilds = self.guilds_service.get_guilds(member.id).await?;
+
+        let (guild, text) = match split_guild_name(&guilds, arguments) {
+            Some(split) => split,
+            None => return Ok(get_unknown_guild_message(&guilds, arguments)),
+        };
+
+        let form = TopicFormDTO {
+            text: text.to_string(),
+            will_be_presented_by_the_creator: None,
+        };
+
+        if form.validate().is_err() {
+            return Ok(format!(
+                "Topics need a text between 2 and 200 characters: `{command_name} propose {} <text>`",
+                escape_slack_text(&guild.name)
+            ));
+        }
+
+        let topic =
+            self.topics_service.create_topic(form, guild, member.id).await?;
+
+        Ok(format!(
+            "Proposed {} in {}, its id is `{}`",
+            quote_topic(&topic.text),
+            self.get_guild_link(guild),
+            topic.id
+        ))
+    }
+
+    async fn vote_for_topic(
+        &self,
+        member: &Member,
+        topic_id: &str,
+    ) -> Result<String> {
+        let not_found_message = format!(
+            "There is no topic with the id `{}` in your guilds",
+            escape_slack_text(topic_id)
+        );
+
+        if ObjectId::from_str(topic_id).is_err() {
+            return Ok(not_found_message);
+        }
+
+        let topic = match self.topics_service.get_topic_by_id(topic_id).await?
+        {
+            Some(topic) => topic,
+            None => return Ok(not_found_message),
+        };
+
+        let guild = match self
+            .guilds_service
+            .get_guild(member.clone(), &topic.guild_id)
+            .await?
+        {
+            Some(guild) => guild,
+            None => return Ok(not_found_message),
+        };
+
+        if topic.status != TopicStatus::Created {
+            return Ok(format!(
+                "{} is archived and can't be voted for anymore",
+                quote_topic(&topic.text)
+            ));
+        }
+
+        if topic.upvoted_by_users_ids.contains(&member.id) {
+            return Ok(format!(
+                "You already voted for {}",
+                quote_topic(&topic.text)
+            ));
+        }
+
+        let VoteTopicResult { previously_voted, topic } = self
+            .topics_service
+            .upvote_topic(&guild, topic.id, member.id)
+            .await?;
+
+        let mut message = format!(
+            "Voted for {} in {}, it has {} votes now",
+            quote_topic(&topic.text),
+            self.get_guild_link(&guild),
+            topic.upvoted_by_users.len()
+        );
+
+        // members have one vote per guild
+        if let Some(previously_voted) = previously_voted {
+            message.push_str(&format!(
+                ". Your vote for {} was moved",
+                quote_topic(&previously_voted.text)
+            ));
+        }
+
+        Ok(message)
+    }
+}
+
+/// Finds the guild whose name starts the arguments, names may contain spaces
+/// so the longest match wins. A name in double quotes has to match exactly.
+fn split_guild_name<'a>(
+    guilds: &'a [Guild],
+    arguments: &'a str,
+) -> Option<(&'a Guild, &'a str)> {
+    if let Some(quoted) = arguments.strip_prefix('"') {
+        let (name, rest) = quoted.split_once('"')?;
+
+        return guilds
+            .iter()
+            .find(|guild| guild.name.trim().eq_ignore_ascii_case(name.trim()))
+            .map(|guild| (guild, rest.trim()));
+    }
+
+    guilds
+        .iter()
+        .filter_map(|guild| {
+            let name = guild.name.trim();
+            let prefix = arguments.get(..name.len())?;
+            let rest = &arguments[name.len()..];
+
+            (prefix.eq_ignore_ascii_case(name)
+                && (rest.is_empty() || rest.starts_with(char::is_whitespace)))
+            .then(|| (guild, rest.trim()))
+        })
+        .max_by_key(|(guild, _)| guild.name.trim().len())
+}
+
+fn get_unknown_guild_message(guilds: &[Guild], arguments: &str) -> String {
+    if guilds.is_empty() {
+        return "You are not a member of any guild yet".to_string();
+    }
+
+    let names = guilds
+        .iter()
+        .map(|guild| escape_slack_text(&guild.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match arguments {
+        "" => format!("Which guild? Your guilds are: {names}"),
+        _ => format!(
+            "None of your guilds matches \"{}\", your guilds are: {names}",
+            escape_slack_text(arguments)
+        ),
+    }
+}
+
+/// Quoted topic text, safe to put in a chat message.
+fn quote_topic(text: &str) -> String {
+    escape_slack_text(&quote_topic_text(text))
+}
+
+fn get_help(command_name: &str) -> String {
+    [
+        format!("`{command_name} top <guild>` lists the most voted topics of a guild"),
+        format!("`{command_name} propose <guild> <text>` proposes a new topic"),
+        format!("`{command_name} vote <topic id>` votes for a topic, it moves your vote if you voted for another topic of the guild"),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn guilds(names: &[&str]) -> Vec<Guild> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Guild {
+                id: index.to_string(),
+                name: name.to_string(),
+                topics_count: 0,
+                members: vec![],
+                created_by_user: Member::default(),
+                updated_at: Utc::now(),
+                created_at: Utc::now(),
+            })
+            .collect()
+    }
+
+    fn split<'a>(
+        guilds: &'a [Guild],
+        arguments: &'a str,
+    ) -> Option<(&'a str, &'a str)> {
+        split_guild_name(guilds, arguments)
+            .map(|(guild, rest)| (guild.name.as_str(), rest))
+    }
+
+    #[test]
+    fn guild_names_are_matched_case_insensitively() {
+        let guilds = guilds(&["Rust", "Frontend"]);
+
+        assert_eq!(
+            split(&guilds, "rust Async traits"),
+            Some(("Rust", "Async traits"))
+        );
+        assert_eq!(split(&guilds, "FRONTEND"), Some(("Frontend", "")));
+    }
+
+    #[test]
+    fn the_longest_guild_name_wins() {
+        let guilds = guilds(&["Rust", "Rust Embedded"]);
+
+        assert_eq!(
+            split(&guilds, "rust embedded Probes"),
+            Some(("Rust Embedded", "Probes"))
+        );
+        assert_eq!(split(&guilds, "Rust Embassy"), Some(("Rust", "Embassy")));
+    }
+
+    #[test]
+    fn guild_names_only_match_whole_words() {
+        let guilds = guilds(&["Rust"]);
+
+        assert_eq!(split(&guilds, "Rustacean meetup"), None);
+        assert_eq!(split(&guilds, ""), None);
+        assert_eq!(split(&guilds, "Rüst"), None);
+    }
+
+    #[test]
+    fn unknown_guild_messages_escape_chat_markup() {
+        let guilds = guilds(&["<!channel>", "R&D"]);
+
+        assert_eq!(
+            get_unknown_guild_message(&guilds, "<@U123>"),
+            "None of your guilds matches \"&lt;@U123&gt;\", your guilds are: &lt;!channel&gt;, R&amp;D"
+        );
+    }
+
+    #[test]
+    fn quoted_topics_escape_chat_markup() {
+        assert_eq!(quote_topic(" <!here> "), "\"&lt;!here&gt;\"");
+    }
+
+    #[test]
+    fn quoted_guild_names_have_to_match_exactly() {
+        let guilds = guilds(&["Rust", "Rust Embedded"]);
+
+        assert_eq!(
+            split(&guilds, "\"rust\" Embedded HALs"),
+            Some(("Rust", "Embedded HALs"))
+        );
+        assert_eq!(split(&guilds, "\"Rust Emb\" HALs"), None);
+        assert_eq!(split(&guilds, "\"Rust HALs"), None);
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+/// Form encoded request sent by Mattermost and Slack, fields we don't use
+/// are left out.
+#[derive(Deserialize, Debug)]
+pub struct SlashCommandRequest {
+    pub token: String,
+    pub user_name: String,
+    #[serde(default)]
+    pub command: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlashCommandResponse {
+    pub response_type: String,
+    pub text: String,
+}
+
+impl SlashCommandResponse {
+    /// Only shown to the member who ran the command.
+    pub fn ephemeral(text: String) -> Self {
+        Self { response_type: "ephemeral".to_string(), text }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChatCommand {
+    Top(String),
+    Propose(String),
+    Vote(String),
+    Help,
+}
+
+impl ChatCommand {
+    pub fn parse(text: &str) -> ChatCommand {
+        let text = text.trim();
+
+        let (name, arguments) =
+            text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+        let arguments = arguments.trim().to_string();
+
+        match name.to_lowercase().as_str() {
+            "top" => ChatCommand::Top(arguments),
+            "propose" => ChatCommand::Propose(arguments),
+            "vote" => ChatCommand::Vote(arguments),
+            _ => ChatCommand::Help,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed_with_their_arguments() {
+        assert_eq!(
+            ChatCommand::parse("top Rust"),
+            ChatCommand::Top("Rust".to_string())
+        );
+        assert_eq!(
+            ChatCommand::parse("  PROPOSE\tRust  Async traits  "),
+            ChatCommand::Propose("Rust  Async traits".to_string())
+        );
+        assert_eq!(
+            ChatCommand::parse("vote"),
+            ChatCommand::Vote(String::new())
+        );
+    }
+
+    #[test]
+    fn unknown_commands_show_the_help() {
+        assert_eq!(ChatCommand::parse(""), ChatCommand::Help);
+        assert_eq!(ChatCommand::parse("help"), ChatCommand::Help);
+        assert_eq!(ChatCommand::parse("topics Rust"), ChatCommand::Help);
+    }
+}
@@ -0,0 +1,7 @@
+pub const DIGEST_TOPICS_LIMIT: usize = 5;
+/// New topics and upcoming sessions are looked up this many days from now.
+pub const DIGEST_PERIOD_DAYS: i64 = 7;
+/// Digests that failed to send are tried again this many times, waiting
+/// `DIGEST_RETRY_DELAY_MINUTES` in between.
+pub const DIGEST_MAX_RETRIES: u32 = 3;
+pub const DIGEST_RETRY_DELAY_MINUTES: i64 = 15;
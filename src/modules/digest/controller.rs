@@ -0,0 +1,28 @@
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+};
+use std::sync::Arc;
+
+use crate::modules::app::{app::App, user_extractor::Authenticated, AppError};
+
+/// Shows the digest the user would get this week, as rendered in the email.
+pub async fn get_digest_preview(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let digest_service = match &app.digest_service {
+        Some(digest_service) => digest_service,
+        None => {
+            return Err(AppError::Unavailable(
+                "Emails are disabled".to_string(),
+            ))
+        }
+    };
+
+    let digest = digest_service.get_digest(user).await?;
+
+    let (html_body, _) = digest_service.render(&digest)?;
+
+    Ok(Html(html_body))
+}
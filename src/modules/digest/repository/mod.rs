@@ -0,0 +1,4 @@
+pub mod repository;
+pub use repository::*;
+pub mod model;
+pub use model::*;
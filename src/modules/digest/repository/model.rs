@@ -0,0 +1,14 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// Marks the digest of a week as sent to a user, so several instances don't
+/// send it twice.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DigestDocument {
+    pub _id: ObjectId,
+    pub user_id: usize,
+    /// ISO week, e.g. `2024-W42`.
+    pub week: String,
+    pub sent_at: DateTime,
+}
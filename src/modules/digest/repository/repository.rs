@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use crate::libs::mongo::MongoDatabase;
+use anyhow::Result;
+use bson::{doc, oid::ObjectId};
+use mongodb::{
+    error::{ErrorKind, WriteFailure},
+    options::IndexOptions,
+    Collection, IndexModel,
+};
+
+use super::DigestDocument;
+
+const DUPLICATE_KEY_ERROR_CODE: i32 = 11000;
+
+pub struct DigestsRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+}
+
+impl DigestsRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = DigestsRepository {
+            database,
+            collection_name: String::from("digests"),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let options = IndexOptions::builder()
+            .name("user_id_week".to_string())
+            .unique(true)
+            .build();
+
+        let indexes = vec![IndexModel::builder()
+            .keys(doc! {"user_id":1, "week":1})
+            .options(options)
+            .build()];
+
+        self.database
+            .create_indexes::<DigestDocument>(&self.collection_name, indexes)
+            .await
+    }
+
+    /// Returns false when the digest of the week was already claimed.
+    pub async fn claim_digest(
+        &self,
+        user_id: usize,
+        week: &str,
+    ) -> Result<bool> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<DigestDocument> =
+            database.collection(&self.collection_name);
+
+        let document = DigestDocument {
+            _id: ObjectId::new(),
+            user_id,
+            week: week.to_string(),
+            sent_at: bson::DateTime::now(),
+        };
+
+        match collection.insert_one(document, None).await {
+            Ok(_) => Ok(true),
+            Err(err) => match *err.kind {
+                ErrorKind::Write(WriteFailure::WriteError(
+                    ref write_error,
+                )) if write_error.code == DUPLICATE_KEY_ERROR_CODE => {
+                    Ok(false)
+                }
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    /// Gives the claim back so the digest can be sent again.
+    pub async fn release_digest(
+        &self,
+        user_id: usize,
+        week: &str,
+    ) -> Result<()> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<DigestDocument> =
+            database.collection(&self.collection_name);
+
+        collection
+            .delete_one(doc! {"user_id": user_id as u32, "week": week}, None)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+use std::{cmp::Reverse, collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use askama::Template;
+use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
+use tokio::time::sleep;
+use tracing::{error, info};
+
+use crate::{
+    libs::{gitlab_api::gitlab_api::Member, mailer::Mailer},
+    modules::{
+        guild::GuildsService,
+        member::MemberDirectory,
+        notification::NotificationsService,
+        session::SessionsService,
+        topic::{
+            quote_topic_text,
+            types::{PaginationParameters, TopicStatus},
+            TopicsService,
+        },
+    },
+};
+
+use super::{
+    constants::{
+        DIGEST_MAX_RETRIES, DIGEST_PERIOD_DAYS, DIGEST_RETRY_DELAY_MINUTES,
+        DIGEST_TOPICS_LIMIT,
+    },
+    Digest, DigestEmailTemplate, DigestEmailTextTemplate, DigestSession,
+    DigestTopic, DigestsRepository,
+};
+
+/// Sends every member a weekly email about the guilds they belong to.
+pub struct DigestService {
+    mailer: Arc<Mailer>,
+    repository: Arc<DigestsRepository>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    guilds_service: Arc<GuildsService>,
+    topics_service: Arc<TopicsService>,
+    sessions_service: Arc<SessionsService>,
+    notifications_service: Arc<NotificationsService>,
+}
+
+impl DigestService {
+    pub fn new(
+        mailer: Arc<Mailer>,
+        repository: Arc<DigestsRepository>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        guilds_service: Arc<GuildsService>,
+        topics_service: Arc<TopicsService>,
+        sessions_service: Arc<SessionsService>,
+        notifications_service: Arc<NotificationsService>,
+    ) -> Self {
+        Self {
+            mailer,
+            repository,
+            member_directory,
+            guilds_service,
+            topics_service,
+            sessions_service,
+            notifications_service,
+        }
+    }
+
+    pub async fn get_digest(&self, user: Member) -> Result<Digest> {
+        let now = Utc::now();
+
+        let guilds = self.guilds_service.get_guilds(user.id).await?;
+
+        let guild_ids: Vec<String> =
+            guilds.iter().map(|guild| guild.id.clone()).collect();
+
+        let guild_names: HashMap<&str, &str> = guilds
+            .iter()
+            .map(|guild| (guild.id.as_str(), guild.name.as_str()))
+            .collect();
+
+        let get_guild_name = |guild_id: &str| {
+            guild_names.get(guild_id).copied().unwrap_or_default().to_string()
+        };
+
+        let new_topics = self
+            .topics_service
+            .get_topics_created_since(
+                &guild_ids,
+                now - Duration::days(DIGEST_PERIOD_DAYS),
+                DIGEST_TOPICS_LIMIT as i64,
+            )
+            .await?
+            .into_iter()
+            .map(|topic| DigestTopic {
+                text: quote_topic_text(&topic.text),
+                guild_name: get_guild_name(&topic.guild_id),
+                votes_count: topic.upvoted_by_users_ids.len(),
+                link: self
+                    .mailer
+                    .get_url(&format!("/guilds/{}", topic.guild_id)),
+            })
+            .collect();
+
+        let mut top_topics = Vec::new();
+
+        for guild in guilds.iter() {
+            let topics = self
+                .topics_service
+                .get_topics_by_guild_id(
+                    user.id,
+                    &guild.id,
+                    PaginationParameters {
+                        skip: 0,
+                        limit: DIGEST_TOPICS_LIMIT,
+                    },
+                    guild.clone(),
+                    TopicStatus::Created,
+                )
+                .await?;
+
+            top_topics.extend(
+                topics
+                    .into_iter()
+                    .filter(|topic| !topic.upvoted_by_users.is_empty())
+                    .map(|topic| DigestTopic {
+                        text: quote_topic_text(&topic.text),
+                        guild_name: guild.name.clone(),
+                        votes_count: topic.upvoted_by_users.len(),
+                        link: self
+                            .mailer
+                            .get_url(&format!("/guilds/{}", guild.id)),
+                    }),
+            );
+        }
+
+        top_topics.sort_by_key(|topic| Reverse(topic.votes_count));
+        top_topics.truncate(DIGEST_TOPICS_LIMIT);
+
+        let sessions = self
+            .sessions_service
+            .get_upcoming_sessions(
+                &guild_ids,
+                Some(now + Duration::days(DIGEST_PERIOD_DAYS)),
+            )
+            .await?
+            .into_iter()
+            .map(|session| DigestSession {
+                guild_name: get_guild_name(&session.guild_id),
+                starts_at_label: session.get_starts_at_label(),
+                duration_minutes: session.duration_minutes,
+                link: self
+                    .mailer
+                    .get_url(&format!("/guilds/{}", session.guild_id)),
+            })
+            .collect();
+
+        Ok(Digest {
+            user,
+            new_topics,
+            top_topics,
+            sessions,
+            preferences_link: self.mailer.get_url("/notifications"),
+        })
+    }
+
+    pub fn render(&self, digest: &Digest) -> Result<(String, String)> {
+        Ok((
+            DigestEmailTemplate { digest }.render()?,
+            DigestEmailTextTemplate { digest }.render()?,
+        ))
+    }
+
+    /// Sends the digest of the week to everyone who has something in it and
+    /// didn't get it yet. Returns how many digests failed to send.
+    pub async fn send_digests(&self, week: &str) -> usize {
+        let mut sent_count = 0;
+        let mut failed_count = 0;
+
+        for member in self.member_directory.get_all_members().await {
+            if member.bot {
+                continue;
+            }
+
+            let user_id = member.id;
+
+            match self.send_digest(member, week).await {
+                Ok(true) => sent_count += 1,
+                Ok(false) => {}
+                Err(err) => {
+                    failed_count += 1;
+
+                    error!("Failed to send digest to user {user_id}: {err}")
+                }
+            }
+        }
+
+        info!("Sent {sent_count} digests for {week}, {failed_count} failed");
+
+        failed_count
+    }
+
+    /// Returns whether the digest was sent.
+    async fn send_digest(&self, member: Member, week: &str) -> Result<bool> {
+        if !self.notifications_service.is_digest_enabled(member.id).await? {
+            return Ok(false);
+        }
+
+        let Some(email) =
+            self.member_directory.get_member_email(&member.id).await
+        else {
+            return Ok(false);
+        };
+
+        let user_id = member.id;
+
+        let digest = self.get_digest(member).await?;
+
+        if digest.is_empty() {
+            return Ok(false);
+        }
+
+        // claimed first so instances running side by side don't both send it
+        if !self.repository.claim_digest(user_id, week).await? {
+            return Ok(false);
+        }
+
+        let result = match self.render(&digest) {
+            Ok((html_body, text_body)) => {
+                self.mailer
+                    .send(
+                        &email,
+                        "Your weekly guildhub digest",
+                        html_body,
+                        text_body,
+                    )
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            if let Err(release_err) =
+                self.repository.release_digest(user_id, week).await
+            {
+                error!("Failed to release digest of user {user_id}: {release_err}");
+            }
+
+            return Err(err);
+        }
+
+        Ok(true)
+    }
+
+    pub async fn send_digests_in_loop(&self, weekday: Weekday, hour: u32) {
+        loop {
+            let now = Utc::now();
+            let next_run = get_next_run(now, weekday, hour);
+
+            info!("Next digests are sent at {next_run}");
+
+            sleep((next_run - now).to_std().unwrap_or_default()).await;
+
+            let week = get_week(next_run);
+
+            let mut failed_count = self.send_digests(&week).await;
+
+            for _ in 0..DIGEST_MAX_RETRIES {
+                if failed_count == 0 {
+                    break;
+                }
+
+                sleep(
+                    Duration::minutes(DIGEST_RETRY_DELAY_MINUTES)
+                        .to_std()
+                        .unwrap_or_default(),
+                )
+                .await;
+
+                failed_count = self.send_digests(&week).await;
+            }
+        }
+    }
+}
+
+fn get_week(date: DateTime<Utc>) -> String {
+    let week = date.iso_week();
+
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+fn get_next_run(
+    now: DateTime<Utc>,
+    weekday: Weekday,
+    hour: u32,
+) -> DateTime<Utc> {
+    let days_ahead = (weekday.num_days_from_monday() + 7
+        - now.weekday().num_days_from_monday())
+        % 7;
+
+    let next_run = (now.date_naive() + Duration::days(days_ahead as i64))
+        .and_hms_opt(hour, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+
+    if next_run <= now {
+        return next_run + Duration::weeks(1);
+    }
+
+    next_run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+    ) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn get_week_pads_the_week_number() {
+        assert_eq!(get_week(date(2024, 3, 4, 8, 0)), "2024-W10");
+        assert_eq!(get_week(date(2024, 1, 1, 0, 0)), "2024-W01");
+    }
+
+    #[test]
+    fn get_week_uses_the_iso_year() {
+        // sunday belonging to the last week of the previous year
+        assert_eq!(get_week(date(2023, 1, 1, 12, 0)), "2022-W52");
+        // monday belonging to the first week of the next year
+        assert_eq!(get_week(date(2024, 12, 30, 12, 0)), "2025-W01");
+    }
+
+    #[test]
+    fn get_next_run_on_a_later_weekday() {
+        // wednesday to friday
+        let now = date(2024, 3, 6, 12, 0);
+
+        assert_eq!(get_next_run(now, Weekday::Fri, 8), date(2024, 3, 8, 8, 0));
+    }
+
+    #[test]
+    fn get_next_run_on_an_earlier_weekday() {
+        // wednesday to next monday
+        let now = date(2024, 3, 6, 12, 0);
+
+        assert_eq!(
+            get_next_run(now, Weekday::Mon, 8),
+            date(2024, 3, 11, 8, 0)
+        );
+    }
+
+    #[test]
+    fn get_next_run_on_the_same_weekday_before_the_hour() {
+        let now = date(2024, 3, 4, 7, 30);
+
+        assert_eq!(get_next_run(now, Weekday::Mon, 8), date(2024, 3, 4, 8, 0));
+    }
+
+    #[test]
+    fn get_next_run_on_the_same_weekday_after_the_hour() {
+        let now = date(2024, 3, 4, 9, 0);
+
+        assert_eq!(
+            get_next_run(now, Weekday::Mon, 8),
+            date(2024, 3, 11, 8, 0)
+        );
+    }
+
+    #[test]
+    fn get_next_run_at_the_hour_waits_a_week() {
+        let now = date(2024, 3, 4, 8, 0);
+
+        assert_eq!(
+            get_next_run(now, Weekday::Mon, 8),
+            date(2024, 3, 11, 8, 0)
+        );
+    }
+
+    #[test]
+    fn get_next_run_is_in_the_week_it_sends() {
+        let next_run = get_next_run(date(2024, 3, 10, 23, 0), Weekday::Mon, 8);
+
+        assert_eq!(get_week(next_run), "2024-W11");
+    }
+}
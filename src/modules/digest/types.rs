@@ -0,0 +1,46 @@
+use askama::Template;
+
+use crate::libs::gitlab_api::gitlab_api::Member;
+
+pub struct DigestTopic {
+    pub text: String,
+    pub guild_name: String,
+    pub votes_count: usize,
+    pub link: String,
+}
+
+pub struct DigestSession {
+    pub guild_name: String,
+    pub starts_at_label: String,
+    pub duration_minutes: u32,
+    pub link: String,
+}
+
+/// What happened in the guilds of a user over the last week.
+pub struct Digest {
+    pub user: Member,
+    pub new_topics: Vec<DigestTopic>,
+    pub top_topics: Vec<DigestTopic>,
+    pub sessions: Vec<DigestSession>,
+    pub preferences_link: String,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.new_topics.is_empty()
+            && self.top_topics.is_empty()
+            && self.sessions.is_empty()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "emails/digest.html")]
+pub struct DigestEmailTemplate<'a> {
+    pub digest: &'a Digest,
+}
+
+#[derive(Template)]
+#[template(path = "emails/digest.txt")]
+pub struct DigestEmailTextTemplate<'a> {
+    pub digest: &'a Digest,
+}
@@ -0,0 +1,13 @@
+use chrono::Duration;
+
+/// Past sessions stay in calendars for a while instead of disappearing as
+/// soon as they start.
+pub const CALENDAR_PAST_SESSIONS_PERIOD: Duration = Duration::days(30);
+
+pub const CALENDAR_AGENDA_TOPICS_LIMIT: usize = 5;
+
+/// Entries of each kind in a topics feed, feed readers keep the older ones.
+pub const TOPICS_FEED_ENTRIES_LIMIT: i64 = 20;
+
+/// Characters of a topic kept in the title of its feed entries.
+pub const TOPICS_FEED_TITLE_LENGTH: usize = 80;
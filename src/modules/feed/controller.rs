@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+use std::sync::Arc;
+
+use crate::modules::{
+    app::{
+        app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
+        ToastLevel,
+    },
+    guild::GuildIdParameter,
+};
+
+use super::{FeedTokenParameter, GuildFeedParameters, GuildFeedsTemplate};
+
+fn calendar_response(content: String) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], content)
+}
+
+fn render_guild_feeds(
+    app: &App,
+    guild_id: &str,
+    token: &str,
+) -> GuildFeedsTemplate {
+    GuildFeedsTemplate {
+        guild_id: guild_id.to_string(),
+        calendar_url: app
+            .feeds_service
+            .get_guild_sessions_calendar_url(token, guild_id),
+        all_guilds_calendar_url: app
+            .feeds_service
+            .get_sessions_calendar_url(token),
+        topics_feed_url: app
+            .feeds_service
+            .get_guild_topics_feed_url(token, guild_id),
+    }
+}
+
+pub async fn get_sessions_calendar(
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<FeedTokenParameter>,
+) -> Result<impl IntoResponse, AppError> {
+    let member =
+        app.feeds_service.get_member_by_token(&parameters.token).await?;
+
+    let content = app.feeds_service.render_sessions_calendar(&member).await?;
+
+    Ok(calendar_response(content))
+}
+
+pub async fn get_guild_sessions_calendar(
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildFeedParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    let member =
+        app.feeds_service.get_member_by_token(&parameters.token).await?;
+
+    let content = app
+        .feeds_service
+        .render_guild_sessions_calendar(&member, &parameters.guild_id)
+        .await?;
+
+    Ok(calendar_response(content))
+}
+
+pub async fn get_guild_topics_feed(
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildFeedParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    let member =
+        app.feeds_service.get_member_by_token(&parameters.token).await?;
+
+    let content = app
+        .feeds_service
+        .render_guild_topics_feed(
+            &member,
+            &parameters.guild_id,
+            &parameters.token,
+        )
+        .await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        content,
+    ))
+}
+
+pub async fn get_guild_feeds(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    let token = app.feeds_service.get_or_create_token(user.id).await?;
+
+    Ok(render_guild_feeds(&app, &guild.id, &token))
+}
+
+pub async fn reset_feed_token(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    let token = app.feeds_service.reset_token(user.id).await?;
+
+    let event = HxTriggerEvent::ShowToast {
+        level: ToastLevel::Info,
+        message: "Feed links were reset, previous links stopped working"
+            .to_string(),
+    };
+
+    let event = event.to_header_value()?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Trigger", event);
+
+    Ok((headers, render_guild_feeds(&app, &guild.id, &token)))
+}
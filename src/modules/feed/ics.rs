@@ -0,0 +1,139 @@
+//! Just enough of iCalendar (RFC 5545) to publish events.
+
+use chrono::{DateTime, Utc};
+
+/// Longest line in octets, longer ones are folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+pub struct IcsEvent {
+    /// Stays the same across renders, so calendars update the event in
+    /// place.
+    pub uid: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub summary: String,
+    pub description: String,
+    pub url: String,
+    /// Published with a higher sequence so calendars replace the event they
+    /// already have.
+    pub is_cancelled: bool,
+}
+
+pub fn format_date_time(date: &DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+pub fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits the line into chunks of at most 75 octets without cutting a
+/// character, continuation lines start with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_length = 0;
+
+    for char in line.chars() {
+        if line_length + char.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+
+        folded.push(char);
+        line_length += char.len_utf8();
+    }
+
+    folded
+}
+
+pub fn render_calendar(name: &str, events: &[IcsEvent]) -> String {
+    let now = format_date_time(&Utc::now());
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//guildhub//sessions//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+
+    for event in events {
+        let (status, sequence) = match event.is_cancelled {
+            true => ("CANCELLED", 1),
+            false => ("CONFIRMED", 0),
+        };
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", event.uid),
+            format!("DTSTAMP:{now}"),
+            format!("DTSTART:{}", format_date_time(&event.starts_at)),
+            format!("DTEND:{}", format_date_time(&event.ends_at)),
+            format!("SUMMARY:{}", escape_text(&event.summary)),
+            format!("DESCRIPTION:{}", escape_text(&event.description)),
+            format!("URL:{}", event.url),
+            format!("STATUS:{status}"),
+            format!("SEQUENCE:{sequence}"),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold_line(line) + "\r\n").collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn event(is_cancelled: bool) -> IcsEvent {
+        IcsEvent {
+            uid: "session-1@guildhub".to_string(),
+            starts_at: Utc.with_ymd_and_hms(2024, 3, 4, 8, 0, 0).unwrap(),
+            ends_at: Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap(),
+            summary: "Rust session".to_string(),
+            description: "Agenda".to_string(),
+            url: "https://guildhub.test/guilds/1".to_string(),
+            is_cancelled,
+        }
+    }
+
+    #[test]
+    fn render_calendar_confirms_scheduled_events() {
+        let calendar = render_calendar("guildhub", &[event(false)]);
+
+        assert!(calendar.contains("\r\nSTATUS:CONFIRMED\r\n"));
+        assert!(calendar.contains("\r\nSEQUENCE:0\r\n"));
+    }
+
+    #[test]
+    fn render_calendar_keeps_cancelled_events() {
+        let calendar = render_calendar("guildhub", &[event(true)]);
+
+        assert!(calendar.contains("\r\nUID:session-1@guildhub\r\n"));
+        assert!(calendar.contains("\r\nSTATUS:CANCELLED\r\n"));
+        assert!(calendar.contains("\r\nSEQUENCE:1\r\n"));
+    }
+
+    #[test]
+    fn fold_line_splits_long_lines() {
+        let folded = fold_line(&"a".repeat(100));
+
+        assert_eq!(
+            folded,
+            format!("{}\r\n {}", "a".repeat(75), "a".repeat(25))
+        );
+    }
+
+    #[test]
+    fn escape_text_escapes_separators() {
+        assert_eq!(escape_text("a,b;c\\d\ne"), r"a\,b\;c\\d\ne");
+    }
+}
@@ -0,0 +1,11 @@
+pub mod constants;
+pub mod controller;
+pub mod ics;
+pub mod repository;
+pub mod service;
+pub mod types;
+
+pub use controller::*;
+pub use repository::*;
+pub use service::FeedsService;
+pub use types::*;
@@ -0,0 +1,13 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// Secret of the feed links of a member. Feed readers and calendars can't
+/// send cookies, so the token in the url authenticates them.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FeedTokenDocument {
+    pub _id: ObjectId,
+    pub user_id: usize,
+    pub token: String,
+    pub created_at: DateTime,
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use crate::libs::mongo::MongoDatabase;
+use anyhow::{Context, Result};
+use bson::{doc, oid::ObjectId, DateTime};
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    results::UpdateResult,
+    Collection, IndexModel,
+};
+
+use super::FeedTokenDocument;
+
+pub struct FeedTokensRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+}
+
+impl FeedTokensRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = FeedTokensRepository {
+            database,
+            collection_name: String::from("feed_tokens"),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! {"user_id":1})
+                .options(
+                    IndexOptions::builder()
+                        .name("user_id".to_string())
+                        .unique(true)
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! {"token":1})
+                .options(
+                    IndexOptions::builder()
+                        .name("token".to_string())
+                        .unique(true)
+                        .build(),
+                )
+                .build(),
+        ];
+
+        self.database
+            .create_indexes::<FeedTokenDocument>(
+                &self.collection_name,
+                indexes,
+            )
+            .await
+    }
+
+    pub async fn get_token_by_user_id(
+        &self,
+        user_id: usize,
+    ) -> Result<Option<FeedTokenDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<FeedTokenDocument> =
+            database.collection(&self.collection_name);
+
+        let document = collection
+            .find_one(doc! {"user_id": user_id as u32}, None)
+            .await?;
+
+        Ok(document)
+    }
+
+    pub async fn get_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<FeedTokenDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<FeedTokenDocument> =
+            database.collection(&self.collection_name);
+
+        let document =
+            collection.find_one(doc! {"token": token}, None).await?;
+
+        Ok(document)
+    }
+
+    /// Keeps the existing token of the user, if any.
+    pub async fn insert_token_if_missing(
+        &self,
+        user_id: usize,
+        token: &str,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<FeedTokenDocument> =
+            database.collection(&self.collection_name);
+
+        let options = UpdateOptions::builder().upsert(true).build();
+
+        let result = collection
+            .update_one(
+                doc! {"user_id": user_id as u32},
+                doc! {
+                    "$setOnInsert": {
+                        "_id": ObjectId::new(),
+                        "token": token,
+                        "created_at": DateTime::now(),
+                    }
+                },
+                options,
+            )
+            .await
+            .with_context(|| "Failed to insert feed token")?;
+
+        Ok(result)
+    }
+
+    pub async fn replace_token(
+        &self,
+        user_id: usize,
+        token: &str,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<FeedTokenDocument> =
+            database.collection(&self.collection_name);
+
+        let options = UpdateOptions::builder().upsert(true).build();
+
+        let result = collection
+            .update_one(
+                doc! {"user_id": user_id as u32},
+                doc! {
+                    "$set": {
+                        "token": token,
+                        "created_at": DateTime::now(),
+                    }
+                },
+                options,
+            )
+            .await
+            .with_context(|| "Failed to replace feed token")?;
+
+        Ok(result)
+    }
+}
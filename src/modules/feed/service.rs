@@ -0,0 +1,459 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{bail, Result};
+use askama::Template;
+use chrono::Utc;
+
+use crate::{
+    libs::gitlab_api::gitlab_api::Member,
+    modules::{
+        app::AppError,
+        guild::{Guild, GuildsService},
+        member::{MemberDirectory, MembersService},
+        session::{Session, SessionsService},
+        topic::{
+            types::{
+                PaginationParameters, Topic, TopicPersonalized, TopicStatus,
+            },
+            TopicsService,
+        },
+    },
+};
+
+use super::{
+    constants::{
+        CALENDAR_AGENDA_TOPICS_LIMIT, CALENDAR_PAST_SESSIONS_PERIOD,
+        TOPICS_FEED_ENTRIES_LIMIT, TOPICS_FEED_TITLE_LENGTH,
+    },
+    ics::{self, IcsEvent},
+    AtomEntry, FeedTokensRepository, GuildTopicsFeedTemplate,
+};
+
+/// Calendar feeds of guild sessions and Atom feeds of guild topics,
+/// authenticated by a feed token per member.
+pub struct FeedsService {
+    repository: Arc<FeedTokensRepository>,
+    guilds_service: Arc<GuildsService>,
+    topics_service: Arc<TopicsService>,
+    sessions_service: Arc<SessionsService>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    app_url: String,
+}
+
+impl FeedsService {
+    pub fn new(
+        repository: Arc<FeedTokensRepository>,
+        guilds_service: Arc<GuildsService>,
+        topics_service: Arc<TopicsService>,
+        sessions_service: Arc<SessionsService>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        app_url: String,
+    ) -> Self {
+        Self {
+            repository,
+            guilds_service,
+            topics_service,
+            sessions_service,
+            member_directory,
+            app_url,
+        }
+    }
+
+    pub async fn get_or_create_token(&self, user_id: usize) -> Result<String> {
+        if let Some(document) =
+            self.repository.get_token_by_user_id(user_id).await?
+        {
+            return Ok(document.token);
+        }
+
+        self.repository
+            .insert_token_if_missing(user_id, &generate_token())
+            .await?;
+
+        // another request may have created it in the meantime
+        match self.repository.get_token_by_user_id(user_id).await? {
+            Some(document) => Ok(document.token),
+            None => bail!("Failed to create feed token of user {user_id}"),
+        }
+    }
+
+    /// Replaces the token, links with the previous one stop working.
+    pub async fn reset_token(&self, user_id: usize) -> Result<String> {
+        let token = generate_token();
+
+        self.repository.replace_token(user_id, &token).await?;
+
+        Ok(token)
+    }
+
+    pub async fn get_member_by_token(&self, token: &str) -> Result<Member> {
+        let document = match self.repository.get_token(token).await? {
+            Some(document) => document,
+            None => bail!(AppError::NotFound("Unknown feed".to_string())),
+        };
+
+        match self.member_directory.get_member(&document.user_id).await {
+            Some(member) => Ok(member),
+            None => bail!(AppError::NotFound("Unknown feed".to_string())),
+        }
+    }
+
+    pub fn get_sessions_calendar_url(&self, token: &str) -> String {
+        format!("{}/feeds/{token}/sessions.ics", self.app_url)
+    }
+
+    pub fn get_guild_sessions_calendar_url(
+        &self,
+        token: &str,
+        guild_id: &str,
+    ) -> String {
+        format!(
+            "{}/feeds/{token}/guilds/{guild_id}/sessions.ics",
+            self.app_url
+        )
+    }
+
+    pub fn get_guild_topics_feed_url(
+        &self,
+        token: &str,
+        guild_id: &str,
+    ) -> String {
+        format!("{}/feeds/{token}/guilds/{guild_id}/topics.atom", self.app_url)
+    }
+
+    /// Sessions of every guild of the member.
+    pub async fn render_sessions_calendar(
+        &self,
+        member: &Member,
+    ) -> Result<String> {
+        let guilds = self.guilds_service.get_guilds(member.id).await?;
+
+        let events = self.get_session_events(&guilds).await?;
+
+        Ok(ics::render_calendar("guildhub sessions", &events))
+    }
+
+    pub async fn render_guild_sessions_calendar(
+        &self,
+        member: &Member,
+        guild_id: &str,
+    ) -> Result<String> {
+        let guild = self
+            .guilds_service
+            .get_guild_or_error(member.clone(), guild_id)
+            .await?;
+
+        let events =
+            self.get_session_events(std::slice::from_ref(&guild)).await?;
+
+        Ok(ics::render_calendar(
+            &format!("{} sessions", guild.name.trim()),
+            &events,
+        ))
+    }
+
+    /// Newly created and recently archived topics of the guild, newest
+    /// first.
+    pub async fn render_guild_topics_feed(
+        &self,
+        member: &Member,
+        guild_id: &str,
+        token: &str,
+    ) -> Result<String> {
+        let guild = self
+            .guilds_service
+            .get_guild_or_error(member.clone(), guild_id)
+            .await?;
+
+        let created_topics = self
+            .topics_service
+            .get_latest_created_topics(&guild.id, TOPICS_FEED_ENTRIES_LIMIT)
+            .await?;
+
+        let archived_topics = self
+            .topics_service
+            .get_latest_archived_topics(&guild.id, TOPICS_FEED_ENTRIES_LIMIT)
+            .await?;
+
+        let mut member_ids: Vec<usize> = created_topics
+            .iter()
+            .chain(archived_topics.iter())
+            .map(|topic| topic.created_by_user_id)
+            .collect();
+
+        member_ids.sort_unstable();
+        member_ids.dedup();
+
+        let members: HashMap<usize, Member> = self
+            .member_directory
+            .get_members_by_ids(&member_ids)
+            .await
+            .into_iter()
+            .map(|member| (member.id, member))
+            .collect();
+
+        let url = format!("{}/guilds/{}", self.app_url, guild.id);
+
+        let get_entry = |topic: &Topic, is_archived: bool| -> AtomEntry {
+            let author = members
+                .get(&topic.created_by_user_id)
+                .cloned()
+                .unwrap_or_else(|| {
+                    MembersService::former_member(topic.created_by_user_id)
+                });
+
+            let (kind, title_prefix, published) = match is_archived {
+                true => ("archived", "Archived", topic.updated_at),
+                false => ("created", "New topic", topic.created_at),
+            };
+
+            let votes_count = topic.upvoted_by_users_ids.len();
+
+            AtomEntry {
+                id: format!("{url}/topics/{}#{kind}", topic.id),
+                title: format!(
+                    "{title_prefix}: {}",
+                    get_entry_title(topic.text.trim())
+                ),
+                author_name: author.name.clone(),
+                content: format!(
+                    "{}\n\n{votes_count} {} · by @{}",
+                    topic.text.trim(),
+                    if votes_count == 1 { "vote" } else { "votes" },
+                    author.username
+                ),
+                url: get_topic_url(&url, topic),
+                published,
+                updated: published,
+            }
+        };
+
+        let mut entries: Vec<AtomEntry> = created_topics
+            .iter()
+            .map(|topic| get_entry(topic, false))
+            .chain(archived_topics.iter().map(|topic| get_entry(topic, true)))
+            .collect();
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.updated));
+
+        let template = GuildTopicsFeedTemplate {
+            id: format!("{url}/topics"),
+            title: format!("{} topics", guild.name.trim()),
+            updated: entries
+                .first()
+                .map(|entry| entry.updated)
+                .unwrap_or(guild.updated_at),
+            self_url: self.get_guild_topics_feed_url(token, &guild.id),
+            url,
+            entries,
+        };
+
+        Ok(template.render()?)
+    }
+
+    async fn get_session_events(
+        &self,
+        guilds: &[Guild],
+    ) -> Result<Vec<IcsEvent>> {
+        let guild_ids: Vec<String> =
+            guilds.iter().map(|guild| guild.id.clone()).collect();
+
+        let sessions = self
+            .sessions_service
+            .get_sessions_since(
+                &guild_ids,
+                Utc::now() - CALENDAR_PAST_SESSIONS_PERIOD,
+            )
+            .await?;
+
+        let guilds: HashMap<&str, &Guild> =
+            guilds.iter().map(|guild| (guild.id.as_str(), guild)).collect();
+
+        let mut agendas: HashMap<String, String> = HashMap::new();
+        let mut events = Vec::with_capacity(sessions.len());
+
+        for session in sessions {
+            let guild = match guilds.get(session.guild_id.as_str()) {
+                Some(guild) => *guild,
+                None => continue,
+            };
+
+            // the agenda is what the guild would talk about now, past and
+            // cancelled sessions are left without one
+            let agenda = match session.starts_at > Utc::now()
+                && !session.is_cancelled()
+            {
+                true => match agendas.get(&guild.id) {
+                    Some(agenda) => agenda.clone(),
+                    None => {
+                        let agenda = self.get_agenda(guild).await?;
+                        agendas.insert(guild.id.clone(), agenda.clone());
+                        agenda
+                    }
+                },
+                false => String::new(),
+            };
+
+            events.push(self.get_session_event(guild, &session, agenda));
+        }
+
+        Ok(events)
+    }
+
+    async fn get_agenda(&self, guild: &Guild) -> Result<String> {
+        // topics are listed as seen by the guild owner
+        let topics = self
+            .topics_service
+            .get_topics_by_guild_id(
+                guild.created_by_user.id,
+                &guild.id,
+                PaginationParameters {
+                    skip: 0,
+                    limit: CALENDAR_AGENDA_TOPICS_LIMIT,
+                },
+                guild.clone(),
+                TopicStatus::Created,
+            )
+            .await?;
+
+        if topics.is_empty() {
+            return Ok("No open topics yet".to_string());
+        }
+
+        let lines: Vec<String> = topics
+            .iter()
+            .enumerate()
+            .map(|(index, topic)| {
+                format!(
+                    "{}. {} ({} votes, {})",
+                    index + 1,
+                    topic.text.trim(),
+                    topic.upvoted_by_users.len(),
+                    get_presenter_label(topic)
+                )
+            })
+            .collect();
+
+        Ok(format!("Agenda:\n{}", lines.join("\n")))
+    }
+
+    fn get_session_event(
+        &self,
+        guild: &Guild,
+        session: &Session,
+        agenda: String,
+    ) -> IcsEvent {
+        let url = format!("{}/guilds/{}", self.app_url, guild.id);
+
+        let description = match agenda.is_empty() {
+            true => url.clone(),
+            false => format!("{agenda}\n\n{url}"),
+        };
+
+        IcsEvent {
+            uid: format!("session-{}@{}", session.id, self.get_uid_domain()),
+            starts_at: session.starts_at,
+            ends_at: session.get_ends_at(),
+            summary: format!("{} session", guild.name.trim()),
+            description,
+            url,
+            is_cancelled: session.is_cancelled(),
+        }
+    }
+
+    /// Host of the instance, so events of different instances don't clash.
+    fn get_uid_domain(&self) -> String {
+        url::Url::parse(&self.app_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "guildhub".to_string())
+    }
+}
+
+fn generate_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// First line of the topic, shortened so titles fit in a feed reader list.
+fn get_entry_title(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+
+    match line.char_indices().nth(TOPICS_FEED_TITLE_LENGTH) {
+        Some((index, _)) => format!("{}…", line[..index].trim_end()),
+        None => line.to_string(),
+    }
+}
+
+/// The topic card on the guild page, in the archived list once the topic is
+/// archived.
+fn get_topic_url(guild_url: &str, topic: &Topic) -> String {
+    match topic.status {
+        TopicStatus::Archived => {
+            format!("{guild_url}?archived=true#topic-{}", topic.id)
+        }
+        TopicStatus::Created => format!("{guild_url}#topic-{}", topic.id),
+    }
+}
+
+fn get_presenter_label(topic: &TopicPersonalized) -> String {
+    if topic.will_be_presented_by_the_creator {
+        return format!("presented by @{}", topic.created_by_user.username);
+    }
+
+    match &topic.presented_by_user {
+        Some(presenter) => format!("presented by @{}", presenter.username),
+        None => "looking for a presenter".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topic(status: TopicStatus) -> Topic {
+        Topic {
+            id: "66f0c0ffee".to_string(),
+            guild_id: "guild".to_string(),
+            text: "Rust lifetimes".to_string(),
+            status,
+            will_be_presented_by_the_creator: false,
+            presenter_user_id: None,
+            created_by_user_id: 1,
+            upvoted_by_users_ids: Vec::new(),
+            gitlab_issue: None,
+            updated_at: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn get_topic_url_links_to_the_topic_card() {
+        assert_eq!(
+            get_topic_url(
+                "https://guildhub.test/guilds/1",
+                &topic(TopicStatus::Created)
+            ),
+            "https://guildhub.test/guilds/1#topic-66f0c0ffee"
+        );
+    }
+
+    #[test]
+    fn get_topic_url_opens_archived_topics_in_the_archived_list() {
+        assert_eq!(
+            get_topic_url(
+                "https://guildhub.test/guilds/1",
+                &topic(TopicStatus::Archived)
+            ),
+            "https://guildhub.test/guilds/1?archived=true#topic-66f0c0ffee"
+        );
+    }
+
+    #[test]
+    fn get_entry_title_shortens_the_first_line() {
+        assert_eq!(get_entry_title("Title\nbody"), "Title");
+        assert_eq!(
+            get_entry_title(&"a".repeat(100)),
+            format!("{}…", "a".repeat(TOPICS_FEED_TITLE_LENGTH))
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use askama_axum::Template;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct FeedTokenParameter {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct GuildFeedParameters {
+    pub token: String,
+    pub guild_id: String,
+}
+
+#[derive(Template)]
+#[template(path = "components/feed/guild-feeds.html")]
+pub struct GuildFeedsTemplate {
+    pub guild_id: String,
+    pub calendar_url: String,
+    pub all_guilds_calendar_url: String,
+    pub topics_feed_url: String,
+}
+
+pub struct AtomEntry {
+    /// Stays the same across renders, so readers don't show it twice.
+    pub id: String,
+    pub title: String,
+    pub author_name: String,
+    pub content: String,
+    pub url: String,
+    pub published: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+#[derive(Template)]
+#[template(path = "feed/guild-topics.xml")]
+pub struct GuildTopicsFeedTemplate {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub self_url: String,
+    pub updated: DateTime<Utc>,
+    pub entries: Vec<AtomEntry>,
+}
+
+impl GuildTopicsFeedTemplate {
+    pub fn get_updated(&self) -> String {
+        self.format_date(&self.updated)
+    }
+
+    pub fn format_date(&self, date: &DateTime<Utc>) -> String {
+        date.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
@@ -0,0 +1,10 @@
+use chrono::Duration;
+use std::time::Duration as StdDuration;
+
+/// How often the members are fetched from gitlab again.
+pub const MEMBERS_REFRESH_INTERVAL: StdDuration =
+    StdDuration::from_secs(60 * 5);
+
+/// Gitlab is reported down when the members weren't refreshed for this
+/// long, a few refreshes in a row must have failed.
+pub const MEMBERS_REFRESH_MAX_AGE: Duration = Duration::minutes(15);
@@ -0,0 +1,118 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::{
+    libs::utils::constant_time_eq,
+    modules::app::{app::App, AppError},
+};
+
+use super::types::{GitlabIssueEvent, GitlabMemberEvent};
+
+pub async fn gitlab_webhook(
+    State(app): State<Arc<App>>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> Result<impl IntoResponse, AppError> {
+    let (secret, gitlab_service) = match (
+        app.configuration
+            .gitlab
+            .as_ref()
+            .and_then(|gitlab| gitlab.webhook_secret.as_ref()),
+        &app.gitlab_service,
+    ) {
+        (Some(secret), Some(gitlab_service)) => (secret, gitlab_service),
+        _ => return Ok(StatusCode::NOT_FOUND),
+    };
+
+    if !is_valid_token(&headers, secret) {
+        warn!("Rejected gitlab webhook with invalid token");
+        return Ok(StatusCode::UNAUTHORIZED);
+    }
+
+    let object_kind =
+        payload.get("object_kind").and_then(Value::as_str).unwrap_or_default();
+
+    if object_kind == "issue" {
+        let event: GitlabIssueEvent = serde_json::from_value(payload)?;
+
+        app.topics_service
+            .handle_gitlab_issue_update(
+                event.object_attributes.project_id,
+                event.object_attributes.iid,
+                &event.object_attributes.state,
+                app.configuration.gitlab.as_ref().is_some_and(|gitlab| {
+                    gitlab.archive_topics_on_issue_close
+                }),
+            )
+            .await?;
+
+        return Ok(StatusCode::OK);
+    }
+
+    let event_name =
+        payload.get("event_name").and_then(Value::as_str).unwrap_or_default();
+
+    match event_name {
+        "user_add_to_group"
+        | "user_remove_from_group"
+        | "user_update_for_group" => {
+            let event: GitlabMemberEvent = serde_json::from_value(payload)?;
+
+            gitlab_service.handle_member_event(event).await;
+        }
+        _ => {
+            debug!("Ignoring gitlab webhook event {event_name:?}");
+        }
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Gitlab sends the secret token of the webhook as is in `X-Gitlab-Token`.
+fn is_valid_token(headers: &HeaderMap, secret: &str) -> bool {
+    let token = headers
+        .get("X-Gitlab-Token")
+        .map(|token| token.as_bytes())
+        .unwrap_or_default();
+
+    constant_time_eq(token, secret.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(token: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        headers.insert("X-Gitlab-Token", HeaderValue::from_static(token));
+
+        headers
+    }
+
+    #[test]
+    fn is_valid_token_accepts_the_secret() {
+        assert!(is_valid_token(&headers("webhook secret"), "webhook secret"));
+    }
+
+    #[test]
+    fn is_valid_token_refuses_other_tokens() {
+        assert!(!is_valid_token(&headers("webhook secre"), "webhook secret"));
+        assert!(!is_valid_token(&headers("webhook secrex"), "webhook secret"));
+        assert!(!is_valid_token(&headers("Webhook secret"), "webhook secret"));
+    }
+
+    #[test]
+    fn is_valid_token_refuses_a_missing_token() {
+        assert!(!is_valid_token(&HeaderMap::new(), "webhook secret"));
+        assert!(!is_valid_token(&headers(""), "webhook secret"));
+    }
+}
@@ -1,49 +1,358 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use chrono::Utc;
 use futures::future::join_all;
 use moka::future::Cache;
 use oauth2::{CsrfToken, Scope};
-use std::time::Duration;
-use tokio::time::sleep;
-use tracing::error;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::broadcast::{channel, Receiver, Sender},
+    time::sleep,
+};
+use tracing::{error, info, warn};
 
-use crate::libs::gitlab_api::{gitlab_api::Member, GitlabApi};
+use crate::{
+    configuration::GitlabConfiguration,
+    libs::{
+        gitlab_api::{
+            gitlab_api::{Issue, Member, NewIssue, NewWikiPage, WikiPage},
+            GitlabApi,
+        },
+        health_checker::HealthChecker,
+    },
+    modules::{
+        app::AppError,
+        member::{
+            MemberDirectory, MemberEvent, MembersReconciliation,
+            MembersRepository,
+        },
+    },
+};
+
+use super::{
+    constants::MEMBERS_REFRESH_MAX_AGE,
+    types::{GitlabMemberEvent, GitlabMemberEventName},
+};
 
 pub struct GitlabService {
+    pub events_channel: (Sender<MemberEvent>, Receiver<MemberEvent>),
     pub gitlab_api: GitlabApi,
-    pub group_id: String,
+    pub group_ids: Vec<String>,
+    min_access_level: u32,
+    issues_project_id: Option<String>,
+    wiki_project_id: Option<String>,
     cache: Cache<usize, Member>,
+    max_removed_members_percent: u32,
+    members_repository: Arc<MembersRepository>,
+    /// Unix timestamp of the latest refresh that was applied, 0 before the
+    /// first one.
+    last_refreshed_at: AtomicI64,
+    is_refresh_failing: AtomicBool,
+    is_healthy: AtomicBool,
 }
 
 impl GitlabService {
     pub fn new(
         gitlab_api: GitlabApi,
-        group_id: String,
-        cache_ttl: Duration,
+        configuration: &GitlabConfiguration,
+        cache_ttl: Option<Duration>,
+        members_repository: Arc<MembersRepository>,
     ) -> Self {
-        let cache: Cache<usize, Member> =
-            Cache::builder().time_to_live(cache_ttl).build();
+        let cache: Cache<usize, Member> = match cache_ttl {
+            Some(cache_ttl) => {
+                Cache::builder().time_to_live(cache_ttl).build()
+            }
+            None => Cache::builder().build(),
+        };
 
-        Self { gitlab_api, group_id, cache }
+        Self {
+            events_channel: channel::<MemberEvent>(50),
+            gitlab_api,
+            group_ids: configuration.group_ids.clone(),
+            min_access_level: configuration.min_access_level,
+            issues_project_id: configuration.issues_project_id.clone(),
+            wiki_project_id: configuration.wiki_project_id.clone(),
+            cache,
+            max_removed_members_percent: configuration
+                .max_removed_members_percent,
+            members_repository,
+            last_refreshed_at: AtomicI64::new(0),
+            is_refresh_failing: AtomicBool::new(false),
+            is_healthy: AtomicBool::new(false),
+        }
     }
 
+    fn is_eligible(&self, member: &Member) -> bool {
+        member.is_eligible(self.min_access_level, Utc::now().date_naive())
+    }
+
+    /// Fetches the members of every configured group. Members of several
+    /// groups are merged, keeping their highest access level, and those who
+    /// are not eligible are filtered out.
     pub async fn fetch_all_group_members(&self) -> Result<Vec<Member>> {
-        self.gitlab_api.get_all_group_members(&self.group_id).await
+        let mut groups = Vec::with_capacity(self.group_ids.len());
+
+        for group_id in self.group_ids.iter() {
+            groups
+                .push(self.gitlab_api.get_all_group_members(group_id).await?);
+        }
+
+        Ok(merge_group_members(groups)
+            .into_iter()
+            .filter(|member| self.is_eligible(member))
+            .collect())
+    }
+
+    /// Looks the user up in every configured group, returning the membership
+    /// with the highest access level.
+    async fn fetch_group_member(
+        &self,
+        user_id: usize,
+    ) -> Result<Option<Member>> {
+        let mut member: Option<Member> = None;
+
+        for group_id in self.group_ids.iter() {
+            let group_member =
+                self.gitlab_api.get_group_member(group_id, user_id).await?;
+
+            if let Some(group_member) = group_member {
+                if member.as_ref().is_none_or(|member| {
+                    member.access_level < group_member.access_level
+                }) {
+                    member = Some(group_member);
+                }
+            }
+        }
+
+        Ok(member)
+    }
+
+    async fn remove_member(&self, user_id: usize) {
+        self.remove_member_from_cache(&user_id).await;
+
+        if let Err(err) =
+            self.members_repository.mark_members_removed(&[user_id]).await
+        {
+            error!("Failed to persist removal of member: {err}");
+        }
+
+        let _ = self.events_channel.0.send(MemberEvent::Remove(user_id));
     }
 
     pub async fn insert_member_into_cache(&self, member: Member) {
         self.cache.insert(member.id, member).await
     }
 
+    pub async fn remove_member_from_cache(&self, user_id: &usize) {
+        self.cache.invalidate(user_id).await
+    }
+
+    pub async fn handle_member_event(&self, event: GitlabMemberEvent) {
+        if !self.group_ids.contains(&event.group_id.to_string()) {
+            info!(
+                "Ignoring gitlab member event for unrelated group {}",
+                event.group_id
+            );
+            return;
+        }
+
+        // The user may still belong to another configured group, or to this
+        // one with a different access level, so their membership is looked up
+        // again instead of trusting the event alone.
+        let membership = match self.fetch_group_member(event.user_id).await {
+            Ok(membership) => membership,
+            Err(err) => {
+                warn!(
+                    "Failed to fetch gitlab membership of user {}: {err}",
+                    event.user_id
+                );
+
+                match get_membership_from_event(&event, self.group_ids.len()) {
+                    Some(membership) => membership,
+                    None => return,
+                }
+            }
+        };
+
+        let member = match membership {
+            Some(member) if self.is_eligible(&member) => member,
+            _ => {
+                info!(
+                    "User {} was removed from group or is no longer eligible",
+                    event.user_id
+                );
+
+                self.remove_member(event.user_id).await;
+
+                return;
+            }
+        };
+
+        let member = match self.gitlab_api.get_user(event.user_id).await {
+            Ok(user) => Member {
+                access_level: member.access_level,
+                expires_at: member.expires_at,
+                ..user
+            },
+            Err(err) => {
+                warn!("Failed to fetch gitlab user {}: {err}", event.user_id);
+
+                member
+            }
+        };
+
+        if !self.is_eligible(&member) {
+            info!("User {} is no longer eligible", member.id);
+
+            self.remove_member(member.id).await;
+
+            return;
+        }
+
+        info!("User {} joined or was updated in group", member.id);
+
+        if let Err(err) = self.members_repository.upsert_member(&member).await
+        {
+            error!("Failed to persist member: {err}");
+        }
+
+        self.insert_member_into_cache(member).await
+    }
+
+    /// Loads the persisted member directory into the cache, so the app can
+    /// serve requests while gitlab is unreachable.
+    pub async fn warm_cache_from_database(&self) -> Result<usize> {
+        let members: Vec<Member> = self
+            .members_repository
+            .get_active_members()
+            .await?
+            .into_iter()
+            .map(Member::from)
+            // memberships may have expired since they were persisted
+            .filter(|member| self.is_eligible(member))
+            .collect();
+
+        let members_count = members.len();
+
+        let insert_futures = members
+            .into_iter()
+            .map(|member| self.cache.insert(member.id, member));
+
+        join_all(insert_futures).await;
+
+        Ok(members_count)
+    }
+
     pub async fn refresh_members_cache(&self) -> Result<()> {
+        let result = self.apply_members_refresh().await;
+
+        let is_applied = matches!(result, Ok(true));
+
+        if is_applied {
+            self.last_refreshed_at
+                .store(Utc::now().timestamp(), Ordering::Relaxed);
+        }
+
+        self.is_refresh_failing.store(!is_applied, Ordering::Relaxed);
+
+        result.map(|_| ())
+    }
+
+    /// Fetches and reconciles the members, `false` when the fetched ones were
+    /// refused.
+    async fn apply_members_refresh(&self) -> Result<bool> {
         let members = self.fetch_all_group_members().await?;
 
+        let reconciliation = match self.reconcile_members(&members).await? {
+            Some(reconciliation) => reconciliation,
+            None => {
+                // keeps entries alive past the cache ttl until gitlab recovers
+                self.warm_cache_from_database().await?;
+
+                return Ok(false);
+            }
+        };
+
         let insert_futures = members
             .into_iter()
             .map(|member| self.cache.insert(member.id, member));
 
         join_all(insert_futures).await;
 
-        Ok(())
+        for user_id in reconciliation.removed.iter() {
+            self.cache.invalidate(user_id).await;
+
+            let _ = self.events_channel.0.send(MemberEvent::Remove(*user_id));
+        }
+
+        if reconciliation != MembersReconciliation::default() {
+            info!(
+                "Reconciled members: {} added, {} updated, {} removed",
+                reconciliation.added.len(),
+                reconciliation.updated.len(),
+                reconciliation.removed.len()
+            );
+        }
+
+        Ok(true)
+    }
+
+    /// Persists the changes between the fetched and the stored members,
+    /// unless too many of them would be removed. `None` when nothing was
+    /// applied for that reason.
+    async fn reconcile_members(
+        &self,
+        members: &[Member],
+    ) -> Result<Option<MembersReconciliation>> {
+        let existing: HashMap<usize, Member> = self
+            .members_repository
+            .get_all_members()
+            .await?
+            .into_iter()
+            .filter(|document| document.removed_at.is_none())
+            .map(|document| (document.user_id, document.into()))
+            .collect();
+
+        let reconciliation = reconcile(&existing, members);
+
+        if is_implausible_removal(
+            existing.len(),
+            reconciliation.removed.len(),
+            self.max_removed_members_percent,
+        ) {
+            warn!(
+                "Gitlab returned {} members, removing {} of the {} known ones is not applied",
+                members.len(),
+                reconciliation.removed.len(),
+                existing.len()
+            );
+
+            return Ok(None);
+        }
+
+        for member in members {
+            let is_changed = reconciliation.added.contains(&member.id)
+                || reconciliation.updated.contains(&member.id);
+
+            if is_changed {
+                self.members_repository.upsert_member(member).await?;
+            }
+        }
+
+        if !reconciliation.removed.is_empty() {
+            self.members_repository
+                .mark_members_removed(&reconciliation.removed)
+                .await?;
+        }
+
+        Ok(Some(reconciliation))
     }
 
     pub async fn refresh_cache_loop(&self, interval: Duration) {
@@ -54,6 +363,12 @@ impl GitlabService {
                 }
                 Err(err) => {
                     error!("Failed to refresh members cache: {err}. Retrying in 5 sec...");
+
+                    // keeps entries alive past the cache ttl during an outage
+                    if let Err(err) = self.warm_cache_from_database().await {
+                        error!("Failed to warm members cache: {err}");
+                    }
+
                     sleep(Duration::from_secs(5)).await;
                 }
             }
@@ -73,6 +388,50 @@ impl GitlabService {
         members
     }
 
+    pub fn is_issues_project_configured(&self) -> bool {
+        self.issues_project_id.is_some()
+    }
+
+    /// Opens an issue in the configured issues project with the service
+    /// token.
+    pub async fn create_issue(&self, issue: NewIssue) -> Result<Issue> {
+        let project_id = match &self.issues_project_id {
+            Some(project_id) => project_id,
+            None => bail!(AppError::Unavailable(
+                "Gitlab issues project is not configured".to_string()
+            )),
+        };
+
+        Ok(self.gitlab_api.create_issue(project_id, &issue).await?)
+    }
+
+    pub fn is_wiki_project_configured(&self) -> bool {
+        self.wiki_project_id.is_some()
+    }
+
+    /// Creates the wiki page titled `title` in the configured wiki project,
+    /// or replaces its content when it already exists.
+    pub async fn publish_wiki_page(
+        &self,
+        title: &str,
+        content: String,
+    ) -> Result<WikiPage> {
+        let project_id = match &self.wiki_project_id {
+            Some(project_id) => project_id,
+            None => bail!(AppError::Unavailable(
+                "Gitlab wiki project is not configured".to_string()
+            )),
+        };
+
+        let page = NewWikiPage {
+            title: title.to_string(),
+            content,
+            format: "markdown".to_string(),
+        };
+
+        Ok(self.gitlab_api.publish_wiki_page(project_id, &page).await?)
+    }
+
     pub fn get_oath_url(&self) -> String {
         let (auth_url, _) = self
             .gitlab_api
@@ -83,22 +442,340 @@ impl GitlabService {
 
         auth_url.to_string()
     }
+}
 
-    pub async fn get_cached_members_by_ids(
-        &self,
-        user_ids: &Vec<usize>,
-    ) -> Vec<Member> {
-        let members =
-            user_ids.iter().map(|id: &usize| self.get_cached_member(id));
+#[async_trait]
+impl HealthChecker for GitlabService {
+    fn get_service_name(&self) -> String {
+        "Gitlab".to_string()
+    }
 
-        let members = join_all(members).await;
+    fn get_health(&self) -> bool {
+        self.is_healthy.load(Ordering::Relaxed)
+    }
 
-        let members: Vec<Member> = members
-            .into_iter()
-            .filter(|member| member.is_some())
-            .map(|member| member.unwrap())
-            .collect();
+    /// Known members keep being served while gitlab is down.
+    fn is_required(&self) -> bool {
+        false
+    }
+
+    fn persist_new_health_status(&self, is_healthy: bool) {
+        self.is_healthy.store(is_healthy, Ordering::Relaxed);
+    }
+
+    async fn check_is_healthy(&self) -> Result<()> {
+        if self.is_refresh_failing.load(Ordering::Relaxed) {
+            bail!("The latest refresh of gitlab members failed")
+        }
+
+        let last_refreshed_at = self.last_refreshed_at.load(Ordering::Relaxed);
+
+        if last_refreshed_at
+            < (Utc::now() - MEMBERS_REFRESH_MAX_AGE).timestamp()
+        {
+            bail!("Gitlab members were not refreshed recently")
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares the fetched members with the persisted ones.
+fn reconcile(
+    existing: &HashMap<usize, Member>,
+    members: &[Member],
+) -> MembersReconciliation {
+    let mut reconciliation = MembersReconciliation::default();
+
+    for member in members {
+        match existing.get(&member.id) {
+            None => reconciliation.added.push(member.id),
+            Some(existing_member)
+                if !existing_member.has_same_profile(member)
+                    || !existing_member.has_same_membership(member) =>
+            {
+                reconciliation.updated.push(member.id)
+            }
+            _ => {}
+        }
+    }
+
+    reconciliation.removed = existing
+        .keys()
+        .filter(|user_id| !members.iter().any(|member| &member.id == *user_id))
+        .cloned()
+        .collect();
+
+    reconciliation.removed.sort_unstable();
+
+    reconciliation
+}
+
+/// Whether removing `removed_count` of the `existing_count` stored members is
+/// more likely an incomplete answer from gitlab, e.g. after the scope of the
+/// token changed, than members who actually left.
+fn is_implausible_removal(
+    existing_count: usize,
+    removed_count: usize,
+    max_removed_percent: u32,
+) -> bool {
+    if removed_count == 0 {
+        return false;
+    }
+
+    removed_count == existing_count
+        || removed_count * 100 > existing_count * max_removed_percent as usize
+}
+
+/// The membership the event alone tells about, for when gitlab can't be
+/// asked. Removals from one of several groups say nothing about the other
+/// groups, `None` is returned for those so the member is left as is.
+fn get_membership_from_event(
+    event: &GitlabMemberEvent,
+    groups_count: usize,
+) -> Option<Option<Member>> {
+    match event.event_name {
+        GitlabMemberEventName::UserRemoveFromGroup if groups_count == 1 => {
+            Some(None)
+        }
+        GitlabMemberEventName::UserRemoveFromGroup => None,
+        _ => Some(Some(event.clone().into())),
+    }
+}
+
+#[async_trait]
+impl MemberDirectory for GitlabService {
+    fn get_directory_name(&self) -> String {
+        "gitlab".to_string()
+    }
+
+    async fn get_member(&self, user_id: &usize) -> Option<Member> {
+        self.get_cached_member(user_id).await
+    }
+
+    async fn get_all_members(&self) -> Vec<Member> {
+        self.get_all_cached_members().await
+    }
+
+    async fn get_member_email(&self, user_id: &usize) -> Option<String> {
+        match self.gitlab_api.get_user_email(*user_id).await {
+            Ok(email) => email,
+            Err(err) => {
+                error!("Failed to fetch email of user {user_id}: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// Merges the members of several groups, keeping the membership with the
+/// highest access level of those in more than one. Sorted by id.
+fn merge_group_members(
+    groups: impl IntoIterator<Item = Vec<Member>>,
+) -> Vec<Member> {
+    let mut members: HashMap<usize, Member> = HashMap::new();
+
+    for member in groups.into_iter().flatten() {
+        match members.get(&member.id) {
+            Some(existing) if existing.access_level >= member.access_level => {
+            }
+            _ => {
+                members.insert(member.id, member);
+            }
+        }
+    }
+
+    let mut members: Vec<Member> = members.into_values().collect();
+
+    members.sort_by_key(|member| member.id);
+
+    members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: usize, username: &str) -> Member {
+        Member { id, username: username.to_string(), ..Member::default() }
+    }
+
+    fn existing(members: &[Member]) -> HashMap<usize, Member> {
+        members.iter().map(|member| (member.id, member.clone())).collect()
+    }
+
+    #[test]
+    fn merge_group_members_keeps_the_highest_access_level() {
+        let developer = |id, username| Member {
+            access_level: Some(30),
+            ..member(id, username)
+        };
+        let maintainer = |id, username| Member {
+            access_level: Some(40),
+            ..member(id, username)
+        };
+
+        let members = merge_group_members([
+            vec![developer(1, "alice"), maintainer(2, "bob")],
+            vec![maintainer(1, "alice"), developer(2, "bob")],
+            vec![developer(3, "carol")],
+        ]);
+
+        assert_eq!(
+            members,
+            vec![
+                maintainer(1, "alice"),
+                maintainer(2, "bob"),
+                developer(3, "carol")
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_group_members_prefers_a_known_access_level() {
+        let members = merge_group_members([
+            vec![member(1, "alice")],
+            vec![Member { access_level: Some(10), ..member(1, "alice") }],
+        ]);
+
+        assert_eq!(members[0].access_level, Some(10));
+    }
+
+    #[test]
+    fn reconcile_finds_added_updated_and_removed_members() {
+        let existing = existing(&[
+            member(1, "alice"),
+            member(2, "bob"),
+            member(3, "carol"),
+        ]);
+
+        let fetched =
+            [member(1, "alice"), member(2, "bobby"), member(4, "dave")];
+
+        assert_eq!(
+            reconcile(&existing, &fetched),
+            MembersReconciliation {
+                added: vec![4],
+                updated: vec![2],
+                removed: vec![3],
+            }
+        );
+    }
+
+    #[test]
+    fn reconcile_updates_members_whose_membership_changed() {
+        let existing = existing(&[member(1, "alice")]);
+
+        let fetched =
+            [Member { access_level: Some(40), ..member(1, "alice") }];
+
+        assert_eq!(reconcile(&existing, &fetched).updated, vec![1]);
+    }
+
+    #[test]
+    fn reconcile_leaves_unchanged_members_alone() {
+        let existing = existing(&[member(1, "alice"), member(2, "bob")]);
+
+        let fetched = [member(2, "bob"), member(1, "alice")];
+
+        assert_eq!(
+            reconcile(&existing, &fetched),
+            MembersReconciliation::default()
+        );
+    }
+
+    #[test]
+    fn reconcile_of_an_empty_database_adds_everyone() {
+        let fetched = [member(1, "alice"), member(2, "bob")];
+
+        assert_eq!(reconcile(&HashMap::new(), &fetched).added, vec![1, 2]);
+    }
+
+    #[test]
+    fn reconcile_of_an_empty_fetch_is_not_applied() {
+        let existing = existing(&[member(1, "alice"), member(2, "bob")]);
+
+        let removed = reconcile(&existing, &[]).removed;
+
+        assert_eq!(removed, vec![1, 2]);
+        assert!(is_implausible_removal(existing.len(), removed.len(), 100));
+    }
+
+    #[test]
+    fn reconcile_removing_too_many_members_is_not_applied() {
+        let existing = existing(&[
+            member(1, "alice"),
+            member(2, "bob"),
+            member(3, "carol"),
+            member(4, "dave"),
+        ]);
+
+        let removed = reconcile(&existing, &[member(1, "alice")]).removed;
+
+        assert!(is_implausible_removal(existing.len(), removed.len(), 50));
+        assert!(!is_implausible_removal(existing.len(), removed.len(), 75));
+
+        let removed =
+            reconcile(&existing, &[member(1, "alice"), member(2, "bob")])
+                .removed;
+
+        assert!(!is_implausible_removal(existing.len(), removed.len(), 50));
+        assert!(!is_implausible_removal(0, 0, 50));
+    }
+
+    fn event(event_name: GitlabMemberEventName) -> GitlabMemberEvent {
+        GitlabMemberEvent {
+            event_name,
+            group_id: 10,
+            user_id: 7,
+            user_username: "alice".to_string(),
+            user_name: "Alice Anderson".to_string(),
+            group_access: Some("Developer".to_string()),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn added_members_are_taken_from_the_event() {
+        let membership = get_membership_from_event(
+            &event(GitlabMemberEventName::UserAddToGroup),
+            2,
+        );
+
+        let member = membership.flatten().unwrap();
+
+        assert_eq!(member.id, 7);
+        assert_eq!(member.username, "alice");
+        assert_eq!(member.access_level, Some(30));
+    }
+
+    #[test]
+    fn updated_members_are_taken_from_the_event() {
+        let membership = get_membership_from_event(
+            &event(GitlabMemberEventName::UserUpdateForGroup),
+            1,
+        );
+
+        assert_eq!(membership.flatten().map(|member| member.id), Some(7));
+    }
+
+    #[test]
+    fn removal_from_the_only_group_removes_the_member() {
+        let membership = get_membership_from_event(
+            &event(GitlabMemberEventName::UserRemoveFromGroup),
+            1,
+        );
+
+        assert_eq!(membership, Some(None));
+    }
+
+    #[test]
+    fn removal_from_one_of_several_groups_is_left_to_the_next_refresh() {
+        let membership = get_membership_from_event(
+            &event(GitlabMemberEventName::UserRemoveFromGroup),
+            2,
+        );
 
-        return members;
+        assert_eq!(membership, None);
     }
 }
@@ -1,3 +1,7 @@
+pub mod constants;
+pub mod controller;
 pub mod gitlab_service;
+pub mod types;
 
+pub use controller::*;
 pub use gitlab_service::GitlabService;
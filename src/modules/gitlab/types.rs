@@ -0,0 +1,129 @@
+use serde::Deserialize;
+
+use crate::libs::gitlab_api::gitlab_api::Member;
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GitlabMemberEventName {
+    UserAddToGroup,
+    UserRemoveFromGroup,
+    UserUpdateForGroup,
+}
+
+/// Payload of gitlab "Member events" group webhooks.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitlabMemberEvent {
+    pub event_name: GitlabMemberEventName,
+    pub group_id: usize,
+    pub user_id: usize,
+    pub user_username: String,
+    pub user_name: String,
+    /// Role name, e.g. "Developer".
+    #[serde(default)]
+    pub group_access: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+/// Payload of gitlab "Issues events" webhooks, reduced to what topics track.
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitlabIssueEvent {
+    pub object_attributes: GitlabIssueAttributes,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GitlabIssueAttributes {
+    pub iid: u64,
+    pub project_id: u64,
+    /// "opened" or "closed"
+    pub state: String,
+}
+
+/// Maps gitlab role names to their numeric access levels.
+fn access_level_from_role(role: &str) -> Option<u32> {
+    match role.to_lowercase().as_str() {
+        "minimal access" => Some(5),
+        "guest" => Some(10),
+        "planner" => Some(15),
+        "reporter" => Some(20),
+        "developer" => Some(30),
+        "maintainer" => Some(40),
+        "owner" => Some(50),
+        _ => None,
+    }
+}
+
+impl From<GitlabMemberEvent> for Member {
+    fn from(event: GitlabMemberEvent) -> Member {
+        Member {
+            id: event.user_id,
+            username: event.user_username,
+            name: event.user_name,
+            access_level: event
+                .group_access
+                .as_deref()
+                .and_then(access_level_from_role),
+            // gitlab sends full timestamps, members carry dates only
+            expires_at: event
+                .expires_at
+                .map(|expires_at| expires_at.chars().take(10).collect()),
+            ..Member::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn member_events_are_read_from_gitlab_payloads() {
+        let event: GitlabMemberEvent = serde_json::from_value(json!({
+            "event_name": "user_add_to_group",
+            "group_id": 10,
+            "group_name": "guild",
+            "user_id": 7,
+            "user_username": "alice",
+            "user_name": "Alice Anderson",
+            "user_email": "alice@example.com",
+            "group_access": "Maintainer",
+            "expires_at": "2030-01-31T00:00:00Z",
+        }))
+        .unwrap();
+
+        assert_eq!(event.event_name, GitlabMemberEventName::UserAddToGroup);
+
+        let member: Member = event.into();
+
+        assert_eq!(member.id, 7);
+        assert_eq!(member.name, "Alice Anderson");
+        assert_eq!(member.access_level, Some(40));
+        assert_eq!(member.expires_at.as_deref(), Some("2030-01-31"));
+    }
+
+    #[test]
+    fn member_events_without_access_or_expiry() {
+        let event: GitlabMemberEvent = serde_json::from_value(json!({
+            "event_name": "user_remove_from_group",
+            "group_id": 10,
+            "user_id": 7,
+            "user_username": "alice",
+            "user_name": "Alice Anderson",
+        }))
+        .unwrap();
+
+        let member: Member = event.into();
+
+        assert_eq!(member.access_level, None);
+        assert_eq!(member.expires_at, None);
+    }
+
+    #[test]
+    fn access_level_from_role_knows_gitlab_roles() {
+        assert_eq!(access_level_from_role("Guest"), Some(10));
+        assert_eq!(access_level_from_role("developer"), Some(30));
+        assert_eq!(access_level_from_role("Owner"), Some(50));
+        assert_eq!(access_level_from_role("Custom role"), None);
+    }
+}
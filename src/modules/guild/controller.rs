@@ -6,13 +6,14 @@ use crate::{
     modules::{
         app::{
             app::App, user_extractor::Authenticated, AppError,
-            Event as AppEvent, HxTriggerEvent, ToastLevel,
+            Event as AppEvent, HxTriggerEvent, Replay, ToastLevel,
         },
         guild::GuildEvent,
+        meeting::{render_guild_meeting, MeetingEvent},
+        presence::{GuildViewersTemplate, PresenceEvent},
         topic::types::{TopicEvent, TopicStatus, TopicsListItemTemplate},
     },
 };
-use anyhow::anyhow;
 use axum::{
     extract::{Path, Query, State},
     http::HeaderValue,
@@ -31,16 +32,17 @@ use serde::Deserialize;
 use std::{
     collections::HashMap, convert::Infallible, sync::Arc, time::Duration,
 };
-use tokio::{select, time::sleep};
+use tokio::{select, sync::broadcast::error::RecvError, time::sleep};
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
-use tracing::{debug, error, info};
+use tracing::{debug, info, warn};
 use validator::Validate;
 
 use super::{
     ArchivedQueryParameter, CreateGuildFormTemplate, EditGuildFormTemplate,
-    GuildDraft, GuildFormDTO, GuildIdParameter, GuildListItemsTemplate,
-    GuildOverviewTemplate, GuildTemplate, GuildsListTemplate,
+    EventsQueryParameter, Guild, GuildDraft, GuildFormDTO, GuildIdParameter,
+    GuildListItemsTemplate, GuildOverviewTemplate, GuildTemplate,
+    GuildsListTemplate,
 };
 
 pub async fn get_guilds_page(
@@ -87,8 +89,8 @@ pub async fn post_guild_form_draft(
 
     if !form.member_search_term.is_empty() {
         members = app
-            .gitlab_service
-            .get_all_cached_members()
+            .member_directory
+            .get_all_members()
             .await
             .into_iter()
             .filter(|member| {
@@ -174,7 +176,7 @@ pub async fn create_guild(
         message: "Guild was created".to_string(),
     };
 
-    let event = HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+    let event = event.to_header_value()?;
 
     let location = Location {
         path: format!("/guilds/{}", created_guild.id),
@@ -213,17 +215,18 @@ pub async fn insert_new_member(
     State(app): State<Arc<App>>,
     Form(form): Form<GuildFormDTO>,
 ) -> Result<impl IntoResponse, AppError> {
-    let member_to_insert = match app
-        .gitlab_service
-        .get_cached_member(&parameters.member_id)
-        .await
-    {
-        Some(member) => member,
-        None => return Err(anyhow!("Failed to find user").into()),
-    };
+    let member_to_insert =
+        match app.member_directory.get_member(&parameters.member_id).await {
+            Some(member) => member,
+            None => {
+                return Err(AppError::NotFound(
+                    "Failed to find user".to_string(),
+                ))
+            }
+        };
 
     let mut existing_members: Vec<Member> =
-        app.gitlab_service.get_cached_members_by_ids(&form.member_ids).await;
+        app.member_directory.get_members_by_ids(&form.member_ids).await;
 
     if !form.member_ids.contains(&parameters.member_id) {
         existing_members.insert(0, member_to_insert)
@@ -304,8 +307,7 @@ pub async fn get_guild_overview(
                 message: "Failed to fetch guild".to_string(),
             };
 
-            let event =
-                HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+            let event = event.to_header_value()?;
 
             let mut headers = HeaderMap::new();
 
@@ -322,6 +324,7 @@ pub async fn get_guild_overview(
 
     Ok(GuildOverviewTemplate {
         can_edit: user.id == guild.created_by_user.id,
+        can_export_to_wiki: app.wiki_service.is_some(),
         user,
         guild_id: parameters.guild_id,
         guild,
@@ -334,7 +337,9 @@ pub async fn delete_guild(
     Path(parameters): Path<GuildIdParameter>,
     State(app): State<Arc<App>>,
 ) -> Result<impl IntoResponse, AppError> {
-    app.guilds_service.delete_guild(user.id, &parameters.guild_id).await?;
+    app.guilds_service
+        .delete_guild(&parameters.guild_id, Some(user.id))
+        .await?;
 
     let location = Location {
         path: "/guilds".to_string(),
@@ -350,8 +355,7 @@ pub async fn delete_guild(
         message: "Guild was deleted successfully".to_string(),
     };
 
-    let event: HeaderValue =
-        HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+    let event: HeaderValue = event.to_header_value()?;
 
     let mut headers = HeaderMap::new();
 
@@ -392,8 +396,7 @@ pub async fn get_edit_guild_form(
                 message: "Guild was not found".to_string(),
             };
 
-            let event: HeaderValue =
-                HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+            let event: HeaderValue = event.to_header_value()?;
 
             let mut headers = HeaderMap::new();
             headers.insert(
@@ -461,8 +464,7 @@ pub async fn update_guild(
         message: "Guild was updated".to_string(),
     };
 
-    let event: HeaderValue =
-        HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+    let event: HeaderValue = event.to_header_value()?;
 
     let location = Location {
         path: format!("/guilds/{}", updated_guild.id),
@@ -490,24 +492,44 @@ pub async fn subscribe_to_events(
     State(app): State<Arc<App>>,
     Authenticated(user): Authenticated,
     Path(parameters): Path<GuildIdParameter>,
+    Query(EventsQueryParameter { last_event_id }): Query<EventsQueryParameter>,
+    headers: HeaderMap,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let user_id = user.id.clone();
 
-    let guild = app.guilds_service.get_guild(user, &parameters.guild_id).await;
+    let guild =
+        app.guilds_service.get_guild(user.clone(), &parameters.guild_id).await;
 
     info!("User {} subscribed to events", &user_id);
 
-    let mut app_events_receiver = app.events_channel.0.subscribe();
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .or(last_event_id);
+
+    // subscribe before replaying so nothing falls in between
+    let (mut app_events_receiver, subscribed_at_id) =
+        app.event_bus.subscribe_from();
+
+    // the member counts as a viewer for as long as the stream is open
+    let presence_guard = match &guild {
+        Ok(Some(guild)) => Some(app.presence_service.join(&guild.id, user)),
+        _ => None,
+    };
 
     let (tx, rx) = tokio::sync::mpsc::channel::<Event>(10);
 
     let stream = ReceiverStream::<Event>::new(rx).map(|evt| Ok(evt));
 
     tokio::spawn(async move {
+        let _presence_guard = presence_guard;
+
         select! {
             _ = async {
                 loop {
-                    match tx.send(Event::default().data("ping").event("ping").id("ping")).await {
+                    // pings carry no id so they don't move the resume point
+                    match tx.send(Event::default().data("ping").event("ping")).await {
                         Ok(()) => {
                             sleep(Duration::from_secs(5)).await;
                         }
@@ -526,76 +548,68 @@ pub async fn subscribe_to_events(
                     _ => return
                 };
 
+                let mut last_sent_id = subscribed_at_id;
+
+                if let Some(last_event_id) = last_event_id {
+                    match app.event_bus.replay(&guild.id, last_event_id) {
+                        Replay::Events(events) => {
+                            debug!("Replaying {} events to user {}", events.len(), &user_id);
+
+                            last_sent_id = last_event_id;
+
+                            for event in events {
+                                last_sent_id = event.id;
+
+                                for sse_event in map_app_event(&app, &guild, user_id, event.event).await {
+                                    let _ = tx.send(sse_event.id(event.id.to_string())).await;
+                                }
+                            }
+                        }
+                        Replay::FullRefresh { last_id } => {
+                            debug!("Events since {last_event_id} are gone, user {} has to refresh", &user_id);
+
+                            last_sent_id = last_id;
+
+                            let _ = tx.send(full_refresh_event(last_id)).await;
+                        }
+                    }
+                }
 
                 loop {
                     match app_events_receiver.recv().await {
                         Ok(event) => {
                             debug!("new event {event:?}");
 
-                            match event {
-                                AppEvent::Guild(GuildEvent::Update(guild)) => {
-                                    if guild.id == parameters.guild_id {
-                                        let _ = tx.send(Event::default().data(" ").event("guild-updated")).await;
-                                    }
-                                }
-                                AppEvent::Topic(TopicEvent::Update(topic)) => {
-                                    if topic.guild_id == parameters.guild_id {
-                                        let _ = tx.send(Event::default().data(" ").event(format!("topic-updated-{}", topic.id))).await;
-                                    }
-                                }
-                                AppEvent::Topic(TopicEvent::Delete(topic)) => {
-                                    if topic.guild_id == parameters.guild_id {
-                                        let _ = tx.send(Event::default().data(" ").event(format!("topic-deleted-{}", topic.id))).await;
-                                    }
-                                }
-                                AppEvent::Topic(TopicEvent::Create(topic)) => {
-                                    if topic.guild_id == parameters.guild_id {
-
-                                        match app.topics_service.map_topic_with_user(&guild,topic, user_id).await {
-                                            Ok(topic) => {
-                                                let event_name = format!("topic-{}", &topic.status);
-
-                                                let topic_item = TopicsListItemTemplate { topic };
-                                                let _ = tx.send(Event::default().data(topic_item.to_string()).event(event_name)).await;
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                AppEvent::Topic(TopicEvent::StatusChange(topic)) => {
-                                    if topic.guild_id == parameters.guild_id {
-
-                                        match app.topics_service.map_topic_with_user(&guild,topic, user_id).await {
-                                            Ok(topic) => {
-                                                let _ = tx.send(Event::default().data(" ").event(format!("topic-deleted-{}", topic.id))).await;
-
-                                                let event_name = format!("topic-{}", &topic.status);
+                            // already delivered by the replay
+                            if event.id <= last_sent_id {
+                                continue;
+                            }
 
-                                                let topic_item = TopicsListItemTemplate { topic };
-                                                let _ = tx.send(Event::default().data(topic_item.to_string()).event(event_name)).await;
+                            if let AppEvent::Resync = event.event {
+                                last_sent_id = event.id;
 
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                AppEvent::Topic(TopicEvent::OrderChange(ids)) => {
-                                    debug!("TopicEvent::OrderChange ids: {ids:?}");
+                                let _ = tx.send(full_refresh_event(event.id)).await;
+                                continue;
+                            }
 
-                                    match serde_json::to_string(&ids) {
-                                        Ok(data) => {
-                                            let _ = tx.send(Event::default().data(data).event("topics-order-changed")).await;
-                                        }
-                                        _ => {}
-                                     }
+                            if event.event.get_guild_id().as_ref() != Some(&guild.id) {
+                                continue;
+                            }
 
-                                }
-                                _ => {}
+                            for sse_event in map_app_event(&app, &guild, user_id, event.event).await {
+                                let _ = tx.send(sse_event.id(event.id.to_string())).await;
                             }
                         }
-                        Err(err) => {
-                            error!("{err}");
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("User {} lagged behind by {count} events, asking for a full refresh", &user_id);
+
+                            app.event_bus.record_subscriber_lag(count);
+
+                            last_sent_id = app.event_bus.get_last_id();
+
+                            let _ = tx.send(full_refresh_event(last_sent_id)).await;
                         }
+                        Err(RecvError::Closed) => break,
                     }
                 }
             }=>{
@@ -605,3 +619,117 @@ pub async fn subscribe_to_events(
 
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
+
+fn full_refresh_event(last_id: u64) -> Event {
+    Event::default().data(" ").event("full-refresh").id(last_id.to_string())
+}
+
+async fn map_app_event(
+    app: &App,
+    guild: &Guild,
+    user_id: usize,
+    event: AppEvent,
+) -> Vec<Event> {
+    match event {
+        AppEvent::Guild(GuildEvent::Update(_)) => {
+            vec![Event::default().data(" ").event("guild-updated")]
+        }
+        AppEvent::Topic(TopicEvent::Update(topic)) => {
+            vec![Event::default()
+                .data(" ")
+                .event(format!("topic-updated-{}", topic.id))]
+        }
+        AppEvent::Topic(TopicEvent::Delete(topic)) => {
+            vec![Event::default()
+                .data(" ")
+                .event(format!("topic-deleted-{}", topic.id))]
+        }
+        AppEvent::Topic(TopicEvent::Create(topic)) => {
+            match app
+                .topics_service
+                .map_topic_with_user(guild, topic, user_id)
+                .await
+            {
+                Ok(topic) => {
+                    let event_name = format!("topic-{}", &topic.status);
+
+                    let topic_item = TopicsListItemTemplate { topic };
+
+                    vec![Event::default()
+                        .data(topic_item.to_string())
+                        .event(event_name)]
+                }
+                _ => vec![],
+            }
+        }
+        AppEvent::Topic(TopicEvent::StatusChange(topic)) => {
+            match app
+                .topics_service
+                .map_topic_with_user(guild, topic, user_id)
+                .await
+            {
+                Ok(topic) => {
+                    let deleted_event = Event::default()
+                        .data(" ")
+                        .event(format!("topic-deleted-{}", topic.id));
+
+                    let event_name = format!("topic-{}", &topic.status);
+
+                    let topic_item = TopicsListItemTemplate { topic };
+
+                    vec![
+                        deleted_event,
+                        Event::default()
+                            .data(topic_item.to_string())
+                            .event(event_name),
+                    ]
+                }
+                _ => vec![],
+            }
+        }
+        AppEvent::Topic(TopicEvent::OrderChange { topic_ids, .. }) => {
+            debug!("TopicEvent::OrderChange ids: {topic_ids:?}");
+
+            match serde_json::to_string(&topic_ids) {
+                Ok(data) => {
+                    vec![Event::default()
+                        .data(data)
+                        .event("topics-order-changed")]
+                }
+                _ => vec![],
+            }
+        }
+        AppEvent::Meeting(MeetingEvent::Change { meeting, .. }) => {
+            match render_guild_meeting(
+                app,
+                user_id,
+                guild,
+                meeting.map(|meeting| *meeting),
+                String::new(),
+            )
+            .await
+            {
+                Ok(template) => vec![Event::default()
+                    .data(template.to_string())
+                    .event("meeting-changed")],
+                _ => vec![],
+            }
+        }
+        AppEvent::Presence(event) => {
+            // recorded here too, this stream may get the event before the
+            // presence listener does
+            app.presence_service.record(&event);
+
+            let PresenceEvent::Change { guild_id, .. } = event;
+
+            let template = GuildViewersTemplate::new(
+                app.presence_service.get_viewers(&guild_id),
+            );
+
+            vec![Event::default()
+                .data(template.to_string())
+                .event("viewers-changed")]
+        }
+        _ => vec![],
+    }
+}
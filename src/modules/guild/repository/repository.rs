@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::libs::mongo::MongoDatabase;
 use anyhow::{Context, Result};
-use bson::{doc, oid::ObjectId};
+use bson::{doc, oid::ObjectId, DateTime};
 use futures_util::TryStreamExt;
 use mongodb::{
     options::{FindOptions, IndexOptions},
@@ -64,6 +64,46 @@ impl GuildsRepository {
         Ok(result)
     }
 
+    /// Guilds of the user newest first, starting after `before_id`.
+    pub async fn get_guilds_page(
+        &self,
+        user_id: usize,
+        before_id: Option<ObjectId>,
+        limit: i64,
+    ) -> Result<Vec<GuildDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<GuildDocument> =
+            database.collection(&self.collection_name);
+
+        let mut query = doc! {
+            "$or": [
+                {
+                    "created_by_user_id": user_id as u32,
+                },
+                {
+                    "member_ids": user_id as u32,
+                }
+            ]
+        };
+
+        if let Some(before_id) = before_id {
+            query.insert("_id", doc! {"$lt": before_id});
+        }
+
+        let options =
+            FindOptions::builder().sort(doc! {"_id": -1}).limit(limit).build();
+
+        let documents = collection
+            .find(query, options)
+            .await
+            .context("Failed to fetch page of guilds")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
     pub async fn get_guilds(
         &self,
         current_user_id: usize,
@@ -100,6 +140,27 @@ impl GuildsRepository {
         Ok(documents)
     }
 
+    pub async fn get_all_guilds(&self) -> Result<Vec<GuildDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<GuildDocument> =
+            database.collection(&self.collection_name);
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {
+                "created_at": -1
+            })
+            .build();
+
+        let documents = collection
+            .find(doc! {}, Some(find_options))
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
     pub async fn get_guild(
         &self,
         id: ObjectId,
@@ -191,4 +252,40 @@ impl GuildsRepository {
 
         Ok(result)
     }
+
+    /// Hands the guild over to `owner_id` along with its new members in a
+    /// single write. Nothing is matched when the guild was updated after
+    /// `updated_at`, so a concurrent edit of the members isn't overwritten.
+    pub async fn update_guild_owner(
+        &self,
+        id: ObjectId,
+        updated_at: DateTime,
+        owner_id: usize,
+        member_ids: Vec<usize>,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<GuildDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+            "updated_at": updated_at,
+        };
+
+        let member_ids: Vec<u32> =
+            member_ids.into_iter().map(|member_id| member_id as u32).collect();
+
+        let payload = doc! {
+            "$set": {
+                "member_ids": member_ids,
+                "created_by_user_id": owner_id as u32,
+                "updated_at": DateTime::now(),
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
 }
@@ -1,4 +1,4 @@
-use std::{str::FromStr, sync::Arc};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use anyhow::{bail, Result};
 use bson::oid::ObjectId;
@@ -7,18 +7,27 @@ use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tracing::error;
 
 use crate::{
+    configuration::DepartedMemberPolicy,
     libs::gitlab_api::gitlab_api::Member,
-    modules::{gitlab::GitlabService, topic::TopicsService},
+    modules::{
+        app::{parse_object_id, AppError},
+        member::{MemberDirectory, MembersService},
+        notification::{NotificationKind, NotificationsService},
+        topic::TopicsService,
+    },
 };
 
 use super::{
-    Guild, GuildEvent, GuildFormDTO, GuildsRepository, UpdateGuildPayload,
+    Guild, GuildDocument, GuildEvent, GuildFormDTO, GuildsRepository,
+    UpdateGuildPayload,
 };
 
 pub struct GuildsService {
     pub events_channel: (Sender<GuildEvent>, Receiver<GuildEvent>),
     repository: Arc<GuildsRepository>,
-    gitlab_service: Arc<GitlabService>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    members_service: Arc<MembersService>,
+    notifications_service: Arc<NotificationsService>,
     topics_service: Arc<TopicsService>,
 }
 
@@ -26,13 +35,52 @@ impl GuildsService {
     pub fn new(
         topics_service: Arc<TopicsService>,
         repository: Arc<GuildsRepository>,
-        gitlab_service: Arc<GitlabService>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        members_service: Arc<MembersService>,
+        notifications_service: Arc<NotificationsService>,
     ) -> Self {
         Self {
             events_channel: channel::<GuildEvent>(50),
             topics_service,
             repository,
-            gitlab_service,
+            member_directory,
+            members_service,
+            notifications_service,
+        }
+    }
+
+    /// Notifications never fail the action that caused them.
+    async fn notify_added_members(
+        &self,
+        guild: &Guild,
+        previous_member_ids: &[usize],
+        added_by_user: &Member,
+    ) {
+        let added_members = guild.members.iter().filter(|member| {
+            member.id != added_by_user.id
+                && !previous_member_ids.contains(&member.id)
+        });
+
+        for member in added_members {
+            if let Err(err) = self
+                .notifications_service
+                .notify(
+                    member.id,
+                    NotificationKind::AddedToGuild,
+                    &guild.id,
+                    None,
+                    format!(
+                        "@{} added you to the {} guild",
+                        added_by_user.username, guild.name
+                    ),
+                )
+                .await
+            {
+                error!(
+                    "Failed to notify user {} about guild {}: {err}",
+                    member.id, guild.id
+                );
+            }
         }
     }
 
@@ -42,8 +90,8 @@ impl GuildsService {
         created_by_user: Member,
     ) -> Result<Guild> {
         let members = self
-            .gitlab_service
-            .get_cached_members_by_ids(&form_dto.member_ids)
+            .member_directory
+            .get_members_by_ids(&form_dto.member_ids)
             .await;
 
         let guild = Guild {
@@ -68,25 +116,28 @@ impl GuildsService {
                 )
             })?;
 
-        let created_guild =
-            match self.get_guild(created_by_user, &created_id.to_hex()).await?
-            {
-                Some(guild) => guild,
-                None => {
-                    error!(
-                        "Failed to find created document by id {}",
-                        insert_result.inserted_id
-                    );
-
-                    bail!("Guild document was not found");
-                }
-            };
+        let created_guild = match self
+            .get_guild(created_by_user.clone(), &created_id.to_hex())
+            .await?
+        {
+            Some(guild) => guild,
+            None => {
+                error!(
+                    "Failed to find created document by id {}",
+                    insert_result.inserted_id
+                );
+
+                bail!("Guild document was not found");
+            }
+        };
 
         let _ = self
             .events_channel
             .0
             .send(GuildEvent::Create(created_guild.clone()));
 
+        self.notify_added_members(&created_guild, &[], &created_by_user).await;
+
         Ok(created_guild)
     }
 
@@ -95,7 +146,10 @@ impl GuildsService {
         user: Member,
         guild_id: &str,
     ) -> Result<Option<Guild>> {
-        let guild_id: ObjectId = ObjectId::from_str(guild_id)?;
+        let guild_id: ObjectId = match ObjectId::from_str(guild_id) {
+            Ok(guild_id) => guild_id,
+            Err(_) => return Ok(None),
+        };
 
         let guild_document =
             match self.repository.get_guild(guild_id, Some(user.id)).await? {
@@ -104,8 +158,8 @@ impl GuildsService {
             };
 
         let members = self
-            .gitlab_service
-            .get_cached_members_by_ids(&guild_document.member_ids)
+            .member_directory
+            .get_members_by_ids(&guild_document.member_ids)
             .await;
 
         let mut topics_count = self
@@ -116,17 +170,46 @@ impl GuildsService {
         let topics_count =
             topics_count.remove(&guild_id.to_hex()).unwrap_or(0);
 
+        let created_by_user = if guild_document.created_by_user_id == user.id {
+            user
+        } else {
+            self.member_directory
+                .get_member(&guild_document.created_by_user_id)
+                .await
+                .unwrap_or_else(|| {
+                    MembersService::former_member(
+                        guild_document.created_by_user_id,
+                    )
+                })
+        };
+
         Ok(Some(Guild {
             id: guild_document._id.to_hex(),
             name: guild_document.name,
             topics_count,
             members,
-            created_by_user: user,
+            created_by_user,
             updated_at: guild_document.updated_at.to_chrono(),
             created_at: guild_document.created_at.to_chrono(),
         }))
     }
 
+    /// Fetches a guild regardless of the membership of the current user.
+    pub async fn get_guild_by_id(
+        &self,
+        guild_id: &str,
+    ) -> Result<Option<Guild>> {
+        let guild_id = parse_object_id(guild_id, "guild")?;
+
+        let guild_document =
+            match self.repository.get_guild(guild_id, None).await? {
+                None => return Ok(None),
+                Some(document) => document,
+            };
+
+        Ok(self.map_guild_documents(vec![guild_document]).await?.pop())
+    }
+
     pub async fn get_guild_or_error(
         &self,
         user: Member,
@@ -134,13 +217,42 @@ impl GuildsService {
     ) -> Result<Guild> {
         match self.get_guild(user, guild_id).await? {
             Some(guild) => Ok(guild),
-            _ => bail!("Failed to find guild"),
+            _ => bail!(AppError::NotFound("Failed to find guild".to_string())),
         }
     }
 
     pub async fn get_guilds(&self, user_id: usize) -> Result<Vec<Guild>> {
         let documents = self.repository.get_guilds(user_id).await?;
 
+        self.map_guild_documents(documents).await
+    }
+
+    /// Guilds of the user newest first, `before_id` being the last guild of
+    /// the previous page.
+    pub async fn get_guilds_page(
+        &self,
+        user_id: usize,
+        before_id: Option<ObjectId>,
+        limit: usize,
+    ) -> Result<Vec<Guild>> {
+        let documents = self
+            .repository
+            .get_guilds_page(user_id, before_id, limit as i64)
+            .await?;
+
+        self.map_guild_documents(documents).await
+    }
+
+    pub async fn get_all_guilds(&self) -> Result<Vec<Guild>> {
+        let documents = self.repository.get_all_guilds().await?;
+
+        self.map_guild_documents(documents).await
+    }
+
+    async fn map_guild_documents(
+        &self,
+        documents: Vec<GuildDocument>,
+    ) -> Result<Vec<Guild>> {
         let document_ids =
             documents.iter().map(|document| document._id.clone()).collect();
 
@@ -158,68 +270,87 @@ impl GuildsService {
             }
         }
 
-        let all_members = self
-            .gitlab_service
-            .get_cached_members_by_ids(&all_members_ids)
-            .await;
+        let all_members =
+            self.members_service.get_members_by_ids(&all_members_ids).await;
 
         let mut all_topics_count = self
             .topics_service
             .get_topics_count_by_guild_ids(document_ids)
             .await?;
 
-        let guilds: Result<Vec<Guild>> = documents
+        let guilds: Vec<Guild> = documents
             .into_iter()
             .map(|document| {
-                let members = all_members
+                let members = document
+                    .member_ids
                     .iter()
-                    .filter(|member| document.member_ids.contains(&member.id))
-                    .map(|member| member.clone())
+                    .filter_map(|member_id| all_members.get(member_id))
+                    .filter(|member| !member.is_former_member)
+                    .cloned()
                     .collect();
 
                 let created_by_user = all_members
-                    .iter()
-                    .find(|member| member.id == document.created_by_user_id);
-
-                if created_by_user.is_none() {
-                    bail!(format!(
-                        "Failed to fetch user {} who created guild {}",
-                        document.created_by_user_id,
-                        document._id.to_hex()
-                    ))
-                }
+                    .get(&document.created_by_user_id)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        MembersService::former_member(
+                            document.created_by_user_id,
+                        )
+                    });
 
                 let topics_count = all_topics_count
                     .remove(&document._id.to_hex())
                     .unwrap_or(0);
 
-                return Ok(Guild {
+                Guild {
                     id: document._id.to_hex(),
                     name: document.name,
                     members,
                     topics_count,
-                    created_by_user: created_by_user.unwrap().clone(),
+                    created_by_user,
                     updated_at: document.updated_at.to_chrono(),
                     created_at: document.created_at.to_chrono(),
-                });
+                }
             })
             .collect();
 
-        Ok(guilds?)
+        Ok(guilds)
     }
 
-    pub async fn delete_guild(
+    pub async fn handle_departed_member(
         &self,
         user_id: usize,
+        policy: DepartedMemberPolicy,
+    ) -> Result<()> {
+        let guild_owners: HashMap<ObjectId, usize> = self
+            .repository
+            .get_all_guilds()
+            .await?
+            .into_iter()
+            .map(|document| (document._id, document.created_by_user_id))
+            .collect();
+
+        self.topics_service
+            .handle_departed_member(user_id, policy, &guild_owners)
+            .await
+    }
+
+    /// Deletes the guild if `owner_id` owns it, or whoever owns it when no
+    /// owner is given.
+    pub async fn delete_guild(
+        &self,
         guild_id: &str,
+        owner_id: Option<usize>,
     ) -> Result<()> {
         let result = self
             .repository
-            .delete_guild(ObjectId::from_str(guild_id)?, Some(user_id))
+            .delete_guild(parse_object_id(guild_id, "guild")?, owner_id)
             .await?;
 
         if result.deleted_count == 0 {
-            bail!("Failed to delete guild")
+            bail!(AppError::NotFound(format!(
+                "Failed to find guild {guild_id}"
+            )))
         }
 
         let _ = self
@@ -236,9 +367,25 @@ impl GuildsService {
         form_dto: GuildFormDTO,
         updated_by_user: Member,
     ) -> Result<Guild> {
+        let guild = match self.get_guild_by_id(&guild_id).await? {
+            Some(guild) => guild,
+            None => bail!(AppError::NotFound(format!(
+                "Failed to find guild {guild_id}"
+            ))),
+        };
+
+        if guild.created_by_user.id != updated_by_user.id {
+            bail!(AppError::Forbidden(
+                "Only the owner can edit the guild".to_string()
+            ))
+        }
+
+        let previous_member_ids: Vec<usize> =
+            guild.members.iter().map(|member| member.id).collect();
+
         let members = self
-            .gitlab_service
-            .get_cached_members_by_ids(&form_dto.member_ids)
+            .member_directory
+            .get_members_by_ids(&form_dto.member_ids)
             .await;
 
         let payload = UpdateGuildPayload {
@@ -250,18 +397,21 @@ impl GuildsService {
         let update_result = self
             .repository
             .update_guild(
-                ObjectId::from_str(&guild_id)?,
+                parse_object_id(&guild_id, "guild")?,
                 updated_by_user.id,
                 payload,
             )
             .await?;
 
-        if update_result.modified_count == 0 {
-            bail!("Failed to update guild")
+        // deleted or handed over since it was read
+        if update_result.matched_count == 0 {
+            bail!(AppError::NotFound(format!(
+                "Failed to find guild {guild_id}"
+            )))
         }
 
         let updated_guild = match self
-            .get_guild(updated_by_user, &guild_id)
+            .get_guild(updated_by_user.clone(), &guild_id)
             .await?
         {
             Some(guild) => guild,
@@ -272,6 +422,70 @@ impl GuildsService {
             }
         };
 
+        let _ = self
+            .events_channel
+            .0
+            .send(GuildEvent::Update(updated_guild.clone()));
+
+        self.notify_added_members(
+            &updated_guild,
+            &previous_member_ids,
+            &updated_by_user,
+        )
+        .await;
+
+        Ok(updated_guild)
+    }
+
+    pub async fn reassign_owner(
+        &self,
+        guild_id: &str,
+        new_owner_id: usize,
+    ) -> Result<Guild> {
+        if self.member_directory.get_member(&new_owner_id).await.is_none() {
+            bail!(AppError::NotFound(format!(
+                "Failed to find member {new_owner_id}"
+            )))
+        }
+
+        let document = match self
+            .repository
+            .get_guild(parse_object_id(guild_id, "guild")?, None)
+            .await?
+        {
+            Some(document) => document,
+            None => bail!(AppError::NotFound(format!(
+                "Failed to find guild {guild_id}"
+            ))),
+        };
+
+        let member_ids = get_reassigned_member_ids(
+            &document.member_ids,
+            document.created_by_user_id,
+            new_owner_id,
+        );
+
+        let update_result = self
+            .repository
+            .update_guild_owner(
+                document._id,
+                document.updated_at,
+                new_owner_id,
+                member_ids,
+            )
+            .await?;
+
+        if update_result.matched_count == 0 {
+            bail!(AppError::Conflict(
+                "The guild was changed in the meantime, try again".to_string()
+            ))
+        }
+
+        let updated_guild = match self.get_guild_by_id(guild_id).await? {
+            Some(guild) => guild,
+            None => bail!("Guild document was not found"),
+        };
+
         let _ = self
             .events_channel
             .0
@@ -280,3 +494,57 @@ impl GuildsService {
         Ok(updated_guild)
     }
 }
+
+/// Members of the guild once `new_owner_id` owns it. The previous owner stays
+/// in the guild as a member and the new one is no longer listed as one.
+fn get_reassigned_member_ids(
+    member_ids: &[usize],
+    previous_owner_id: usize,
+    new_owner_id: usize,
+) -> Vec<usize> {
+    if previous_owner_id == new_owner_id {
+        return member_ids.to_vec();
+    }
+
+    let mut member_ids: Vec<usize> = member_ids
+        .iter()
+        .copied()
+        .filter(|member_id| *member_id != new_owner_id)
+        .collect();
+
+    if !member_ids.contains(&previous_owner_id) {
+        member_ids.push(previous_owner_id);
+    }
+
+    member_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassigning_keeps_the_previous_owner_as_a_member() {
+        assert_eq!(get_reassigned_member_ids(&[2, 3], 1, 2), vec![3, 1]);
+    }
+
+    #[test]
+    fn reassigning_to_an_outsider_adds_no_one_else() {
+        assert_eq!(get_reassigned_member_ids(&[2, 3], 1, 4), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn reassigning_in_a_guild_without_members() {
+        assert_eq!(get_reassigned_member_ids(&[], 1, 4), vec![1]);
+    }
+
+    #[test]
+    fn reassigning_does_not_list_the_previous_owner_twice() {
+        assert_eq!(get_reassigned_member_ids(&[1, 2], 1, 2), vec![1]);
+    }
+
+    #[test]
+    fn reassigning_to_the_current_owner_changes_nothing() {
+        assert_eq!(get_reassigned_member_ids(&[2, 3], 1, 1), vec![2, 3]);
+    }
+}
@@ -128,6 +128,7 @@ pub struct GuildOverviewTemplate {
     pub user: Member,
     pub guild: Guild,
     pub can_edit: bool,
+    pub can_export_to_wiki: bool,
 }
 
 #[derive(Template)]
@@ -141,6 +142,13 @@ pub struct GuildIdParameter {
     pub guild_id: String,
 }
 
+/// Fallback for the `Last-Event-ID` header, which the sse extension can't set
+/// when it reconnects with a new event source.
+#[derive(Deserialize)]
+pub struct EventsQueryParameter {
+    pub last_event_id: Option<u64>,
+}
+
 #[derive(Deserialize)]
 pub struct ArchivedQueryParameter {
     pub archived: Option<bool>,
@@ -0,0 +1,4 @@
+use std::time::Duration;
+
+/// Meetings nobody ended are dropped after a day.
+pub const MEETING_TTL: Duration = Duration::from_secs(60 * 60 * 24);
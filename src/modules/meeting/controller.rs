@@ -0,0 +1,161 @@
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use std::sync::Arc;
+use validator::Validate;
+
+use crate::{
+    libs::{axum::Form, gitlab_api::gitlab_api::Member},
+    modules::{
+        app::{app::App, user_extractor::Authenticated, AppError},
+        guild::{Guild, GuildIdParameter},
+    },
+};
+
+use super::{
+    GuildMeetingTemplate, Meeting, MeetingFormDTO, MeetingStepFormDTO,
+};
+
+/// Renders the meeting for the user, the current topic is shared by every
+/// viewer and only personalized here.
+pub async fn render_guild_meeting(
+    app: &App,
+    user_id: usize,
+    guild: &Guild,
+    meeting: Option<Meeting>,
+    error_message: String,
+) -> Result<GuildMeetingTemplate> {
+    let current_topic = match meeting
+        .as_ref()
+        .and_then(|meeting| meeting.current_topic.clone())
+    {
+        Some(topic) => Some(
+            app.topics_service
+                .map_topic_with_user(guild, topic, user_id)
+                .await?,
+        ),
+        None => None,
+    };
+
+    Ok(GuildMeetingTemplate {
+        guild_id: guild.id.clone(),
+        meeting,
+        current_topic,
+        can_control: user_id == guild.created_by_user.id,
+        error_message,
+    })
+}
+
+async fn get_owned_guild(
+    app: &App,
+    user: &Member,
+    guild_id: &str,
+) -> Result<Guild, AppError> {
+    let guild =
+        app.guilds_service.get_guild_or_error(user.clone(), guild_id).await?;
+
+    if user.id != guild.created_by_user.id {
+        return Err(AppError::Forbidden(
+            "Only the guild owner can run meetings".to_string(),
+        ));
+    }
+
+    Ok(guild)
+}
+
+pub async fn get_guild_meeting(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    let meeting = app.meetings_service.get_meeting(&guild).await?;
+
+    Ok(render_guild_meeting(&app, user.id, &guild, meeting, String::new())
+        .await?)
+}
+
+pub async fn start_meeting(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+    Form(form): Form<MeetingFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    if let Err(errors) = form.validate() {
+        let error_message = errors
+            .field_errors()
+            .into_values()
+            .flatten()
+            .filter_map(|error| error.message.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        return Ok(render_guild_meeting(
+            &app,
+            user.id,
+            &guild,
+            None,
+            error_message,
+        )
+        .await?);
+    }
+
+    let (meeting, error_message) = match app
+        .meetings_service
+        .start_meeting(&guild, user.id, form.topic_duration_minutes)
+        .await
+    {
+        Ok(meeting) => (Some(meeting), String::new()),
+        Err(err) => match AppError::from(err) {
+            AppError::Internal(err) => return Err(AppError::Internal(err)),
+            err => (None, err.get_message()),
+        },
+    };
+
+    Ok(render_guild_meeting(&app, user.id, &guild, meeting, error_message)
+        .await?)
+}
+
+pub async fn step_meeting(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+    Form(form): Form<MeetingStepFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    let meeting = app
+        .meetings_service
+        .step(&guild, user.id, &form.topic_id, form.is_presented)
+        .await?;
+
+    Ok(render_guild_meeting(
+        &app,
+        user.id,
+        &guild,
+        Some(meeting),
+        String::new(),
+    )
+    .await?)
+}
+
+pub async fn end_meeting(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    app.meetings_service.end_meeting(&guild).await?;
+
+    Ok(render_guild_meeting(&app, user.id, &guild, None, String::new())
+        .await?)
+}
@@ -0,0 +1,10 @@
+pub mod constants;
+pub mod controller;
+pub mod repository;
+pub mod service;
+pub mod types;
+
+pub use controller::*;
+pub use repository::*;
+pub use service::MeetingsService;
+pub use types::*;
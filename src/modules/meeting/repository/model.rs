@@ -0,0 +1,19 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// The meeting a guild is running, there is at most one per guild.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MeetingDocument {
+    pub _id: ObjectId,
+    pub guild_id: ObjectId,
+    /// None once every topic was presented or skipped.
+    pub current_topic_id: Option<ObjectId>,
+    pub skipped_topic_ids: Vec<ObjectId>,
+    pub presented_topics_count: u32,
+    pub topic_duration_minutes: u32,
+    pub topic_started_at: DateTime,
+    pub started_by_user_id: usize,
+    pub updated_at: DateTime,
+    pub created_at: DateTime,
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use crate::libs::mongo::MongoDatabase;
+use anyhow::{Context, Result};
+use bson::{doc, oid::ObjectId};
+use mongodb::{
+    options::{IndexOptions, ReplaceOptions},
+    results::{DeleteResult, UpdateResult},
+    Collection, IndexModel,
+};
+
+use super::MeetingDocument;
+use crate::modules::meeting::constants::MEETING_TTL;
+
+pub struct MeetingsRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+}
+
+impl MeetingsRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = MeetingsRepository {
+            database,
+            collection_name: String::from("meetings"),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! {"guild_id":1})
+                .options(
+                    IndexOptions::builder()
+                        .name("guild_id".to_string())
+                        .unique(true)
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! {"created_at":1})
+                .options(
+                    IndexOptions::builder()
+                        .name("created_at".to_string())
+                        .expire_after(MEETING_TTL)
+                        .build(),
+                )
+                .build(),
+        ];
+
+        self.database
+            .create_indexes::<MeetingDocument>(&self.collection_name, indexes)
+            .await
+    }
+
+    pub async fn get_meeting(
+        &self,
+        guild_id: ObjectId,
+    ) -> Result<Option<MeetingDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MeetingDocument> =
+            database.collection(&self.collection_name);
+
+        let document =
+            collection.find_one(doc! {"guild_id": guild_id}, None).await?;
+
+        Ok(document)
+    }
+
+    /// Starts the meeting of the guild over.
+    pub async fn upsert_meeting(
+        &self,
+        document: MeetingDocument,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MeetingDocument> =
+            database.collection(&self.collection_name);
+
+        let options = ReplaceOptions::builder().upsert(true).build();
+
+        let result = collection
+            .replace_one(
+                doc! {"guild_id": document.guild_id},
+                document,
+                options,
+            )
+            .await
+            .with_context(|| "Failed to upsert meeting document")?;
+
+        Ok(result)
+    }
+
+    /// Only replaces the meeting while `current_topic_id` is still its
+    /// current topic, so two clicks don't move the meeting twice.
+    pub async fn replace_meeting(
+        &self,
+        document: MeetingDocument,
+        current_topic_id: ObjectId,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MeetingDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .replace_one(
+                doc! {
+                    "_id": document._id,
+                    "current_topic_id": current_topic_id,
+                },
+                document,
+                None,
+            )
+            .await
+            .with_context(|| "Failed to replace meeting document")?;
+
+        Ok(result)
+    }
+
+    pub async fn delete_meeting(
+        &self,
+        guild_id: ObjectId,
+    ) -> Result<DeleteResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MeetingDocument> =
+            database.collection(&self.collection_name);
+
+        let result =
+            collection.delete_one(doc! {"guild_id": guild_id}, None).await?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,364 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{bail, Result};
+use bson::{oid::ObjectId, DateTime};
+use chrono::{Duration, Utc};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tracing::error;
+
+use crate::modules::{
+    app::{parse_object_id, AppError},
+    guild::Guild,
+    topic::{types::TopicStatus, TopicsService},
+};
+
+use super::{Meeting, MeetingDocument, MeetingEvent, MeetingsRepository};
+
+/// Runs guild meetings, where the owner steps through the open topics in
+/// vote order while every viewer follows along.
+pub struct MeetingsService {
+    repository: Arc<MeetingsRepository>,
+    topics_service: Arc<TopicsService>,
+    pub events_channel: (Sender<MeetingEvent>, Receiver<MeetingEvent>),
+}
+
+impl MeetingsService {
+    pub fn new(
+        repository: Arc<MeetingsRepository>,
+        topics_service: Arc<TopicsService>,
+    ) -> Self {
+        Self {
+            repository,
+            topics_service,
+            events_channel: channel::<MeetingEvent>(50),
+        }
+    }
+
+    pub async fn get_meeting(&self, guild: &Guild) -> Result<Option<Meeting>> {
+        let document = self
+            .repository
+            .get_meeting(ObjectId::from_str(&guild.id)?)
+            .await?;
+
+        match document {
+            Some(document) => {
+                Ok(Some(self.map_meeting(guild, document).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Starts with the most voted topic, a running meeting starts over.
+    pub async fn start_meeting(
+        &self,
+        guild: &Guild,
+        user_id: usize,
+        topic_duration_minutes: u32,
+    ) -> Result<Meeting> {
+        let topic_ids = self.get_topic_ids_sorted(guild).await?;
+
+        let current_topic_id = match topic_ids.first() {
+            Some(topic_id) => *topic_id,
+            None => bail!(AppError::Conflict(
+                "There are no open topics to talk about".to_string()
+            )),
+        };
+
+        let now = DateTime::now();
+
+        let document = MeetingDocument {
+            _id: ObjectId::new(),
+            guild_id: ObjectId::from_str(&guild.id)?,
+            current_topic_id: Some(current_topic_id),
+            skipped_topic_ids: vec![],
+            presented_topics_count: 0,
+            topic_duration_minutes,
+            topic_started_at: now,
+            started_by_user_id: user_id,
+            updated_at: now,
+            created_at: now,
+        };
+
+        self.repository.upsert_meeting(document.clone()).await?;
+
+        let meeting = self.map_meeting(guild, document).await?;
+
+        self.notify(&guild.id, Some(meeting.clone()));
+
+        Ok(meeting)
+    }
+
+    /// Moves on from `topic_id` to the most voted topic that is left, votes
+    /// cast during the meeting count. Presented topics are archived.
+    pub async fn step(
+        &self,
+        guild: &Guild,
+        user_id: usize,
+        topic_id: &str,
+        is_presented: bool,
+    ) -> Result<Meeting> {
+        let mut document = match self
+            .repository
+            .get_meeting(ObjectId::from_str(&guild.id)?)
+            .await?
+        {
+            Some(document) => document,
+            None => {
+                bail!(AppError::Conflict("The meeting is over".to_string()))
+            }
+        };
+
+        let topic_id = parse_object_id(topic_id, "topic")?;
+
+        // the meeting already moved on, e.g. after a double click
+        if document.current_topic_id != Some(topic_id) {
+            return self.map_meeting(guild, document).await;
+        }
+
+        let topic_ids = self.get_topic_ids_sorted(guild).await?;
+
+        advance_meeting(
+            &mut document,
+            is_presented,
+            &topic_ids,
+            DateTime::now(),
+        );
+
+        let result = self
+            .repository
+            .replace_meeting(document.clone(), topic_id)
+            .await?;
+
+        if result.modified_count == 0 {
+            return match self.get_meeting(guild).await? {
+                Some(meeting) => Ok(meeting),
+                None => bail!(AppError::Conflict(
+                    "The meeting is over".to_string()
+                )),
+            };
+        }
+
+        if is_presented {
+            if let Err(err) = self
+                .topics_service
+                .change_topic_status(
+                    &topic_id.to_hex(),
+                    user_id,
+                    TopicStatus::Archived,
+                    guild,
+                )
+                .await
+            {
+                error!("Failed to archive presented topic {topic_id}: {err}");
+            }
+        }
+
+        let meeting = self.map_meeting(guild, document).await?;
+
+        self.notify(&guild.id, Some(meeting.clone()));
+
+        Ok(meeting)
+    }
+
+    pub async fn end_meeting(&self, guild: &Guild) -> Result<()> {
+        self.repository.delete_meeting(ObjectId::from_str(&guild.id)?).await?;
+
+        self.notify(&guild.id, None);
+
+        Ok(())
+    }
+
+    /// Open topics of the guild, most voted first.
+    async fn get_topic_ids_sorted(
+        &self,
+        guild: &Guild,
+    ) -> Result<Vec<ObjectId>> {
+        Ok(self
+            .topics_service
+            .get_topic_ids_sorted(&guild.id)
+            .await?
+            .iter()
+            .filter_map(|topic_id| ObjectId::from_str(topic_id).ok())
+            .collect())
+    }
+
+    async fn map_meeting(
+        &self,
+        guild: &Guild,
+        document: MeetingDocument,
+    ) -> Result<Meeting> {
+        let current_topic = match document.current_topic_id {
+            Some(topic_id) => {
+                self.topics_service.get_topic_by_id(&topic_id.to_hex()).await?
+            }
+            None => None,
+        };
+
+        let topic_ids = self.get_topic_ids_sorted(guild).await?;
+
+        Ok(Meeting {
+            guild_id: guild.id.clone(),
+            current_topic_id: document
+                .current_topic_id
+                .map(|topic_id| topic_id.to_hex()),
+            current_topic,
+            presented_topics_count: document.presented_topics_count,
+            remaining_topics_count: count_remaining_topics(
+                &document, &topic_ids,
+            ),
+            topic_duration_minutes: document.topic_duration_minutes,
+            topic_ends_at: get_topic_ends_at(&document),
+        })
+    }
+
+    fn notify(&self, guild_id: &str, meeting: Option<Meeting>) {
+        let _ = self.events_channel.0.send(MeetingEvent::Change {
+            guild_id: guild_id.to_string(),
+            meeting: meeting.map(Box::new),
+        });
+    }
+}
+
+/// Moves the meeting on from its current topic, which was presented or
+/// skipped, to the most voted topic that is left. The timer starts over.
+fn advance_meeting(
+    document: &mut MeetingDocument,
+    is_presented: bool,
+    topic_ids: &[ObjectId],
+    now: DateTime,
+) {
+    let Some(topic_id) = document.current_topic_id else {
+        return;
+    };
+
+    if is_presented {
+        document.presented_topics_count += 1;
+    } else {
+        document.skipped_topic_ids.push(topic_id);
+    }
+
+    // a presented topic is only archived after the meeting moved on
+    document.current_topic_id = topic_ids.iter().copied().find(|id| {
+        *id != topic_id && !document.skipped_topic_ids.contains(id)
+    });
+    document.topic_started_at = now;
+    document.updated_at = now;
+}
+
+/// Open topics that weren't skipped, the current one aside.
+fn count_remaining_topics(
+    document: &MeetingDocument,
+    topic_ids: &[ObjectId],
+) -> usize {
+    topic_ids
+        .iter()
+        .filter(|topic_id| {
+            document.current_topic_id != Some(**topic_id)
+                && !document.skipped_topic_ids.contains(topic_id)
+        })
+        .count()
+}
+
+fn get_topic_ends_at(document: &MeetingDocument) -> chrono::DateTime<Utc> {
+    document.topic_started_at.to_chrono()
+        + Duration::minutes(document.topic_duration_minutes as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meeting(topic_ids: &[ObjectId]) -> MeetingDocument {
+        let started_at = DateTime::from_millis(0);
+
+        MeetingDocument {
+            _id: ObjectId::new(),
+            guild_id: ObjectId::new(),
+            current_topic_id: topic_ids.first().copied(),
+            skipped_topic_ids: vec![],
+            presented_topics_count: 0,
+            topic_duration_minutes: 5,
+            topic_started_at: started_at,
+            started_by_user_id: 1,
+            updated_at: started_at,
+            created_at: started_at,
+        }
+    }
+
+    fn topic_ids(count: usize) -> Vec<ObjectId> {
+        (0..count).map(|_| ObjectId::new()).collect()
+    }
+
+    #[test]
+    fn presented_topics_are_counted() {
+        let topic_ids = topic_ids(3);
+        let mut document = meeting(&topic_ids);
+
+        advance_meeting(&mut document, true, &topic_ids, DateTime::now());
+
+        assert_eq!(document.current_topic_id, Some(topic_ids[1]));
+        assert_eq!(document.presented_topics_count, 1);
+        assert!(document.skipped_topic_ids.is_empty());
+        assert_eq!(count_remaining_topics(&document, &topic_ids[1..]), 1);
+    }
+
+    #[test]
+    fn skipped_topics_are_not_picked_again() {
+        let topic_ids = topic_ids(2);
+        let mut document = meeting(&topic_ids);
+
+        advance_meeting(&mut document, false, &topic_ids, DateTime::now());
+
+        assert_eq!(document.current_topic_id, Some(topic_ids[1]));
+        assert_eq!(document.skipped_topic_ids, vec![topic_ids[0]]);
+        assert_eq!(count_remaining_topics(&document, &topic_ids), 0);
+
+        advance_meeting(&mut document, false, &topic_ids, DateTime::now());
+
+        assert_eq!(document.current_topic_id, None);
+        assert_eq!(document.presented_topics_count, 0);
+    }
+
+    #[test]
+    fn votes_cast_during_the_meeting_pick_the_next_topic() {
+        let topic_ids = topic_ids(3);
+        let mut document = meeting(&topic_ids);
+
+        let reordered = vec![topic_ids[0], topic_ids[2], topic_ids[1]];
+
+        advance_meeting(&mut document, true, &reordered, DateTime::now());
+
+        assert_eq!(document.current_topic_id, Some(topic_ids[2]));
+    }
+
+    #[test]
+    fn finished_meetings_stay_finished() {
+        let mut document = meeting(&[]);
+
+        advance_meeting(&mut document, true, &topic_ids(2), DateTime::now());
+
+        assert_eq!(document.current_topic_id, None);
+        assert_eq!(document.presented_topics_count, 0);
+    }
+
+    #[test]
+    fn the_timer_starts_over_with_every_topic() {
+        let topic_ids = topic_ids(2);
+        let mut document = meeting(&topic_ids);
+
+        assert_eq!(
+            get_topic_ends_at(&document),
+            chrono::DateTime::UNIX_EPOCH + Duration::minutes(5)
+        );
+
+        let now = DateTime::from_millis(60 * 1000);
+
+        advance_meeting(&mut document, true, &topic_ids, now);
+
+        assert_eq!(document.topic_started_at, now);
+        assert_eq!(
+            get_topic_ends_at(&document),
+            chrono::DateTime::UNIX_EPOCH + Duration::minutes(6)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use askama_axum::Template;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    libs::serialization,
+    modules::{
+        app::Event,
+        topic::types::{Topic, TopicPersonalized},
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Meeting {
+    pub guild_id: String,
+    /// None once every topic was presented or skipped.
+    pub current_topic_id: Option<String>,
+    /// None as well when the current topic was deleted in the meantime.
+    /// Shared by every viewer, it is personalized when rendered.
+    pub current_topic: Option<Topic>,
+    pub presented_topics_count: u32,
+    pub remaining_topics_count: usize,
+    pub topic_duration_minutes: u32,
+    #[serde(with = "serialization::chrono_date")]
+    pub topic_ends_at: DateTime<Utc>,
+}
+
+impl Meeting {
+    /// The countdown runs in the browser of every viewer.
+    pub fn get_topic_ends_at_iso(&self) -> String {
+        self.topic_ends_at.to_rfc3339()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum MeetingEvent {
+    /// The meeting started, moved on or ended when `meeting` is None.
+    Change { guild_id: String, meeting: Option<Box<Meeting>> },
+}
+
+impl From<MeetingEvent> for Event {
+    fn from(event: MeetingEvent) -> Event {
+        Event::Meeting(event)
+    }
+}
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct MeetingFormDTO {
+    #[validate(range(
+        min = 1,
+        max = 60,
+        message = "Time per topic must be between 1 and 60 minutes"
+    ))]
+    pub topic_duration_minutes: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MeetingStepFormDTO {
+    /// Topic the owner saw as current, steps for older topics are ignored.
+    pub topic_id: String,
+    /// Archives the topic, otherwise it is skipped.
+    pub is_presented: bool,
+}
+
+#[derive(Template)]
+#[template(path = "components/meeting/guild-meeting.html")]
+pub struct GuildMeetingTemplate {
+    pub guild_id: String,
+    pub meeting: Option<Meeting>,
+    /// Current topic of the meeting as seen by the viewer.
+    pub current_topic: Option<TopicPersonalized>,
+    pub can_control: bool,
+    pub error_message: String,
+}
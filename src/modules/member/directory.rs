@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use futures::future::join_all;
+
+use crate::libs::gitlab_api::gitlab_api::Member;
+
+/// Source of truth for who the members of the instance are.
+#[async_trait]
+pub trait MemberDirectory {
+    fn get_directory_name(&self) -> String;
+
+    async fn get_member(&self, user_id: &usize) -> Option<Member>;
+
+    async fn get_all_members(&self) -> Vec<Member>;
+
+    async fn get_members_by_ids(&self, user_ids: &[usize]) -> Vec<Member> {
+        let members = user_ids.iter().map(|id| self.get_member(id));
+
+        join_all(members).await.into_iter().flatten().collect()
+    }
+
+    /// Address emails to the member are sent to, when the directory knows it.
+    async fn get_member_email(&self, _user_id: &usize) -> Option<String> {
+        None
+    }
+
+    /// Directories that don't delegate login to an identity provider verify
+    /// credentials themselves.
+    fn supports_password_login(&self) -> bool {
+        false
+    }
+
+    async fn authenticate(
+        &self,
+        _username: &str,
+        _password: &str,
+    ) -> Option<Member> {
+        None
+    }
+}
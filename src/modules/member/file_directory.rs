@@ -0,0 +1,279 @@
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+use tracing::error;
+
+use crate::libs::gitlab_api::gitlab_api::Member;
+
+use super::MemberDirectory;
+
+/// An entry of a static roster.
+#[derive(Deserialize, Debug, Clone)]
+pub struct FileMember {
+    pub id: usize,
+    pub username: String,
+    pub name: String,
+    pub avatar_url: Option<String>,
+    pub email: Option<String>,
+    /// Argon2 hash in PHC format. Members without one can't log in.
+    pub password_hash: Option<String>,
+}
+
+impl From<FileMember> for Member {
+    fn from(file_member: FileMember) -> Member {
+        Member {
+            id: file_member.id,
+            username: file_member.username,
+            name: file_member.name,
+            avatar_url: file_member.avatar_url.unwrap_or_else(|| {
+                "/static/images/user-avatar.png".to_string()
+            }),
+            ..Member::default()
+        }
+    }
+}
+
+/// Member directory backed by a YAML, JSON or LDIF file, loaded once on
+/// startup.
+pub struct FileMemberDirectory {
+    members: HashMap<usize, FileMember>,
+}
+
+impl FileMemberDirectory {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read member file {path}"))?;
+
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let members: Vec<FileMember> = match extension.as_str() {
+            "yaml" | "yml" => serde_yaml::from_str(&content)?,
+            "json" => serde_json::from_str(&content)?,
+            "ldif" => parse_ldif(&content)?,
+            _ => bail!("Unsupported member file format {extension:?}"),
+        };
+
+        let mut members_by_id = HashMap::new();
+
+        for member in members {
+            if members_by_id.contains_key(&member.id) {
+                bail!("Member {} is listed twice in {path}", member.id);
+            }
+
+            members_by_id.insert(member.id, member);
+        }
+
+        Ok(Self { members: members_by_id })
+    }
+}
+
+#[async_trait]
+impl MemberDirectory for FileMemberDirectory {
+    fn get_directory_name(&self) -> String {
+        "file".to_string()
+    }
+
+    async fn get_member(&self, user_id: &usize) -> Option<Member> {
+        self.members.get(user_id).cloned().map(Member::from)
+    }
+
+    async fn get_all_members(&self) -> Vec<Member> {
+        self.members.values().cloned().map(Member::from).collect()
+    }
+
+    async fn get_member_email(&self, user_id: &usize) -> Option<String> {
+        self.members.get(user_id).and_then(|member| member.email.clone())
+    }
+
+    fn supports_password_login(&self) -> bool {
+        true
+    }
+
+    async fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Option<Member> {
+        let member = self.members.values().find(|member| {
+            member.username.eq_ignore_ascii_case(username.trim())
+        })?;
+
+        let password_hash = member.password_hash.clone()?;
+        let password = password.to_string();
+
+        // hashing is deliberately slow, keep it off the async workers
+        let is_valid = tokio::task::spawn_blocking(move || {
+            match PasswordHash::new(&password_hash) {
+                Ok(hash) => Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok(),
+                Err(err) => {
+                    error!("Invalid password hash: {err}");
+                    false
+                }
+            }
+        })
+        .await
+        .unwrap_or(false);
+
+        is_valid.then(|| member.clone().into())
+    }
+}
+
+/// Reads `uidNumber`, `uid`, `displayName` (or `cn`), `labeledURI` as the
+/// avatar, `mail` and an argon2 `userPassword` from every entry that has them.
+fn parse_ldif(content: &str) -> Result<Vec<FileMember>> {
+    // continuation lines start with a single space
+    let content = content.replace("\r\n", "\n").replace("\n ", "");
+
+    let mut members = Vec::new();
+
+    for entry in content.split("\n\n") {
+        let mut attributes: HashMap<String, String> = HashMap::new();
+
+        for line in entry.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            let value = match value.strip_prefix(':') {
+                Some(encoded) => {
+                    String::from_utf8(STANDARD.decode(encoded.trim())?)?
+                }
+                None => value.trim().to_string(),
+            };
+
+            attributes.entry(key.to_lowercase()).or_insert(value);
+        }
+
+        let Some(id) = attributes.get("uidnumber") else {
+            continue;
+        };
+
+        let username = attributes
+            .get("uid")
+            .ok_or_else(|| anyhow!("LDIF entry {id} is missing uid"))?;
+
+        members.push(FileMember {
+            id: id.parse().with_context(|| {
+                format!("LDIF uidNumber {id} is not an integer")
+            })?,
+            username: username.clone(),
+            name: attributes
+                .get("displayname")
+                .or_else(|| attributes.get("cn"))
+                .unwrap_or(username)
+                .clone(),
+            avatar_url: attributes.get("labeleduri").cloned(),
+            email: attributes.get("mail").cloned(),
+            password_hash: attributes.get("userpassword").map(|password| {
+                password.trim_start_matches("{ARGON2}").to_string()
+            }),
+        });
+    }
+
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ldif_reads_members() {
+        let content = "\
+# people
+dn: uid=alice,ou=people,dc=example,dc=com
+objectClass: inetOrgPerson
+uidNumber: 1001
+uid: alice
+cn: Alice
+displayName: Alice Anderson
+labeledURI: https://example.com/alice.png
+mail: alice@example.com
+userPassword: {ARGON2}$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA
+
+dn: uid=bob,ou=people,dc=example,dc=com
+uidNumber: 1002
+uid: bob
+cn: Bob Brown
+";
+
+        let members = parse_ldif(content).unwrap();
+
+        assert_eq!(members.len(), 2);
+
+        let alice = &members[0];
+
+        assert_eq!(alice.id, 1001);
+        assert_eq!(alice.username, "alice");
+        assert_eq!(alice.name, "Alice Anderson");
+        assert_eq!(
+            alice.avatar_url.as_deref(),
+            Some("https://example.com/alice.png")
+        );
+        assert_eq!(alice.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(
+            alice.password_hash.as_deref(),
+            Some("$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA")
+        );
+
+        let bob = &members[1];
+
+        assert_eq!(bob.name, "Bob Brown");
+        assert_eq!(bob.avatar_url, None);
+        assert_eq!(bob.email, None);
+        assert_eq!(bob.password_hash, None);
+    }
+
+    #[test]
+    fn parse_ldif_handles_folded_and_encoded_values() {
+        // "Zoë Zimmer" in base64, the display name is folded over two lines
+        let content = "dn: uid=zoe,ou=people,dc=example,dc=com\r\n\
+uidNumber: 1003\r\n\
+uid: zoe\r\n\
+cn:: Wm/DqyBaaW1tZXI=\r\n\
+mail: zoe@exa\r\n mple.com\r\n";
+
+        let members = parse_ldif(content).unwrap();
+
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].name, "Zoë Zimmer");
+        assert_eq!(members[0].email.as_deref(), Some("zoe@example.com"));
+    }
+
+    #[test]
+    fn parse_ldif_skips_entries_without_uid_number() {
+        let content = "\
+dn: ou=people,dc=example,dc=com
+objectClass: organizationalUnit
+
+dn: uid=carol,ou=people,dc=example,dc=com
+uidNumber: 1004
+uid: carol
+";
+
+        let members = parse_ldif(content).unwrap();
+
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].username, "carol");
+        assert_eq!(members[0].name, "carol");
+    }
+
+    #[test]
+    fn parse_ldif_rejects_invalid_entries() {
+        assert!(parse_ldif("uidNumber: 1005\ncn: No Uid\n").is_err());
+        assert!(parse_ldif("uidNumber: abc\nuid: dave\n").is_err());
+    }
+}
@@ -0,0 +1,11 @@
+pub mod directory;
+pub mod file_directory;
+pub mod repository;
+pub mod service;
+pub mod types;
+
+pub use directory::MemberDirectory;
+pub use file_directory::FileMemberDirectory;
+pub use repository::*;
+pub use service::MembersService;
+pub use types::MemberEvent;
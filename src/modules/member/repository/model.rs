@@ -0,0 +1,129 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::libs::gitlab_api::gitlab_api::Member;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MemberDocument {
+    pub _id: ObjectId,
+    pub user_id: usize,
+    pub username: String,
+    pub name: String,
+    pub avatar_url: String,
+    /// Eligibility as last fetched from gitlab, so members warmed from the
+    /// database are filtered like fetched ones.
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub bot: bool,
+    #[serde(default)]
+    pub access_level: Option<u32>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    pub removed_at: Option<DateTime>,
+    pub updated_at: DateTime,
+    pub created_at: DateTime,
+}
+
+impl From<MemberDocument> for Member {
+    fn from(document: MemberDocument) -> Member {
+        Member {
+            id: document.user_id,
+            username: document.username,
+            name: document.name,
+            avatar_url: document.avatar_url,
+            state: document.state,
+            bot: document.bot,
+            access_level: document.access_level,
+            expires_at: document.expires_at,
+            ..Member::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MembersReconciliation {
+    pub added: Vec<usize>,
+    pub updated: Vec<usize>,
+    pub removed: Vec<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bson::doc;
+    use chrono::NaiveDate;
+
+    fn document() -> MemberDocument {
+        MemberDocument {
+            _id: ObjectId::new(),
+            user_id: 7,
+            username: "alice".to_string(),
+            name: "Alice Anderson".to_string(),
+            avatar_url: "/static/images/user-avatar.png".to_string(),
+            state: Some("blocked".to_string()),
+            bot: false,
+            access_level: Some(30),
+            expires_at: Some("2024-01-31".to_string()),
+            removed_at: None,
+            updated_at: DateTime::now(),
+            created_at: DateTime::now(),
+        }
+    }
+
+    #[test]
+    fn members_keep_their_eligibility_fields() {
+        let member: Member = document().into();
+
+        assert_eq!(member.id, 7);
+        assert_eq!(member.state.as_deref(), Some("blocked"));
+        assert_eq!(member.access_level, Some(30));
+        assert_eq!(member.expires_at.as_deref(), Some("2024-01-31"));
+    }
+
+    #[test]
+    fn warmed_members_are_filtered_like_fetched_ones() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+
+        let blocked: Member = document().into();
+
+        let expired: Member =
+            MemberDocument { state: Some("active".to_string()), ..document() }
+                .into();
+
+        let active: Member = MemberDocument {
+            state: Some("active".to_string()),
+            expires_at: None,
+            ..document()
+        }
+        .into();
+
+        assert!(!blocked.is_eligible(0, today));
+        assert!(!expired.is_eligible(0, today));
+        assert!(active.is_eligible(30, today));
+        assert!(!active.is_eligible(40, today));
+    }
+
+    #[test]
+    fn documents_stored_before_eligibility_fields_are_read() {
+        let now = DateTime::now();
+
+        let document: MemberDocument = bson::from_document(doc! {
+            "_id": ObjectId::new(),
+            "user_id": 7,
+            "username": "alice",
+            "name": "Alice Anderson",
+            "avatar_url": "/static/images/user-avatar.png",
+            "removed_at": null,
+            "updated_at": now,
+            "created_at": now,
+        })
+        .unwrap();
+
+        let member: Member = document.into();
+
+        assert!(member
+            .is_eligible(0, NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()));
+    }
+}
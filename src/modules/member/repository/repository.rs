@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use crate::libs::{gitlab_api::gitlab_api::Member, mongo::MongoDatabase};
+use anyhow::{Context, Result};
+use bson::{doc, oid::ObjectId};
+use futures_util::TryStreamExt;
+use mongodb::{
+    options::{IndexOptions, UpdateOptions},
+    results::UpdateResult,
+    Collection, IndexModel,
+};
+
+use super::MemberDocument;
+
+pub struct MembersRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+}
+
+impl MembersRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = MembersRepository {
+            database,
+            collection_name: String::from("members"),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let indexes = vec![
+            ("user_id", doc! {"user_id":1}, true),
+            ("removed_at", doc! {"removed_at":1}, false),
+        ]
+        .into_iter()
+        .map(|(index_name, doc, unique)| {
+            let options = IndexOptions::builder()
+                .name(index_name.to_string())
+                .unique(unique)
+                .build();
+
+            IndexModel::builder().keys(doc).options(options).build()
+        })
+        .collect();
+
+        self.database
+            .create_indexes::<MemberDocument>(&self.collection_name, indexes)
+            .await
+    }
+
+    pub async fn get_all_members(&self) -> Result<Vec<MemberDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MemberDocument> =
+            database.collection(&self.collection_name);
+
+        let documents = collection
+            .find(doc! {}, None)
+            .await
+            .context("Failed to fetch member documents")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn get_active_members(&self) -> Result<Vec<MemberDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MemberDocument> =
+            database.collection(&self.collection_name);
+
+        let documents = collection
+            .find(doc! { "removed_at": null }, None)
+            .await
+            .context("Failed to fetch active member documents")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn get_members_by_ids(
+        &self,
+        user_ids: &[usize],
+    ) -> Result<Vec<MemberDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MemberDocument> =
+            database.collection(&self.collection_name);
+
+        let user_ids: Vec<u32> =
+            user_ids.iter().map(|user_id| *user_id as u32).collect();
+
+        let documents = collection
+            .find(doc! { "user_id": { "$in": user_ids } }, None)
+            .await
+            .context("Failed to fetch member documents by ids")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn upsert_member(
+        &self,
+        member: &Member,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MemberDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "user_id": member.id as u32,
+        };
+
+        let now = bson::DateTime::now();
+
+        let payload = doc! {
+            "$set": {
+                "username": &member.username,
+                "name": &member.name,
+                "avatar_url": &member.avatar_url,
+                "state": &member.state,
+                "bot": member.bot,
+                "access_level": member.access_level,
+                "expires_at": &member.expires_at,
+                "removed_at": null,
+                "updated_at": now,
+            },
+            "$setOnInsert": {
+                "_id": ObjectId::new(),
+                "created_at": now,
+            }
+        };
+
+        let options = UpdateOptions::builder().upsert(true).build();
+
+        let result = collection
+            .update_one(query, payload, options)
+            .await
+            .with_context(|| {
+                format!("Failed to upsert member {}", member.id)
+            })?;
+
+        Ok(result)
+    }
+
+    pub async fn mark_members_removed(
+        &self,
+        user_ids: &[usize],
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<MemberDocument> =
+            database.collection(&self.collection_name);
+
+        let user_ids: Vec<u32> =
+            user_ids.iter().map(|user_id| *user_id as u32).collect();
+
+        let query = doc! {
+            "user_id": { "$in": user_ids },
+            "removed_at": null,
+        };
+
+        let now = bson::DateTime::now();
+
+        let payload = doc! {
+            "$set": {
+                "removed_at": now,
+                "updated_at": now,
+            }
+        };
+
+        let result = collection.update_many(query, payload, None).await?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use futures::future::join_all;
+use tracing::{error, info};
+
+use crate::libs::gitlab_api::gitlab_api::Member;
+
+use super::{MemberDirectory, MembersRepository};
+
+/// Resolves members for display, falling back to the persisted snapshot of
+/// members who left the directory.
+pub struct MembersService {
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    repository: Arc<MembersRepository>,
+}
+
+impl MembersService {
+    pub fn new(
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        repository: Arc<MembersRepository>,
+    ) -> Self {
+        Self { member_directory, repository }
+    }
+
+    pub fn former_member(user_id: usize) -> Member {
+        Member {
+            id: user_id,
+            username: "former-member".to_string(),
+            name: "Former member".to_string(),
+            is_former_member: true,
+            ..Member::default()
+        }
+    }
+
+    /// Returns every requested member. Those missing from the directory are
+    /// rendered as former members.
+    pub async fn get_members_by_ids(
+        &self,
+        user_ids: &[usize],
+    ) -> HashMap<usize, Member> {
+        let mut members: HashMap<usize, Member> = self
+            .member_directory
+            .get_members_by_ids(user_ids)
+            .await
+            .into_iter()
+            .map(|member| (member.id, member))
+            .collect();
+
+        let missing_ids: Vec<usize> = user_ids
+            .iter()
+            .filter(|user_id| !members.contains_key(user_id))
+            .cloned()
+            .collect();
+
+        if missing_ids.is_empty() {
+            return members;
+        }
+
+        let snapshots =
+            match self.repository.get_members_by_ids(&missing_ids).await {
+                Ok(snapshots) => snapshots,
+                Err(err) => {
+                    error!("Failed to fetch former members: {err}");
+                    vec![]
+                }
+            };
+
+        for snapshot in snapshots {
+            let member = Member { is_former_member: true, ..snapshot.into() };
+
+            members.insert(member.id, member);
+        }
+
+        for user_id in missing_ids {
+            members
+                .entry(user_id)
+                .or_insert_with(|| MembersService::former_member(user_id));
+        }
+
+        members
+    }
+
+    /// Persists a snapshot of every directory member and returns the ids of
+    /// previously known members who are no longer listed.
+    pub async fn snapshot_directory(&self) -> Result<Vec<usize>> {
+        let members = self.member_directory.get_all_members().await;
+
+        let upsert_futures =
+            members.iter().map(|member| self.repository.upsert_member(member));
+
+        for result in join_all(upsert_futures).await {
+            result?;
+        }
+
+        let removed_ids: Vec<usize> = self
+            .repository
+            .get_active_members()
+            .await?
+            .into_iter()
+            .map(|document| document.user_id)
+            .filter(|user_id| {
+                !members.iter().any(|member| &member.id == user_id)
+            })
+            .collect();
+
+        if !removed_ids.is_empty() {
+            info!("{} members left the directory", removed_ids.len());
+
+            self.repository.mark_members_removed(&removed_ids).await?;
+        }
+
+        Ok(removed_ids)
+    }
+}
@@ -0,0 +1,5 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemberEvent {
+    /// The member left the directory.
+    Remove(usize),
+}
@@ -1,5 +1,18 @@
+pub mod admin;
+pub mod api;
 pub mod app;
 pub mod auth;
+pub mod chat;
+pub mod digest;
+pub mod feed;
 pub mod gitlab;
 pub mod guild;
+pub mod meeting;
+pub mod member;
+pub mod notification;
+pub mod presence;
+pub mod session;
 pub mod topic;
+pub mod transfer;
+pub mod webhook;
+pub mod wiki;
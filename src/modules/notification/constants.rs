@@ -0,0 +1,2 @@
+/// How many of the latest notifications the notifications page shows.
+pub const NOTIFICATIONS_LIMIT: i64 = 50;
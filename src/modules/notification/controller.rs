@@ -0,0 +1,200 @@
+use crate::{
+    libs::htmx::Location,
+    modules::app::{
+        app::App, user_extractor::Authenticated, AppError, Event as AppEvent,
+    },
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue},
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
+};
+use futures::Stream;
+use std::{convert::Infallible, sync::Arc, time::Duration};
+use tokio::{select, sync::broadcast::error::RecvError, time::sleep};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tracing::{debug, error};
+
+use super::{
+    DigestPreferenceTemplate, NotificationEvent, NotificationIdParameter,
+    NotificationKindParameter, NotificationPreferenceTemplate,
+    NotificationsBadgeTemplate, NotificationsTemplate,
+};
+
+pub async fn get_notifications_page(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let notifications =
+        app.notifications_service.get_notifications(user.id).await?;
+
+    let preferences =
+        app.notifications_service.get_preferences(user.id).await?;
+
+    let is_digest_enabled =
+        app.notifications_service.is_digest_enabled(user.id).await?;
+
+    Ok(NotificationsTemplate {
+        user,
+        notifications,
+        preferences,
+        can_email: app.notifications_service.can_email(),
+        is_digest_enabled,
+    })
+}
+
+pub async fn read_notification(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<NotificationIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let notification = app
+        .notifications_service
+        .mark_as_read(&parameters.notification_id, user.id)
+        .await?;
+
+    let location = Location {
+        path: notification.link,
+        target: "#content".to_string(),
+        select: "#content".to_string(),
+        swap: "outerHTML".to_string(),
+    };
+
+    let location = HeaderValue::from_str(&serde_json::to_string(&location)?)?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Location", location);
+
+    Ok(headers)
+}
+
+pub async fn read_all_notifications(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    app.notifications_service.mark_all_as_read(user.id).await?;
+
+    get_notifications_page(Authenticated(user), State(app)).await
+}
+
+pub async fn toggle_notification_preference(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<NotificationKindParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let preference = app
+        .notifications_service
+        .toggle_preference(user.id, parameters.kind)
+        .await?;
+
+    Ok(NotificationPreferenceTemplate {
+        preference,
+        can_email: app.notifications_service.can_email(),
+    })
+}
+
+pub async fn toggle_notification_email_preference(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<NotificationKindParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let preference = app
+        .notifications_service
+        .toggle_email_preference(user.id, parameters.kind)
+        .await?;
+
+    Ok(NotificationPreferenceTemplate {
+        preference,
+        can_email: app.notifications_service.can_email(),
+    })
+}
+
+pub async fn toggle_digest_preference(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let is_digest_enabled =
+        app.notifications_service.toggle_digest(user.id).await?;
+
+    Ok(DigestPreferenceTemplate { is_digest_enabled })
+}
+
+fn badge_event(unread_count: u64) -> Event {
+    let badge = NotificationsBadgeTemplate { unread_count };
+
+    Event::default()
+        .data(badge.render().unwrap_or_default())
+        .event("notifications-updated")
+}
+
+pub async fn subscribe_to_notifications(
+    State(app): State<Arc<App>>,
+    Authenticated(user): Authenticated,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let user_id = user.id;
+
+    let mut app_events_receiver = app.event_bus.subscribe();
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Event>(10);
+
+    let stream = ReceiverStream::<Event>::new(rx).map(Ok);
+
+    tokio::spawn(async move {
+        select! {
+            _ = async {
+                loop {
+                    match tx.send(Event::default().data("ping").event("ping")).await {
+                        Ok(()) => {
+                            sleep(Duration::from_secs(5)).await;
+                        }
+                        Err(err) => {
+                            debug!("Dropping stale notifications client {}. Error: {err}", &user_id);
+                            break;
+                        }
+                    }
+                };
+            }=>{}
+            _ = async {
+                let send_current_count = || async {
+                    match app.notifications_service.get_unread_count(user_id).await {
+                        Ok(unread_count) => {
+                            let _ = tx.send(badge_event(unread_count)).await;
+                        }
+                        Err(err) => error!("Failed to count unread notifications: {err}"),
+                    }
+                };
+
+                send_current_count().await;
+
+                loop {
+                    match app_events_receiver.recv().await {
+                        Ok(event) => match event.event {
+                            AppEvent::Notification(NotificationEvent::UnreadCountChange {
+                                user_id: notified_user_id,
+                                unread_count,
+                            }) if notified_user_id == user_id => {
+                                let _ = tx.send(badge_event(unread_count)).await;
+                            }
+                            AppEvent::Resync => send_current_count().await,
+                            _ => {}
+                        },
+                        Err(RecvError::Lagged(count)) => {
+                            app.event_bus.record_subscriber_lag(count);
+
+                            send_current_count().await;
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            }=>{}
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,33 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::notification::types::NotificationKind;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NotificationDocument {
+    pub _id: ObjectId,
+    pub user_id: usize,
+    pub kind: NotificationKind,
+    pub guild_id: ObjectId,
+    pub topic_id: Option<ObjectId>,
+    pub message: String,
+    pub read_at: Option<DateTime>,
+    pub created_at: DateTime,
+}
+
+/// Only the kinds a user turned off are stored, everything else is on.
+/// Emails are the other way around, only the kinds a user opted in to are
+/// sent.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NotificationPreferencesDocument {
+    pub _id: ObjectId,
+    pub user_id: usize,
+    #[serde(default)]
+    pub disabled_kinds: Vec<NotificationKind>,
+    #[serde(default)]
+    pub email_kinds: Vec<NotificationKind>,
+    #[serde(default)]
+    pub is_digest_disabled: bool,
+    pub updated_at: DateTime,
+}
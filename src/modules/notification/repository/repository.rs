@@ -0,0 +1,319 @@
+use std::sync::Arc;
+
+use crate::libs::mongo::MongoDatabase;
+use anyhow::{Context, Result};
+use bson::{doc, oid::ObjectId, Document};
+use futures_util::TryStreamExt;
+use mongodb::{
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    results::{InsertOneResult, UpdateResult},
+    Collection, IndexModel,
+};
+
+use crate::modules::notification::types::NotificationKind;
+
+use super::{NotificationDocument, NotificationPreferencesDocument};
+
+pub struct NotificationsRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+    preferences_collection_name: String,
+}
+
+impl NotificationsRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = NotificationsRepository {
+            database,
+            collection_name: String::from("notifications"),
+            preferences_collection_name: String::from(
+                "notification_preferences",
+            ),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let indexes = vec![
+            ("user_id", doc! {"user_id":1, "created_at":-1}),
+            ("topic_id", doc! {"topic_id":1, "kind":1}),
+        ]
+        .into_iter()
+        .map(|(index_name, doc)| {
+            let options =
+                IndexOptions::builder().name(index_name.to_string()).build();
+
+            IndexModel::builder().keys(doc).options(options).build()
+        })
+        .collect();
+
+        self.database
+            .create_indexes::<NotificationDocument>(
+                &self.collection_name,
+                indexes,
+            )
+            .await?;
+
+        let options = IndexOptions::builder()
+            .name("user_id".to_string())
+            .unique(true)
+            .build();
+
+        let preferences_indexes = vec![IndexModel::builder()
+            .keys(doc! {"user_id":1})
+            .options(options)
+            .build()];
+
+        self.database
+            .create_indexes::<NotificationPreferencesDocument>(
+                &self.preferences_collection_name,
+                preferences_indexes,
+            )
+            .await
+    }
+
+    pub async fn insert_notification(
+        &self,
+        document: NotificationDocument,
+    ) -> Result<InsertOneResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .insert_one(document, None)
+            .await
+            .with_context(|| "Failed to insert notification document")?;
+
+        Ok(result)
+    }
+
+    pub async fn get_notifications(
+        &self,
+        user_id: usize,
+        limit: i64,
+    ) -> Result<Vec<NotificationDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let options = FindOptions::builder()
+            .sort(doc! {"created_at": -1})
+            .limit(limit)
+            .build();
+
+        let documents = collection
+            .find(doc! {"user_id": user_id as u32}, options)
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn get_notification(
+        &self,
+        id: ObjectId,
+        user_id: usize,
+    ) -> Result<Option<NotificationDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let document = collection
+            .find_one(doc! {"_id": id, "user_id": user_id as u32}, None)
+            .await?;
+
+        Ok(document)
+    }
+
+    pub async fn count_unread_notifications(
+        &self,
+        user_id: usize,
+    ) -> Result<u64> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let count = collection
+            .count_documents(
+                doc! {"user_id": user_id as u32, "read_at": null},
+                None,
+            )
+            .await?;
+
+        Ok(count)
+    }
+
+    pub async fn has_topic_notification(
+        &self,
+        user_id: usize,
+        topic_id: ObjectId,
+        kind: NotificationKind,
+    ) -> Result<bool> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let document = collection
+            .find_one(
+                doc! {
+                    "user_id": user_id as u32,
+                    "topic_id": topic_id,
+                    "kind": bson::to_bson(&kind)?,
+                },
+                None,
+            )
+            .await?;
+
+        Ok(document.is_some())
+    }
+
+    pub async fn mark_as_read(
+        &self,
+        id: ObjectId,
+        user_id: usize,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .update_one(
+                doc! {"_id": id, "user_id": user_id as u32, "read_at": null},
+                doc! {"$set": {"read_at": bson::DateTime::now()}},
+                None,
+            )
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn mark_all_as_read(
+        &self,
+        user_id: usize,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .update_many(
+                doc! {"user_id": user_id as u32, "read_at": null},
+                doc! {"$set": {"read_at": bson::DateTime::now()}},
+                None,
+            )
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn get_preferences(
+        &self,
+        user_id: usize,
+    ) -> Result<Option<NotificationPreferencesDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationPreferencesDocument> =
+            database.collection(&self.preferences_collection_name);
+
+        let document = collection
+            .find_one(doc! {"user_id": user_id as u32}, None)
+            .await?;
+
+        Ok(document)
+    }
+
+    pub async fn set_kind_enabled(
+        &self,
+        user_id: usize,
+        kind: NotificationKind,
+        is_enabled: bool,
+    ) -> Result<UpdateResult> {
+        let kind = bson::to_bson(&kind)?;
+
+        let change = if is_enabled {
+            doc! {"$pull": {"disabled_kinds": kind}}
+        } else {
+            doc! {"$addToSet": {"disabled_kinds": kind}}
+        };
+
+        self.update_preferences(user_id, change).await
+    }
+
+    pub async fn set_email_kind_enabled(
+        &self,
+        user_id: usize,
+        kind: NotificationKind,
+        is_enabled: bool,
+    ) -> Result<UpdateResult> {
+        let kind = bson::to_bson(&kind)?;
+
+        let change = if is_enabled {
+            doc! {"$addToSet": {"email_kinds": kind}}
+        } else {
+            doc! {"$pull": {"email_kinds": kind}}
+        };
+
+        self.update_preferences(user_id, change).await
+    }
+
+    pub async fn set_digest_enabled(
+        &self,
+        user_id: usize,
+        is_enabled: bool,
+    ) -> Result<UpdateResult> {
+        self.update_preferences(
+            user_id,
+            doc! {"$set": {"is_digest_disabled": !is_enabled}},
+        )
+        .await
+    }
+
+    /// Applies `change` to the preferences of the user, creating them when
+    /// the user never changed any.
+    async fn update_preferences(
+        &self,
+        user_id: usize,
+        change: Document,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<NotificationPreferencesDocument> =
+            database.collection(&self.preferences_collection_name);
+
+        let mut payload = doc! {
+            "$setOnInsert": {"_id": ObjectId::new()},
+        };
+
+        for (operator, fields) in change {
+            payload.insert(operator, fields);
+        }
+
+        let updated_at = doc! {"updated_at": bson::DateTime::now()};
+
+        match payload.get_document_mut("$set") {
+            Ok(set) => set.extend(updated_at),
+            Err(_) => {
+                payload.insert("$set", updated_at);
+            }
+        }
+
+        let options = UpdateOptions::builder().upsert(true).build();
+
+        let result = collection
+            .update_one(doc! {"user_id": user_id as u32}, payload, options)
+            .await?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,324 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{bail, Result};
+use askama::Template;
+use bson::oid::ObjectId;
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tracing::error;
+
+use crate::{
+    libs::mailer::Mailer,
+    modules::{
+        app::{parse_object_id, AppError},
+        member::MemberDirectory,
+    },
+};
+
+use super::{
+    constants::NOTIFICATIONS_LIMIT, Notification, NotificationDocument,
+    NotificationEmailTemplate, NotificationEmailTextTemplate,
+    NotificationEvent, NotificationKind, NotificationPreference,
+    NotificationPreferencesDocument, NotificationsRepository,
+};
+
+pub struct NotificationsService {
+    pub events_channel:
+        (Sender<NotificationEvent>, Receiver<NotificationEvent>),
+    repository: Arc<NotificationsRepository>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    /// Only available when an SMTP server is configured.
+    mailer: Option<Arc<Mailer>>,
+    votes_threshold: usize,
+}
+
+impl NotificationsService {
+    pub fn new(
+        repository: Arc<NotificationsRepository>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        mailer: Option<Arc<Mailer>>,
+        votes_threshold: usize,
+    ) -> Self {
+        Self {
+            events_channel: channel::<NotificationEvent>(50),
+            repository,
+            member_directory,
+            mailer,
+            votes_threshold,
+        }
+    }
+
+    pub fn can_email(&self) -> bool {
+        self.mailer.is_some()
+    }
+
+    /// Number of votes after which the creator of a topic is notified.
+    pub fn get_votes_threshold(&self) -> usize {
+        self.votes_threshold
+    }
+
+    /// Stores the notification unless the user turned this kind off.
+    pub async fn notify(
+        &self,
+        user_id: usize,
+        kind: NotificationKind,
+        guild_id: &str,
+        topic_id: Option<&str>,
+        message: String,
+    ) -> Result<()> {
+        let preferences = self.repository.get_preferences(user_id).await?;
+
+        if preferences.as_ref().is_some_and(|preferences| {
+            preferences.disabled_kinds.contains(&kind)
+        }) {
+            return Ok(());
+        }
+
+        let topic_id = match topic_id {
+            Some(topic_id) => Some(ObjectId::from_str(topic_id)?),
+            None => None,
+        };
+
+        let document = NotificationDocument {
+            _id: ObjectId::new(),
+            user_id,
+            kind,
+            guild_id: ObjectId::from_str(guild_id)?,
+            topic_id,
+            message,
+            read_at: None,
+            created_at: bson::DateTime::now(),
+        };
+
+        self.repository.insert_notification(document.clone()).await?;
+
+        if preferences
+            .is_some_and(|preferences| preferences.email_kinds.contains(&kind))
+        {
+            self.send_email(user_id, document.into()).await;
+        }
+
+        self.send_unread_count(user_id).await
+    }
+
+    /// Sent in the background so a slow SMTP server doesn't hold up the
+    /// action that caused the notification.
+    async fn send_email(&self, user_id: usize, notification: Notification) {
+        let Some(mailer) = self.mailer.clone() else {
+            return;
+        };
+
+        let member_directory = self.member_directory.clone();
+
+        tokio::spawn(async move {
+            let Some(email) =
+                member_directory.get_member_email(&user_id).await
+            else {
+                return;
+            };
+
+            let link = mailer.get_url(&notification.link);
+            let preferences_link = mailer.get_url("/notifications");
+
+            let html_body = NotificationEmailTemplate {
+                message: &notification.message,
+                link: &link,
+                preferences_link: &preferences_link,
+            }
+            .render();
+
+            let text_body = NotificationEmailTextTemplate {
+                message: &notification.message,
+                link: &link,
+                preferences_link: &preferences_link,
+            }
+            .render();
+
+            let result = match (html_body, text_body) {
+                (Ok(html_body), Ok(text_body)) => {
+                    mailer
+                        .send(
+                            &email,
+                            &notification.message,
+                            html_body,
+                            text_body,
+                        )
+                        .await
+                }
+                (Err(err), _) | (_, Err(err)) => Err(err.into()),
+            };
+
+            if let Err(err) = result {
+                error!(
+                    "Failed to email notification {} to user {}: {err}",
+                    notification.id, user_id
+                );
+            }
+        });
+    }
+
+    pub async fn has_topic_notification(
+        &self,
+        user_id: usize,
+        topic_id: &str,
+        kind: NotificationKind,
+    ) -> Result<bool> {
+        self.repository
+            .has_topic_notification(
+                user_id,
+                ObjectId::from_str(topic_id)?,
+                kind,
+            )
+            .await
+    }
+
+    pub async fn get_notifications(
+        &self,
+        user_id: usize,
+    ) -> Result<Vec<Notification>> {
+        let documents = self
+            .repository
+            .get_notifications(user_id, NOTIFICATIONS_LIMIT)
+            .await?;
+
+        Ok(documents.into_iter().map(Notification::from).collect())
+    }
+
+    pub async fn get_unread_count(&self, user_id: usize) -> Result<u64> {
+        self.repository.count_unread_notifications(user_id).await
+    }
+
+    pub async fn mark_as_read(
+        &self,
+        id: &str,
+        user_id: usize,
+    ) -> Result<Notification> {
+        let id = parse_object_id(id, "notification")?;
+
+        let result = self.repository.mark_as_read(id, user_id).await?;
+
+        let document =
+            match self.repository.get_notification(id, user_id).await? {
+                Some(document) => document,
+                None => bail!(AppError::NotFound(format!(
+                    "Failed to find notification {id}"
+                ))),
+            };
+
+        if result.modified_count > 0 {
+            self.send_unread_count(user_id).await?;
+        }
+
+        Ok(document.into())
+    }
+
+    pub async fn mark_all_as_read(&self, user_id: usize) -> Result<()> {
+        let result = self.repository.mark_all_as_read(user_id).await?;
+
+        if result.modified_count > 0 {
+            self.send_unread_count(user_id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_preferences(
+        &self,
+        user_id: usize,
+    ) -> Result<Vec<NotificationPreference>> {
+        let preferences = self.get_preferences_document(user_id).await?;
+
+        Ok(NotificationKind::all()
+            .into_iter()
+            .map(|kind| get_preference(&preferences, kind))
+            .collect())
+    }
+
+    pub async fn toggle_preference(
+        &self,
+        user_id: usize,
+        kind: NotificationKind,
+    ) -> Result<NotificationPreference> {
+        let preferences = self.get_preferences_document(user_id).await?;
+
+        let is_enabled = !preferences.disabled_kinds.contains(&kind);
+
+        self.repository.set_kind_enabled(user_id, kind, !is_enabled).await?;
+
+        Ok(NotificationPreference {
+            is_enabled: !is_enabled,
+            ..get_preference(&preferences, kind)
+        })
+    }
+
+    pub async fn toggle_email_preference(
+        &self,
+        user_id: usize,
+        kind: NotificationKind,
+    ) -> Result<NotificationPreference> {
+        let preferences = self.get_preferences_document(user_id).await?;
+
+        let is_email_enabled = preferences.email_kinds.contains(&kind);
+
+        self.repository
+            .set_email_kind_enabled(user_id, kind, !is_email_enabled)
+            .await?;
+
+        Ok(NotificationPreference {
+            is_email_enabled: !is_email_enabled,
+            ..get_preference(&preferences, kind)
+        })
+    }
+
+    pub async fn is_digest_enabled(&self, user_id: usize) -> Result<bool> {
+        Ok(!self.get_preferences_document(user_id).await?.is_digest_disabled)
+    }
+
+    /// Returns whether the digest is enabled after the toggle.
+    pub async fn toggle_digest(&self, user_id: usize) -> Result<bool> {
+        let is_enabled = !self.is_digest_enabled(user_id).await?;
+
+        self.repository.set_digest_enabled(user_id, is_enabled).await?;
+
+        Ok(is_enabled)
+    }
+
+    /// Users who never changed their preferences get the defaults.
+    async fn get_preferences_document(
+        &self,
+        user_id: usize,
+    ) -> Result<NotificationPreferencesDocument> {
+        Ok(self.repository.get_preferences(user_id).await?.unwrap_or(
+            NotificationPreferencesDocument {
+                _id: ObjectId::new(),
+                user_id,
+                disabled_kinds: Vec::new(),
+                email_kinds: Vec::new(),
+                is_digest_disabled: false,
+                updated_at: bson::DateTime::now(),
+            },
+        ))
+    }
+
+    async fn send_unread_count(&self, user_id: usize) -> Result<()> {
+        let unread_count = self.get_unread_count(user_id).await?;
+
+        let _ =
+            self.events_channel.0.send(NotificationEvent::UnreadCountChange {
+                user_id,
+                unread_count,
+            });
+
+        Ok(())
+    }
+}
+
+fn get_preference(
+    preferences: &NotificationPreferencesDocument,
+    kind: NotificationKind,
+) -> NotificationPreference {
+    NotificationPreference {
+        kind,
+        is_enabled: !preferences.disabled_kinds.contains(&kind),
+        is_email_enabled: preferences.email_kinds.contains(&kind),
+    }
+}
@@ -0,0 +1,161 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    libs::{gitlab_api::gitlab_api::Member, serialization},
+    modules::app::Event,
+};
+
+use super::NotificationDocument;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    TopicArchived,
+    TopicEdited,
+    TopicVotesReached,
+    AddedToGuild,
+    PresenterVolunteered,
+}
+
+impl NotificationKind {
+    pub fn all() -> Vec<NotificationKind> {
+        vec![
+            NotificationKind::TopicArchived,
+            NotificationKind::TopicEdited,
+            NotificationKind::TopicVotesReached,
+            NotificationKind::AddedToGuild,
+            NotificationKind::PresenterVolunteered,
+        ]
+    }
+
+    pub fn get_id(&self) -> &'static str {
+        match self {
+            NotificationKind::TopicArchived => "topic_archived",
+            NotificationKind::TopicEdited => "topic_edited",
+            NotificationKind::TopicVotesReached => "topic_votes_reached",
+            NotificationKind::AddedToGuild => "added_to_guild",
+            NotificationKind::PresenterVolunteered => "presenter_volunteered",
+        }
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            NotificationKind::TopicArchived => "My topic was archived",
+            NotificationKind::TopicEdited => {
+                "My topic was edited by someone else"
+            }
+            NotificationKind::TopicVotesReached => {
+                "My topic got a lot of votes"
+            }
+            NotificationKind::AddedToGuild => "I was added to a guild",
+            NotificationKind::PresenterVolunteered => {
+                "Someone volunteered to present my topic"
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Notification {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub link: String,
+    pub is_read: bool,
+    #[serde(with = "serialization::chrono_date")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<NotificationDocument> for Notification {
+    fn from(document: NotificationDocument) -> Notification {
+        Notification {
+            id: document._id.to_hex(),
+            kind: document.kind,
+            message: document.message,
+            link: format!("/guilds/{}", document.guild_id.to_hex()),
+            is_read: document.read_at.is_some(),
+            created_at: document.created_at.to_chrono(),
+        }
+    }
+}
+
+pub struct NotificationPreference {
+    pub kind: NotificationKind,
+    pub is_enabled: bool,
+    pub is_email_enabled: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum NotificationEvent {
+    UnreadCountChange { user_id: usize, unread_count: u64 },
+}
+
+impl From<NotificationEvent> for Event {
+    fn from(event: NotificationEvent) -> Event {
+        Event::Notification(event)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NotificationIdParameter {
+    pub notification_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct NotificationKindParameter {
+    pub kind: NotificationKind,
+}
+
+#[derive(Template)]
+#[template(path = "pages/notification/notifications.html")]
+pub struct NotificationsTemplate {
+    pub user: Member,
+    pub notifications: Vec<Notification>,
+    pub preferences: Vec<NotificationPreference>,
+    /// Email preferences are only shown when an SMTP server is configured.
+    pub can_email: bool,
+    pub is_digest_enabled: bool,
+}
+
+impl NotificationsTemplate {
+    pub fn has_unread(&self) -> bool {
+        self.notifications.iter().any(|notification| !notification.is_read)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "components/notification/notification-preference.html")]
+pub struct NotificationPreferenceTemplate {
+    pub preference: NotificationPreference,
+    pub can_email: bool,
+}
+
+#[derive(Template)]
+#[template(path = "components/notification/digest-preference.html")]
+pub struct DigestPreferenceTemplate {
+    pub is_digest_enabled: bool,
+}
+
+#[derive(Template)]
+#[template(path = "components/notification/notifications-badge.html")]
+pub struct NotificationsBadgeTemplate {
+    pub unread_count: u64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/notification.html")]
+pub struct NotificationEmailTemplate<'a> {
+    pub message: &'a str,
+    pub link: &'a str,
+    pub preferences_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/notification.txt")]
+pub struct NotificationEmailTextTemplate<'a> {
+    pub message: &'a str,
+    pub link: &'a str,
+    pub preferences_link: &'a str,
+}
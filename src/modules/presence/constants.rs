@@ -0,0 +1,12 @@
+use chrono::Duration;
+use std::time::Duration as StdDuration;
+
+/// Avatars shown before the rest of the viewers are summed up as "+N".
+pub const PRESENCE_VIEWERS_LIMIT: usize = 12;
+
+/// How often every instance announces its viewers to the others.
+pub const PRESENCE_ANNOUNCE_INTERVAL: StdDuration = StdDuration::from_secs(30);
+
+/// Viewers announced by another instance are forgotten when it stays silent
+/// this long, e.g. because it was stopped.
+pub const PRESENCE_REMOTE_TTL: Duration = Duration::seconds(90);
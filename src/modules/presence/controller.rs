@@ -0,0 +1,25 @@
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use std::sync::Arc;
+
+use crate::modules::{
+    app::{app::App, user_extractor::Authenticated, AppError},
+    guild::GuildIdParameter,
+};
+
+use super::GuildViewersTemplate;
+
+pub async fn get_guild_viewers(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user, &parameters.guild_id)
+        .await?;
+
+    Ok(GuildViewersTemplate::new(app.presence_service.get_viewers(&guild.id)))
+}
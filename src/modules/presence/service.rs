@@ -0,0 +1,441 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use chrono::{DateTime, Utc};
+use tokio::{
+    sync::broadcast::{channel, error::RecvError, Receiver, Sender},
+    time::sleep,
+};
+
+use crate::{
+    libs::gitlab_api::gitlab_api::Member,
+    modules::app::{Event, SequencedEvent},
+};
+
+use super::{
+    constants::{PRESENCE_ANNOUNCE_INTERVAL, PRESENCE_REMOTE_TTL},
+    PresenceEvent,
+};
+
+struct Viewer {
+    member: Member,
+    /// Open guild pages of the member, e.g. several tabs.
+    connections: usize,
+}
+
+/// Viewers of a guild on another instance, as of its latest announcement.
+struct RemoteViewers {
+    revision: u64,
+    announced_at: DateTime<Utc>,
+    viewers: Vec<Member>,
+}
+
+/// Keeps track of who has a guild page open, based on the lifetime of their
+/// event streams. Every instance tracks its own viewers and announces them
+/// through presence events, which the other instances merge into theirs.
+pub struct PresenceService {
+    instance_id: String,
+    revision: AtomicU64,
+    /// Viewers of each guild on this instance in the order they joined.
+    viewers: Mutex<HashMap<String, Vec<Viewer>>>,
+    /// Viewers of each guild on the other instances, by instance id.
+    remote_viewers: Mutex<HashMap<String, HashMap<String, RemoteViewers>>>,
+    pub events_channel: (Sender<PresenceEvent>, Receiver<PresenceEvent>),
+}
+
+impl Default for PresenceService {
+    fn default() -> Self {
+        Self {
+            instance_id: uuid::Uuid::new_v4().simple().to_string(),
+            revision: AtomicU64::new(0),
+            viewers: Mutex::new(HashMap::new()),
+            remote_viewers: Mutex::new(HashMap::new()),
+            events_channel: channel::<PresenceEvent>(50),
+        }
+    }
+}
+
+impl PresenceService {
+    /// Marks the member as viewing the guild until the returned guard is
+    /// dropped.
+    pub fn join(
+        self: &Arc<Self>,
+        guild_id: &str,
+        member: Member,
+    ) -> PresenceGuard {
+        let user_id = member.id;
+
+        let is_new_viewer = {
+            let mut viewers = self.viewers.lock().unwrap();
+            let guild_viewers =
+                viewers.entry(guild_id.to_string()).or_default();
+
+            match guild_viewers
+                .iter_mut()
+                .find(|viewer| viewer.member.id == user_id)
+            {
+                Some(viewer) => {
+                    viewer.connections += 1;
+                    false
+                }
+                None => {
+                    guild_viewers.push(Viewer { member, connections: 1 });
+                    true
+                }
+            }
+        };
+
+        if is_new_viewer {
+            self.notify(guild_id);
+        }
+
+        PresenceGuard {
+            service: self.clone(),
+            guild_id: guild_id.to_string(),
+            user_id,
+        }
+    }
+
+    fn leave(&self, guild_id: &str, user_id: usize) {
+        let has_left = {
+            let mut viewers = self.viewers.lock().unwrap();
+
+            let Some(guild_viewers) = viewers.get_mut(guild_id) else {
+                return;
+            };
+
+            let Some(index) = guild_viewers
+                .iter()
+                .position(|viewer| viewer.member.id == user_id)
+            else {
+                return;
+            };
+
+            guild_viewers[index].connections -= 1;
+
+            let has_left = guild_viewers[index].connections == 0;
+
+            if has_left {
+                guild_viewers.remove(index);
+            }
+
+            if guild_viewers.is_empty() {
+                viewers.remove(guild_id);
+            }
+
+            has_left
+        };
+
+        if has_left {
+            self.notify(guild_id);
+        }
+    }
+
+    /// Viewers of the guild on every instance, the ones of this instance
+    /// first.
+    pub fn get_viewers(&self, guild_id: &str) -> Vec<Member> {
+        self.get_viewers_at(guild_id, Utc::now())
+    }
+
+    fn get_viewers_at(
+        &self,
+        guild_id: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<Member> {
+        let mut viewers: Vec<Member> = self
+            .viewers
+            .lock()
+            .unwrap()
+            .get(guild_id)
+            .map(|guild_viewers| {
+                guild_viewers
+                    .iter()
+                    .map(|viewer| viewer.member.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut user_ids: HashSet<usize> =
+            viewers.iter().map(|member| member.id).collect();
+
+        if let Some(instances) =
+            self.remote_viewers.lock().unwrap().get(guild_id)
+        {
+            let mut instances: Vec<(&String, &RemoteViewers)> = instances
+                .iter()
+                .filter(|(_, remote)| !is_expired(remote, now))
+                .collect();
+
+            // the same order on every instance
+            instances.sort_by_key(|(instance_id, _)| *instance_id);
+
+            for (_, remote) in instances {
+                for member in remote.viewers.iter() {
+                    if user_ids.insert(member.id) {
+                        viewers.push(member.clone());
+                    }
+                }
+            }
+        }
+
+        viewers
+    }
+
+    /// Takes the viewers announced by another instance into account. Events
+    /// can arrive more than once or out of order, e.g. when replayed, so only
+    /// newer revisions replace what is known.
+    pub fn record(&self, event: &PresenceEvent) {
+        self.record_at(event, Utc::now())
+    }
+
+    fn record_at(&self, event: &PresenceEvent, now: DateTime<Utc>) {
+        let PresenceEvent::Change {
+            guild_id,
+            instance_id,
+            revision,
+            announced_at,
+            viewers,
+        } = event;
+
+        if *instance_id == self.instance_id {
+            return;
+        }
+
+        let mut remote_viewers = self.remote_viewers.lock().unwrap();
+
+        let instances = remote_viewers.entry(guild_id.clone()).or_default();
+
+        instances.retain(|_, remote| !is_expired(remote, now));
+
+        if instances
+            .get(instance_id)
+            .is_some_and(|remote| remote.revision >= *revision)
+        {
+            return;
+        }
+
+        let remote = RemoteViewers {
+            revision: *revision,
+            announced_at: *announced_at,
+            viewers: viewers.clone(),
+        };
+
+        if !is_expired(&remote, now) {
+            instances.insert(instance_id.clone(), remote);
+        }
+
+        if instances.is_empty() {
+            remote_viewers.remove(guild_id);
+        }
+    }
+
+    /// Records the viewers announced by the other instances, so they are
+    /// known before anyone on this instance opens the guild.
+    pub async fn listen(&self, mut receiver: Receiver<SequencedEvent>) {
+        loop {
+            match receiver.recv().await {
+                Ok(SequencedEvent {
+                    event: Event::Presence(event), ..
+                }) => self.record(&event),
+                // missed announcements are repeated soon enough
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Announces the viewers of this instance again and again, so the other
+    /// instances don't forget them and new ones learn about them.
+    pub async fn announce_in_loop(&self) {
+        loop {
+            sleep(PRESENCE_ANNOUNCE_INTERVAL).await;
+
+            let guild_ids: Vec<String> =
+                self.viewers.lock().unwrap().keys().cloned().collect();
+
+            for guild_id in guild_ids {
+                self.notify(&guild_id);
+            }
+        }
+    }
+
+    fn notify(&self, guild_id: &str) {
+        // numbered under the lock so a later revision has the later viewers
+        let event = {
+            let viewers = self.viewers.lock().unwrap();
+
+            PresenceEvent::Change {
+                guild_id: guild_id.to_string(),
+                instance_id: self.instance_id.clone(),
+                revision: self.revision.fetch_add(1, Ordering::Relaxed) + 1,
+                announced_at: Utc::now(),
+                viewers: viewers
+                    .get(guild_id)
+                    .map(|guild_viewers| {
+                        guild_viewers
+                            .iter()
+                            .map(|viewer| viewer.member.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }
+        };
+
+        let _ = self.events_channel.0.send(event);
+    }
+}
+
+fn is_expired(remote: &RemoteViewers, now: DateTime<Utc>) -> bool {
+    now - remote.announced_at > PRESENCE_REMOTE_TTL
+}
+
+/// Removes the viewer again when the event stream it belongs to ends.
+pub struct PresenceGuard {
+    service: Arc<PresenceService>,
+    guild_id: String,
+    user_id: usize,
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        self.service.leave(&self.guild_id, self.user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn member(id: usize) -> Member {
+        Member { id, username: format!("user-{id}"), ..Member::default() }
+    }
+
+    fn get_viewer_ids(
+        service: &PresenceService,
+        guild_id: &str,
+    ) -> Vec<usize> {
+        service.get_viewers(guild_id).iter().map(|member| member.id).collect()
+    }
+
+    fn remote_change(
+        instance_id: &str,
+        revision: u64,
+        announced_at: DateTime<Utc>,
+        viewer_ids: &[usize],
+    ) -> PresenceEvent {
+        PresenceEvent::Change {
+            guild_id: "guild".to_string(),
+            instance_id: instance_id.to_string(),
+            revision,
+            announced_at,
+            viewers: viewer_ids.iter().map(|id| member(*id)).collect(),
+        }
+    }
+
+    #[test]
+    fn viewers_stay_until_their_last_connection_ends() {
+        let service = Arc::new(PresenceService::default());
+
+        let first_tab = service.join("guild", member(1));
+        let second_tab = service.join("guild", member(1));
+        let other_viewer = service.join("guild", member(2));
+
+        assert_eq!(get_viewer_ids(&service, "guild"), vec![1, 2]);
+
+        drop(first_tab);
+
+        assert_eq!(get_viewer_ids(&service, "guild"), vec![1, 2]);
+
+        drop(second_tab);
+        drop(other_viewer);
+
+        assert!(get_viewer_ids(&service, "guild").is_empty());
+        assert!(service.viewers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn changes_are_announced_only_when_viewers_come_and_go() {
+        let service = Arc::new(PresenceService::default());
+        let mut receiver = service.events_channel.0.subscribe();
+
+        let first_tab = service.join("guild", member(1));
+        let second_tab = service.join("guild", member(1));
+        drop(first_tab);
+        drop(second_tab);
+
+        let mut announced = vec![];
+
+        while let Ok(PresenceEvent::Change { revision, viewers, .. }) =
+            receiver.try_recv()
+        {
+            announced.push((revision, viewers.len()));
+        }
+
+        assert_eq!(announced, vec![(1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn viewers_of_other_instances_are_merged() {
+        let service = Arc::new(PresenceService::default());
+        let now = Utc::now();
+
+        let _guard = service.join("guild", member(1));
+
+        service.record_at(&remote_change("b", 1, now, &[3, 1]), now);
+        service.record_at(&remote_change("a", 1, now, &[2]), now);
+
+        assert_eq!(get_viewer_ids(&service, "guild"), vec![1, 2, 3]);
+        assert!(get_viewer_ids(&service, "other-guild").is_empty());
+    }
+
+    #[test]
+    fn own_announcements_are_ignored() {
+        let service = PresenceService::default();
+        let now = Utc::now();
+        let instance_id = service.instance_id.clone();
+
+        service.record_at(&remote_change(&instance_id, 1, now, &[1]), now);
+
+        assert!(get_viewer_ids(&service, "guild").is_empty());
+    }
+
+    #[test]
+    fn older_revisions_are_ignored() {
+        let service = PresenceService::default();
+        let now = Utc::now();
+
+        service.record_at(&remote_change("a", 2, now, &[]), now);
+        service.record_at(&remote_change("a", 1, now, &[1]), now);
+
+        assert!(get_viewer_ids(&service, "guild").is_empty());
+
+        service.record_at(&remote_change("a", 3, now, &[2]), now);
+
+        assert_eq!(get_viewer_ids(&service, "guild"), vec![2]);
+    }
+
+    #[test]
+    fn silent_instances_are_forgotten() {
+        let service = PresenceService::default();
+        let announced_at = Utc::now() - Duration::minutes(10);
+
+        service.record_at(
+            &remote_change("a", 1, announced_at, &[1]),
+            announced_at,
+        );
+
+        assert_eq!(service.get_viewers_at("guild", announced_at)[0].id, 1);
+        assert!(get_viewer_ids(&service, "guild").is_empty());
+
+        service.record(&remote_change("b", 1, announced_at, &[2]));
+
+        assert!(service.remote_viewers.lock().unwrap().is_empty());
+    }
+}
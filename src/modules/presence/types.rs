@@ -0,0 +1,45 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{libs::gitlab_api::gitlab_api::Member, modules::app::Event};
+
+use super::constants::PRESENCE_VIEWERS_LIMIT;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum PresenceEvent {
+    /// Someone started or stopped viewing the guild on the instance, or the
+    /// instance announced its viewers again. Carries every viewer of the
+    /// guild on that instance, newer revisions replacing older ones.
+    Change {
+        guild_id: String,
+        instance_id: String,
+        revision: u64,
+        announced_at: DateTime<Utc>,
+        viewers: Vec<Member>,
+    },
+}
+
+impl From<PresenceEvent> for Event {
+    fn from(event: PresenceEvent) -> Event {
+        Event::Presence(event)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "components/presence/guild-viewers.html")]
+pub struct GuildViewersTemplate {
+    pub viewers: Vec<Member>,
+    pub hidden_viewers_count: usize,
+}
+
+impl GuildViewersTemplate {
+    pub fn new(mut viewers: Vec<Member>) -> Self {
+        let hidden_viewers_count =
+            viewers.len().saturating_sub(PRESENCE_VIEWERS_LIMIT);
+
+        viewers.truncate(PRESENCE_VIEWERS_LIMIT);
+
+        Self { viewers, hidden_viewers_count }
+    }
+}
@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use std::sync::Arc;
+use validator::Validate;
+
+use crate::{
+    libs::{axum::Form, gitlab_api::gitlab_api::Member},
+    modules::{
+        app::{
+            app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
+            ToastLevel,
+        },
+        guild::{Guild, GuildIdParameter},
+    },
+};
+
+use super::{GuildSessionsTemplate, SessionFormDTO, SessionIdParameter};
+
+async fn render_guild_sessions(
+    app: &App,
+    user: &Member,
+    guild: &Guild,
+    error_message: String,
+) -> Result<GuildSessionsTemplate, AppError> {
+    let sessions = app
+        .sessions_service
+        .get_upcoming_sessions(std::slice::from_ref(&guild.id), None)
+        .await?;
+
+    Ok(GuildSessionsTemplate {
+        guild_id: guild.id.clone(),
+        sessions,
+        can_schedule: user.id == guild.created_by_user.id,
+        error_message,
+    })
+}
+
+pub async fn get_guild_sessions(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    render_guild_sessions(&app, &user, &guild, String::new()).await
+}
+
+pub async fn schedule_session(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+    Form(form): Form<SessionFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    if user.id != guild.created_by_user.id {
+        return Err(AppError::Forbidden(
+            "Only the guild owner can schedule sessions".to_string(),
+        ));
+    }
+
+    if let Err(errors) = form.validate() {
+        let error_message = errors
+            .field_errors()
+            .into_values()
+            .flatten()
+            .filter_map(|error| error.message.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        return Ok(render_guild_sessions(&app, &user, &guild, error_message)
+            .await?
+            .into_response());
+    }
+
+    let starts_at =
+        match NaiveDateTime::parse_from_str(&form.starts_at, "%Y-%m-%dT%H:%M")
+        {
+            Ok(starts_at) => {
+                starts_at.and_utc()
+                    + Duration::minutes(form.timezone_offset_minutes)
+            }
+            Err(_) => {
+                return Ok(render_guild_sessions(
+                    &app,
+                    &user,
+                    &guild,
+                    "Pick a date and time".to_string(),
+                )
+                .await?
+                .into_response())
+            }
+        };
+
+    if starts_at <= Utc::now() {
+        return Ok(render_guild_sessions(
+            &app,
+            &user,
+            &guild,
+            "Sessions can't be scheduled in the past".to_string(),
+        )
+        .await?
+        .into_response());
+    }
+
+    app.sessions_service
+        .schedule_session(&guild.id, user.id, starts_at, form.duration_minutes)
+        .await?;
+
+    let event = HxTriggerEvent::ShowToast {
+        level: ToastLevel::Info,
+        message: "Session was scheduled".to_string(),
+    };
+
+    let event = event.to_header_value()?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Trigger", event);
+
+    Ok((
+        headers,
+        render_guild_sessions(&app, &user, &guild, String::new()).await?,
+    )
+        .into_response())
+}
+
+pub async fn cancel_session(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<SessionIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    if user.id != guild.created_by_user.id {
+        return Err(AppError::Forbidden(
+            "Only the guild owner can cancel sessions".to_string(),
+        ));
+    }
+
+    app.sessions_service
+        .cancel_session(&guild.id, &parameters.session_id)
+        .await?;
+
+    render_guild_sessions(&app, &user, &guild, String::new()).await
+}
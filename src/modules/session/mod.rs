@@ -0,0 +1,9 @@
+pub mod controller;
+pub mod repository;
+pub mod service;
+pub mod types;
+
+pub use controller::*;
+pub use repository::*;
+pub use service::SessionsService;
+pub use types::*;
@@ -0,0 +1,19 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// A scheduled meeting of a guild.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionDocument {
+    pub _id: ObjectId,
+    pub guild_id: ObjectId,
+    pub starts_at: DateTime,
+    pub duration_minutes: u32,
+    pub created_by_user_id: usize,
+    /// Cancelled sessions are kept so calendar feeds can mark them as
+    /// cancelled.
+    #[serde(default)]
+    pub cancelled_at: Option<DateTime>,
+    pub updated_at: DateTime,
+    pub created_at: DateTime,
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::libs::mongo::MongoDatabase;
+use anyhow::{Context, Result};
+use bson::{doc, oid::ObjectId, DateTime};
+use futures_util::TryStreamExt;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    results::{InsertOneResult, UpdateResult},
+    Collection, IndexModel,
+};
+
+use super::SessionDocument;
+
+pub struct SessionsRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+}
+
+impl SessionsRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = SessionsRepository {
+            database,
+            collection_name: String::from("sessions"),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let options = IndexOptions::builder()
+            .name("guild_id_starts_at".to_string())
+            .build();
+
+        let indexes = vec![IndexModel::builder()
+            .keys(doc! {"guild_id":1, "starts_at":1})
+            .options(options)
+            .build()];
+
+        self.database
+            .create_indexes::<SessionDocument>(&self.collection_name, indexes)
+            .await
+    }
+
+    pub async fn insert_session(
+        &self,
+        document: SessionDocument,
+    ) -> Result<InsertOneResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<SessionDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .insert_one(document, None)
+            .await
+            .with_context(|| "Failed to insert session document")?;
+
+        Ok(result)
+    }
+
+    pub async fn cancel_session(
+        &self,
+        id: ObjectId,
+        guild_id: ObjectId,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<SessionDocument> =
+            database.collection(&self.collection_name);
+
+        let now = DateTime::now();
+
+        let result = collection
+            .update_one(
+                doc! {"_id": id, "guild_id": guild_id, "cancelled_at": null},
+                doc! {"$set": {"cancelled_at": now, "updated_at": now}},
+                None,
+            )
+            .await?;
+
+        Ok(result)
+    }
+
+    /// Sessions of the guilds starting between `from` and `until`, soonest
+    /// first.
+    pub async fn get_sessions(
+        &self,
+        guild_ids: Vec<ObjectId>,
+        from: DateTime,
+        until: Option<DateTime>,
+        include_cancelled: bool,
+    ) -> Result<Vec<SessionDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<SessionDocument> =
+            database.collection(&self.collection_name);
+
+        let mut starts_at = doc! {"$gte": from};
+
+        if let Some(until) = until {
+            starts_at.insert("$lt", until);
+        }
+
+        let mut query = doc! {
+            "guild_id": {"$in": guild_ids},
+            "starts_at": starts_at,
+        };
+
+        if !include_cancelled {
+            // also matches sessions stored before cancelling kept them
+            query.insert("cancelled_at", bson::Bson::Null);
+        }
+
+        let options =
+            FindOptions::builder().sort(doc! {"starts_at": 1}).build();
+
+        let documents =
+            collection.find(query, options).await?.try_collect().await?;
+
+        Ok(documents)
+    }
+}
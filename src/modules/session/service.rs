@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+
+use crate::modules::app::{parse_object_id, AppError};
+
+use super::{Session, SessionDocument, SessionEvent, SessionsRepository};
+
+pub struct SessionsService {
+    pub events_channel: (Sender<SessionEvent>, Receiver<SessionEvent>),
+    repository: Arc<SessionsRepository>,
+}
+
+impl SessionsService {
+    pub fn new(repository: Arc<SessionsRepository>) -> Self {
+        Self { events_channel: channel::<SessionEvent>(50), repository }
+    }
+
+    pub async fn schedule_session(
+        &self,
+        guild_id: &str,
+        user_id: usize,
+        starts_at: DateTime<Utc>,
+        duration_minutes: u32,
+    ) -> Result<Session> {
+        if starts_at <= Utc::now() {
+            bail!(AppError::Validation(
+                "Sessions can't be scheduled in the past".to_string()
+            ));
+        }
+
+        let guild_id = parse_object_id(guild_id, "guild")?;
+
+        let now = bson::DateTime::now();
+
+        let document = SessionDocument {
+            _id: ObjectId::new(),
+            guild_id,
+            starts_at: bson::DateTime::from_chrono(starts_at),
+            duration_minutes,
+            created_by_user_id: user_id,
+            cancelled_at: None,
+            updated_at: now,
+            created_at: now,
+        };
+
+        self.repository.insert_session(document.clone()).await?;
+
+        let session: Session = document.into();
+
+        let _ = self
+            .events_channel
+            .0
+            .send(SessionEvent::Schedule(session.clone()));
+
+        Ok(session)
+    }
+
+    pub async fn cancel_session(
+        &self,
+        guild_id: &str,
+        session_id: &str,
+    ) -> Result<()> {
+        let result = self
+            .repository
+            .cancel_session(
+                parse_object_id(session_id, "session")?,
+                parse_object_id(guild_id, "guild")?,
+            )
+            .await?;
+
+        if result.matched_count == 0 {
+            bail!(AppError::NotFound(format!(
+                "Failed to find session {session_id}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Sessions of the guilds that haven't started yet and start before
+    /// `until`, soonest first.
+    pub async fn get_upcoming_sessions(
+        &self,
+        guild_ids: &[String],
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Session>> {
+        let guild_ids = guild_ids
+            .iter()
+            .map(|guild_id| parse_object_id(guild_id, "guild"))
+            .collect::<Result<Vec<_>>>()?;
+
+        let documents = self
+            .repository
+            .get_sessions(
+                guild_ids,
+                bson::DateTime::now(),
+                until.map(bson::DateTime::from_chrono),
+                false,
+            )
+            .await?;
+
+        Ok(documents.into_iter().map(Session::from).collect())
+    }
+
+    /// Sessions of the guilds starting after `since`, soonest first, cancelled
+    /// ones included.
+    pub async fn get_sessions_since(
+        &self,
+        guild_ids: &[String],
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Session>> {
+        let guild_ids = guild_ids
+            .iter()
+            .map(|guild_id| parse_object_id(guild_id, "guild"))
+            .collect::<Result<Vec<_>>>()?;
+
+        let documents = self
+            .repository
+            .get_sessions(
+                guild_ids,
+                bson::DateTime::from_chrono(since),
+                None,
+                true,
+            )
+            .await?;
+
+        Ok(documents.into_iter().map(Session::from).collect())
+    }
+}
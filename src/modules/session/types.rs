@@ -0,0 +1,87 @@
+use askama_axum::Template;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::libs::serialization;
+
+use super::SessionDocument;
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub guild_id: String,
+    #[serde(with = "serialization::chrono_date")]
+    pub starts_at: DateTime<Utc>,
+    pub duration_minutes: u32,
+    pub created_by_user_id: usize,
+    #[serde(with = "serialization::chrono_date_option")]
+    pub cancelled_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled_at.is_some()
+    }
+
+    pub fn get_ends_at(&self) -> DateTime<Utc> {
+        self.starts_at + Duration::minutes(self.duration_minutes as i64)
+    }
+
+    /// Rendered as UTC, pages convert it to the viewer's time zone.
+    pub fn get_starts_at_label(&self) -> String {
+        self.starts_at.format("%a, %d %b %Y %H:%M UTC").to_string()
+    }
+
+    pub fn get_starts_at_iso(&self) -> String {
+        self.starts_at.to_rfc3339()
+    }
+}
+
+impl From<SessionDocument> for Session {
+    fn from(document: SessionDocument) -> Session {
+        Session {
+            id: document._id.to_hex(),
+            guild_id: document.guild_id.to_hex(),
+            starts_at: document.starts_at.to_chrono(),
+            duration_minutes: document.duration_minutes,
+            created_by_user_id: document.created_by_user_id,
+            cancelled_at: document.cancelled_at.map(|date| date.to_chrono()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct SessionFormDTO {
+    /// Local time of the browser as sent by a `datetime-local` input.
+    pub starts_at: String,
+    #[validate(range(
+        min = 5,
+        max = 480,
+        message = "Duration must be between 5 and 480 minutes"
+    ))]
+    pub duration_minutes: u32,
+    /// `Date.getTimezoneOffset()` of the browser, UTC minus local time.
+    #[serde(default)]
+    pub timezone_offset_minutes: i64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub enum SessionEvent {
+    Schedule(Session),
+}
+
+#[derive(Deserialize)]
+pub struct SessionIdParameter {
+    pub guild_id: String,
+    pub session_id: String,
+}
+
+#[derive(Template)]
+#[template(path = "components/session/guild-sessions.html")]
+pub struct GuildSessionsTemplate {
+    pub guild_id: String,
+    pub sessions: Vec<Session>,
+    pub can_schedule: bool,
+    pub error_message: String,
+}
@@ -1 +1,7 @@
+use chrono::Duration;
+
 pub const TOPICS_LIMIT: usize = 300;
+
+/// A claim to open the gitlab issue of a topic is given up after this long,
+/// e.g. when the instance holding it was stopped mid-request.
+pub const GITLAB_ISSUE_CLAIM_TTL: Duration = Duration::minutes(5);
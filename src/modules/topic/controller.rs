@@ -1,5 +1,8 @@
 use crate::{
-    libs::{htmx::Location, validator::validator_errors_to_hashmap},
+    libs::{
+        gitlab_api::gitlab_api::NewIssue, htmx::Location,
+        validator::validator_errors_to_hashmap,
+    },
     modules::{
         app::{
             app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
@@ -8,7 +11,6 @@ use crate::{
         guild::GuildIdParameter,
     },
 };
-use anyhow::anyhow;
 use askama_axum::IntoResponse;
 use axum::http::{header, HeaderValue};
 use axum::{
@@ -24,7 +26,7 @@ use super::types::{
     CreateTopicTemplate, EditTopicTemplate, PaginationParameters, TopicDraft,
     TopicFormDTO, TopicsListItemTemplate, TopicsListTemplate, VoteTopicResult,
 };
-use super::{constants::TOPICS_LIMIT, types::TopicStatus};
+use super::{constants::TOPICS_LIMIT, types::TopicStatus, TopicGitlabIssue};
 
 #[derive(Deserialize)]
 pub struct PaginationQueryParameters {
@@ -173,7 +175,7 @@ pub async fn create_topic(
         message: "Topic was created".to_string(),
     };
 
-    let event = HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+    let event = event.to_header_value()?;
 
     let location = Location {
         path: format!("/guilds/{}", &guild.id),
@@ -254,7 +256,9 @@ pub async fn get_topic_card(
         .await
     {
         Ok(Some(topic)) => topic,
-        _ => return Err(anyhow!("Failed to find topic").into()),
+        _ => {
+            return Err(AppError::NotFound("Failed to find topic".to_string()))
+        }
     };
 
     Ok(TopicsListItemTemplate { topic })
@@ -276,7 +280,9 @@ pub async fn get_edit_topic_form(
         .await
     {
         Ok(Some(topic)) => topic,
-        _ => return Err(anyhow!("Failed to find topic").into()),
+        _ => {
+            return Err(AppError::NotFound("Failed to find topic".to_string()))
+        }
     };
 
     Ok(EditTopicTemplate {
@@ -336,7 +342,7 @@ pub async fn update_topic(
         message: "Topic was updated".to_string(),
     };
 
-    let event = HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+    let event = event.to_header_value()?;
 
     let location = Location {
         path: format!("/guilds/{}", &parameters.guild_id),
@@ -378,7 +384,7 @@ pub async fn delete_topic(
         message: "Topic was deleted".to_string(),
     };
 
-    let event = HeaderValue::from_str(&serde_json::to_string(&event)?)?;
+    let event = event.to_header_value()?;
 
     let mut headers = HeaderMap::new();
 
@@ -437,3 +443,110 @@ pub async fn unarchive_topic(
 
     Ok(TopicsListItemTemplate { topic })
 }
+
+pub async fn volunteer_to_present(
+    Path(parameters): Path<TopicParameters>,
+    State(app): State<Arc<App>>,
+    Authenticated(user): Authenticated,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), parameters.guild_id.as_str())
+        .await?;
+
+    let topic = app
+        .topics_service
+        .volunteer_to_present(&parameters.topic_id, user.id, &guild)
+        .await?;
+
+    Ok(TopicsListItemTemplate { topic })
+}
+
+pub async fn create_gitlab_issue(
+    Path(parameters): Path<TopicParameters>,
+    State(app): State<Arc<App>>,
+    Authenticated(user): Authenticated,
+) -> Result<impl IntoResponse, AppError> {
+    let gitlab_service = match &app.gitlab_service {
+        Some(gitlab_service) => gitlab_service,
+        None => {
+            return Err(AppError::Unavailable(
+                "Gitlab integration is disabled".to_string(),
+            ))
+        }
+    };
+
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), parameters.guild_id.as_str())
+        .await?;
+
+    let topic = match app
+        .topics_service
+        .get_topic(&parameters.topic_id, user.id, &guild)
+        .await
+    {
+        Ok(Some(topic)) => topic,
+        _ => {
+            return Err(AppError::NotFound("Failed to find topic".to_string()))
+        }
+    };
+
+    if !topic.can_create_gitlab_issue {
+        return Err(AppError::Forbidden(
+            "Not allowed to create an issue for topic".to_string(),
+        ));
+    }
+
+    // claimed first, two requests racing each other would open two issues
+    app.topics_service.claim_gitlab_issue(&parameters.topic_id).await?;
+
+    let issue = match gitlab_service
+        .create_issue(NewIssue {
+            title: topic.text.trim().to_string(),
+            description: format!(
+                "Topic of the **{}** guild proposed by @{}.",
+                guild.name, topic.created_by_user.username
+            ),
+        })
+        .await
+    {
+        Ok(issue) => issue,
+        Err(err) => {
+            let _ = app
+                .topics_service
+                .release_gitlab_issue_claim(&parameters.topic_id)
+                .await;
+
+            return Err(err.into());
+        }
+    };
+
+    let topic = app
+        .topics_service
+        .link_gitlab_issue(
+            &parameters.topic_id,
+            TopicGitlabIssue {
+                project_id: issue.project_id,
+                iid: issue.iid,
+                url: issue.web_url,
+                state: issue.state,
+            },
+            user.id,
+            &guild,
+        )
+        .await?;
+
+    let event = HxTriggerEvent::ShowToast {
+        level: ToastLevel::Info,
+        message: format!("Issue #{} was created", issue.iid),
+    };
+
+    let event = event.to_header_value()?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Trigger", event);
+
+    Ok((headers, TopicsListItemTemplate { topic }))
+}
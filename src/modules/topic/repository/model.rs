@@ -11,12 +11,37 @@ pub struct TopicDocument {
     pub text: String,
     pub status: TopicStatus,
     pub will_be_presented_by_the_creator: bool,
+    /// Member who volunteered to present a topic its creator won't present.
+    #[serde(default)]
+    pub presenter_user_id: Option<usize>,
     pub created_by_user_id: usize,
     pub upvoted_by_users_ids: Vec<usize>,
+    #[serde(default)]
+    pub gitlab_issue: Option<TopicGitlabIssue>,
+    /// Set while an issue is being opened for the topic, so it is only
+    /// opened once.
+    #[serde(default)]
+    pub gitlab_issue_claimed_at: Option<DateTime>,
     pub updated_at: DateTime,
     pub created_at: DateTime,
 }
 
+/// Gitlab issue opened from a topic.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TopicGitlabIssue {
+    pub project_id: u64,
+    pub iid: u64,
+    pub url: String,
+    /// "opened" or "closed"
+    pub state: String,
+}
+
+impl TopicGitlabIssue {
+    pub fn is_closed(&self) -> bool {
+        self.state == "closed"
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct TopicsCountAggregationResult {
     pub guild_id: ObjectId,
@@ -8,14 +8,14 @@ use anyhow::{Context, Result};
 use bson::{doc, oid::ObjectId, Document};
 use futures::TryStreamExt;
 use mongodb::{
-    options::{AggregateOptions, IndexOptions},
-    results::{DeleteResult, InsertOneResult, UpdateResult},
+    options::{AggregateOptions, FindOptions, IndexOptions},
+    results::{DeleteResult, InsertManyResult, InsertOneResult, UpdateResult},
     Collection, IndexModel,
 };
 use serde_json::Value;
 
 use super::{
-    PartialTopicDocument, TopicDocument, TopicDocumentId,
+    PartialTopicDocument, TopicDocument, TopicDocumentId, TopicGitlabIssue,
     TopicsCountAggregationResult,
 };
 
@@ -41,6 +41,10 @@ impl TopicsRepository {
             ("guild_id", doc! {"guild_id":1}),
             ("created_by_user_id", doc! {"created_by_user_id":1}),
             ("status", doc! {"status":1}),
+            (
+                "gitlab_issue",
+                doc! {"gitlab_issue.project_id":1, "gitlab_issue.iid":1},
+            ),
         ]
         .into_iter()
         .map(|(index_name, doc)| {
@@ -73,6 +77,46 @@ impl TopicsRepository {
         Ok(result)
     }
 
+    pub async fn insert_topic_documents(
+        &self,
+        documents: Vec<TopicDocument>,
+    ) -> Result<InsertManyResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .insert_many(documents, None)
+            .await
+            .with_context(|| "Failed to insert topic documents")?;
+
+        Ok(result)
+    }
+
+    /// Every topic of the guild regardless of its status, oldest first.
+    pub async fn get_all_guild_topics(
+        &self,
+        guild_id: ObjectId,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let options =
+            FindOptions::builder().sort(doc! {"created_at": 1}).build();
+
+        let documents = collection
+            .find(doc! {"guild_id": guild_id}, options)
+            .await
+            .context("Failed to fetch all topics of guild")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
     pub async fn get_guild_topics(
         &self,
         PaginationParameters { skip, limit }: PaginationParameters,
@@ -288,6 +332,405 @@ impl TopicsRepository {
         Ok(result)
     }
 
+    pub async fn get_open_topics_by_user(
+        &self,
+        user_id: usize,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "status": TopicStatus::Created.to_string(),
+            "$or": [
+                { "created_by_user_id": user_id as u32 },
+                { "upvoted_by_users_ids": user_id as u32 },
+                { "presenter_user_id": user_id as u32 },
+            ]
+        };
+
+        let documents = collection
+            .find(query, None)
+            .await
+            .context("Failed to fetch topics by user")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    /// Open topics of the guilds created after `since`, newest first.
+    pub async fn get_topics_created_since(
+        &self,
+        guild_ids: Vec<ObjectId>,
+        since: bson::DateTime,
+        limit: i64,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "guild_id": {"$in": guild_ids},
+            "status": TopicStatus::Created.to_string(),
+            "created_at": {"$gte": since},
+        };
+
+        let options = FindOptions::builder()
+            .sort(doc! {"created_at": -1})
+            .limit(limit)
+            .build();
+
+        let documents = collection
+            .find(query, options)
+            .await
+            .context("Failed to fetch topics created since")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    /// Topics of the guild whatever their status, most recently created
+    /// first.
+    pub async fn get_latest_created_topics(
+        &self,
+        guild_id: ObjectId,
+        limit: i64,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let options = FindOptions::builder()
+            .sort(doc! {"created_at": -1})
+            .limit(limit)
+            .build();
+
+        let documents = collection
+            .find(doc! {"guild_id": guild_id}, options)
+            .await
+            .context("Failed to fetch latest created topics")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    /// Archived topics of the guild, most recently archived first as
+    /// archiving is their last update.
+    pub async fn get_latest_archived_topics(
+        &self,
+        guild_id: ObjectId,
+        limit: i64,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "guild_id": guild_id,
+            "status": TopicStatus::Archived.to_string(),
+        };
+
+        let options = FindOptions::builder()
+            .sort(doc! {"updated_at": -1})
+            .limit(limit)
+            .build();
+
+        let documents = collection
+            .find(query, options)
+            .await
+            .context("Failed to fetch latest archived topics")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    /// Topics of the guild newest first, starting after `before_id`.
+    pub async fn get_topics_page(
+        &self,
+        guild_id: ObjectId,
+        status: Option<TopicStatus>,
+        before_id: Option<ObjectId>,
+        limit: i64,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let mut query = doc! {"guild_id": guild_id};
+
+        if let Some(status) = status {
+            query.insert("status", status.to_string());
+        }
+
+        if let Some(before_id) = before_id {
+            query.insert("_id", doc! {"$lt": before_id});
+        }
+
+        let options =
+            FindOptions::builder().sort(doc! {"_id": -1}).limit(limit).build();
+
+        let documents = collection
+            .find(query, options)
+            .await
+            .context("Failed to fetch page of topics")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn remove_user_votes(
+        &self,
+        user_id: usize,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "status": TopicStatus::Created.to_string(),
+            "upvoted_by_users_ids": user_id as u32
+        };
+
+        let payload = doc! {
+            "$pull": doc!{
+                "upvoted_by_users_ids": user_id as u32
+            }
+        };
+
+        let result = collection.update_many(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    /// Frees the open topics the user volunteered to present.
+    pub async fn remove_presenter(
+        &self,
+        user_id: usize,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "status": TopicStatus::Created.to_string(),
+            "presenter_user_id": user_id as u32
+        };
+
+        let payload = doc! {
+            "$set": {
+                "presenter_user_id": null,
+            }
+        };
+
+        let result = collection.update_many(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    pub async fn reassign_topic(
+        &self,
+        id: ObjectId,
+        user_id: usize,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+        };
+
+        let payload = doc! {
+            "$set": {
+                "created_by_user_id": user_id as u32,
+                "will_be_presented_by_the_creator": false,
+                "updated_at": bson::DateTime::now(),
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    /// Claims the topic for opening its gitlab issue, unless it has one or
+    /// someone claimed it after `claimed_before`.
+    pub async fn claim_gitlab_issue(
+        &self,
+        id: ObjectId,
+        claimed_before: bson::DateTime,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+            "gitlab_issue": null,
+            "$or": [
+                {"gitlab_issue_claimed_at": null},
+                {"gitlab_issue_claimed_at": {"$lt": claimed_before}},
+            ],
+        };
+
+        let payload = doc! {
+            "$set": {
+                "gitlab_issue_claimed_at": bson::DateTime::now(),
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    pub async fn release_gitlab_issue_claim(
+        &self,
+        id: ObjectId,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+            "gitlab_issue": null,
+        };
+
+        let payload = doc! {
+            "$set": {
+                "gitlab_issue_claimed_at": null,
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    pub async fn set_gitlab_issue(
+        &self,
+        id: ObjectId,
+        gitlab_issue: &TopicGitlabIssue,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+            "gitlab_issue": null,
+        };
+
+        let payload = doc! {
+            "$set": {
+                "gitlab_issue": bson::to_bson(gitlab_issue)?,
+                "gitlab_issue_claimed_at": null,
+                "updated_at": bson::DateTime::now(),
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    pub async fn set_presenter(
+        &self,
+        id: ObjectId,
+        user_id: usize,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+            "status": TopicStatus::Created.to_string(),
+            "will_be_presented_by_the_creator": false,
+            "presenter_user_id": null,
+        };
+
+        let payload = doc! {
+            "$set": {
+                "presenter_user_id": user_id as u32,
+                "updated_at": bson::DateTime::now(),
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
+
+    pub async fn get_topics_by_gitlab_issue(
+        &self,
+        project_id: u64,
+        iid: u64,
+    ) -> Result<Vec<TopicDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "gitlab_issue.project_id": project_id as i64,
+            "gitlab_issue.iid": iid as i64,
+        };
+
+        let documents = collection
+            .find(query, None)
+            .await
+            .context("Failed to fetch topics by gitlab issue")?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn update_gitlab_issue_state(
+        &self,
+        id: ObjectId,
+        state: &str,
+    ) -> Result<UpdateResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<TopicDocument> =
+            database.collection(&self.collection_name);
+
+        let query = doc! {
+            "_id": id,
+        };
+
+        let payload = doc! {
+            "$set": {
+                "gitlab_issue.state": state,
+                "updated_at": bson::DateTime::now(),
+            }
+        };
+
+        let result = collection.update_one(query, payload, None).await?;
+
+        Ok(result)
+    }
+
     pub async fn get_upvoted_topic_by_guild_id(
         &self,
         guild_id: &ObjectId,
@@ -2,38 +2,52 @@ use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use anyhow::{bail, Result};
 use bson::{oid::ObjectId, DateTime};
+use chrono::Utc;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
-use tracing::error;
+use tracing::{error, info};
 
 use crate::{
+    configuration::DepartedMemberPolicy,
     libs::gitlab_api::gitlab_api::Member,
-    modules::{gitlab::GitlabService, guild::Guild},
+    modules::{
+        app::{parse_object_id, AppError},
+        guild::Guild,
+        member::MembersService,
+        notification::{NotificationKind, NotificationsService},
+    },
 };
 
 use super::{
+    constants::GITLAB_ISSUE_CLAIM_TTL,
     repository::TopicsRepository,
     types::{
         PaginationParameters, Topic, TopicEvent, TopicFormDTO,
         TopicPersonalized, TopicStatus, VoteTopicResult,
     },
-    PartialTopicDocument, TopicDocument,
+    PartialTopicDocument, TopicDocument, TopicGitlabIssue,
 };
 
 pub struct TopicsService {
     pub events_channel: (Sender<TopicEvent>, Receiver<TopicEvent>),
-    gitlab_service: Arc<GitlabService>,
+    members_service: Arc<MembersService>,
+    notifications_service: Arc<NotificationsService>,
     repository: Arc<TopicsRepository>,
+    gitlab_issues_enabled: bool,
 }
 
 impl TopicsService {
     pub fn new(
-        gitlab_service: Arc<GitlabService>,
+        members_service: Arc<MembersService>,
+        notifications_service: Arc<NotificationsService>,
         repository: Arc<TopicsRepository>,
+        gitlab_issues_enabled: bool,
     ) -> Self {
         Self {
             events_channel: channel::<TopicEvent>(50),
-            gitlab_service,
+            members_service,
+            notifications_service,
             repository,
+            gitlab_issues_enabled,
         }
     }
 
@@ -56,6 +70,112 @@ impl TopicsService {
         Ok(result_map)
     }
 
+    pub async fn get_topics_created_since(
+        &self,
+        guild_ids: &[String],
+        since: chrono::DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Topic>> {
+        let guild_ids = guild_ids
+            .iter()
+            .map(|guild_id| ObjectId::from_str(guild_id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let documents = self
+            .repository
+            .get_topics_created_since(
+                guild_ids,
+                DateTime::from_chrono(since),
+                limit,
+            )
+            .await?;
+
+        Ok(documents.into_iter().map(Topic::from).collect())
+    }
+
+    pub async fn get_latest_created_topics(
+        &self,
+        guild_id: &str,
+        limit: i64,
+    ) -> Result<Vec<Topic>> {
+        let documents = self
+            .repository
+            .get_latest_created_topics(
+                parse_object_id(guild_id, "guild")?,
+                limit,
+            )
+            .await?;
+
+        Ok(documents.into_iter().map(Topic::from).collect())
+    }
+
+    pub async fn get_latest_archived_topics(
+        &self,
+        guild_id: &str,
+        limit: i64,
+    ) -> Result<Vec<Topic>> {
+        let documents = self
+            .repository
+            .get_latest_archived_topics(
+                parse_object_id(guild_id, "guild")?,
+                limit,
+            )
+            .await?;
+
+        Ok(documents.into_iter().map(Topic::from).collect())
+    }
+
+    /// Fetches a topic without checking who can see it, callers have to
+    /// verify the membership in its guild.
+    pub async fn get_topic_by_id(&self, id: &str) -> Result<Option<Topic>> {
+        let document =
+            self.repository.get_topic(parse_object_id(id, "topic")?).await?;
+
+        Ok(document.map(Topic::from))
+    }
+
+    /// Ids of the open topics of the guild, most voted first.
+    pub async fn get_topic_ids_sorted(
+        &self,
+        guild_id: &str,
+    ) -> Result<Vec<String>> {
+        self.repository
+            .get_topic_ids_sorted(&parse_object_id(guild_id, "guild")?)
+            .await
+    }
+
+    /// Topics of the guild newest first, `before_id` being the last topic of
+    /// the previous page.
+    pub async fn get_topics_page(
+        &self,
+        user_id: usize,
+        guild: &Guild,
+        status: Option<TopicStatus>,
+        before_id: Option<ObjectId>,
+        limit: usize,
+    ) -> Result<Vec<TopicPersonalized>> {
+        let documents = self
+            .repository
+            .get_topics_page(
+                ObjectId::from_str(&guild.id)?,
+                status,
+                before_id,
+                limit as i64,
+            )
+            .await?;
+
+        let mut topics = Vec::with_capacity(documents.len());
+
+        for document in documents {
+            topics.push(
+                self.map_topic_with_user(guild, document.into(), user_id)
+                    .await?,
+            );
+        }
+
+        Ok(topics)
+    }
+
     pub async fn get_topics_by_guild_id(
         &self,
         user_id: usize,
@@ -64,7 +184,7 @@ impl TopicsService {
         guild: Guild,
         status: TopicStatus,
     ) -> Result<Vec<TopicPersonalized>> {
-        let guild_id = ObjectId::from_str(guild_id)?;
+        let guild_id = parse_object_id(guild_id, "guild")?;
 
         let documents = self
             .repository
@@ -90,6 +210,12 @@ impl TopicsService {
                 all_members_ids.push(document.created_by_user_id)
             }
 
+            if let Some(presenter_user_id) = document.presenter_user_id {
+                if !all_members_ids.contains(&presenter_user_id) {
+                    all_members_ids.push(presenter_user_id)
+                }
+            }
+
             for member_id in document.upvoted_by_users_ids.iter() {
                 if !all_members_ids.contains(&member_id) {
                     all_members_ids.push(member_id.to_owned())
@@ -97,54 +223,44 @@ impl TopicsService {
             }
         }
 
-        let all_members = self
-            .gitlab_service
-            .get_cached_members_by_ids(&all_members_ids)
-            .await;
+        let all_members =
+            self.members_service.get_members_by_ids(&all_members_ids).await;
 
         let is_current_user_created_guild =
             guild.created_by_user.id == user_id;
 
         for document in documents {
-            let document_id = document._id.to_hex();
-
-            let created_by_user = all_members
-                .iter()
-                .find(|member| member.id == document.created_by_user_id);
-
-            if created_by_user.is_none() {
-                bail!(format!(
-                    "Failed to fetch user {} who created topic {}",
-                    &document.created_by_user_id, &document_id
-                ))
-            };
-
             let is_upvoted_by_current_user =
                 document.upvoted_by_users_ids.contains(&user_id);
             let is_created_by_current_user =
                 document.created_by_user_id == user_id;
 
-            let created_by_user = created_by_user.unwrap().clone();
+            let created_by_user =
+                get_member(&all_members, document.created_by_user_id);
 
-            let upvoted_by_users: Result<Vec<Member>> = document
+            let upvoted_by_users: Vec<Member> = document
                 .upvoted_by_users_ids
                 .iter()
-                .map(|user_id| {
-                    let user = all_members
-                        .iter()
-                        .find(|member| &member.id == user_id);
-
-                    if user.is_none() {
-                        bail!(format!(
-                            "Failed to fetch user {} who upvoted topic {}",
-                            &user_id, &document_id
-                        ))
-                    };
-
-                    return Ok(user.unwrap().clone());
-                })
+                .map(|user_id| get_member(&all_members, *user_id))
                 .collect();
 
+            let can_create_gitlab_issue = self.can_create_gitlab_issue(
+                is_current_user_created_guild || is_created_by_current_user,
+                &document.status,
+                &document.gitlab_issue,
+            );
+
+            let can_volunteer = can_volunteer(
+                is_created_by_current_user,
+                &document.status,
+                document.will_be_presented_by_the_creator,
+                document.presenter_user_id,
+            );
+
+            let presented_by_user = document
+                .presenter_user_id
+                .map(|user_id| get_member(&all_members, user_id));
+
             let topic = TopicPersonalized {
                 id: document._id.to_hex(),
                 guild_id: document.guild_id.to_hex(),
@@ -154,13 +270,17 @@ impl TopicsService {
                 will_be_presented_by_the_creator: document
                     .will_be_presented_by_the_creator,
                 created_by_user,
+                presented_by_user,
                 can_change_status: is_current_user_created_guild,
                 can_delete: is_current_user_created_guild
                     || is_created_by_current_user,
                 can_edit: is_current_user_created_guild
                     || is_created_by_current_user,
+                can_create_gitlab_issue,
+                can_volunteer,
                 is_upvoted_by_current_user,
-                upvoted_by_users: upvoted_by_users?,
+                upvoted_by_users,
+                gitlab_issue: document.gitlab_issue,
                 updated_at: document.updated_at.to_chrono(),
                 created_at: document.created_at.to_chrono(),
             };
@@ -186,8 +306,11 @@ impl TopicsService {
             will_be_presented_by_the_creator: form
                 .will_be_presented_by_the_creator
                 .is_some_and(|val| val == true),
+            presenter_user_id: None,
             created_by_user_id: user_id,
             upvoted_by_users_ids: vec![],
+            gitlab_issue: None,
+            gitlab_issue_claimed_at: None,
             updated_at: bson::DateTime::now(),
             created_at: bson::DateTime::now(),
         };
@@ -225,7 +348,10 @@ impl TopicsService {
         let topic_ids =
             self.repository.get_topic_ids_sorted(&guild_id).await?;
 
-        let _ = self.events_channel.0.send(TopicEvent::OrderChange(topic_ids));
+        let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+            guild_id: guild_id.to_hex(),
+            topic_ids,
+        });
 
         Ok(created_topic)
     }
@@ -236,11 +362,15 @@ impl TopicsService {
         user_id: usize,
         guild: &Guild,
     ) -> Result<Option<TopicPersonalized>> {
-        let document =
-            match self.repository.get_topic(ObjectId::from_str(id)?).await? {
-                Some(document) => document,
-                None => return Ok(None),
-            };
+        let id = match ObjectId::from_str(id) {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+
+        let document = match self.repository.get_topic(id).await? {
+            Some(document) => document,
+            None => return Ok(None),
+        };
 
         let mapped_topic =
             self.map_topic_with_user(guild, document.into(), user_id).await?;
@@ -248,6 +378,48 @@ impl TopicsService {
         Ok(Some(mapped_topic))
     }
 
+    fn can_create_gitlab_issue(
+        &self,
+        can_edit: bool,
+        status: &TopicStatus,
+        gitlab_issue: &Option<TopicGitlabIssue>,
+    ) -> bool {
+        self.gitlab_issues_enabled
+            && can_create_gitlab_issue(can_edit, status, gitlab_issue)
+    }
+
+    async fn get_username(&self, user_id: usize) -> String {
+        let members =
+            self.members_service.get_members_by_ids(&[user_id]).await;
+
+        get_member(&members, user_id).username
+    }
+
+    /// Notifications never fail the action that caused them.
+    async fn notify_topic_creator(
+        &self,
+        topic: &Topic,
+        kind: NotificationKind,
+        message: String,
+    ) {
+        if let Err(err) = self
+            .notifications_service
+            .notify(
+                topic.created_by_user_id,
+                kind,
+                &topic.guild_id,
+                Some(&topic.id),
+                message,
+            )
+            .await
+        {
+            error!(
+                "Failed to notify user {} about topic {}: {err}",
+                topic.created_by_user_id, topic.id
+            );
+        }
+    }
+
     pub async fn map_topic_with_user(
         &self,
         guild: &Guild,
@@ -260,26 +432,19 @@ impl TopicsService {
             all_members_ids.push(topic.created_by_user_id)
         }
 
+        if let Some(presenter_user_id) = topic.presenter_user_id {
+            if !all_members_ids.contains(&presenter_user_id) {
+                all_members_ids.push(presenter_user_id)
+            }
+        }
+
         for member_id in topic.upvoted_by_users_ids.iter() {
             if !all_members_ids.contains(&member_id) {
                 all_members_ids.push(member_id.to_owned())
             }
         }
-        let all_members = self
-            .gitlab_service
-            .get_cached_members_by_ids(&all_members_ids)
-            .await;
-
-        let created_by_user = all_members
-            .iter()
-            .find(|member| member.id == topic.created_by_user_id);
-
-        if created_by_user.is_none() {
-            bail!(format!(
-                "Failed to fetch user {} who created topic {}",
-                &topic.created_by_user_id, &topic.id
-            ))
-        };
+        let all_members =
+            self.members_service.get_members_by_ids(&all_members_ids).await;
 
         let is_current_user_created_guild =
             guild.created_by_user.id == user_id;
@@ -288,26 +453,32 @@ impl TopicsService {
             topic.upvoted_by_users_ids.contains(&user_id);
         let is_created_by_current_user = topic.created_by_user_id == user_id;
 
-        let created_by_user = created_by_user.unwrap().clone();
+        let created_by_user =
+            get_member(&all_members, topic.created_by_user_id);
 
-        let upvoted_by_users: Result<Vec<Member>> = topic
+        let upvoted_by_users: Vec<Member> = topic
             .upvoted_by_users_ids
             .iter()
-            .map(|user_id| {
-                let user =
-                    all_members.iter().find(|member| &member.id == user_id);
-
-                if user.is_none() {
-                    bail!(format!(
-                        "Failed to fetch user {} who upvoted topic {}",
-                        &user_id, &topic.id
-                    ))
-                };
-
-                return Ok(user.unwrap().clone());
-            })
+            .map(|user_id| get_member(&all_members, *user_id))
             .collect();
 
+        let can_create_gitlab_issue = self.can_create_gitlab_issue(
+            is_current_user_created_guild || is_created_by_current_user,
+            &topic.status,
+            &topic.gitlab_issue,
+        );
+
+        let can_volunteer = can_volunteer(
+            is_created_by_current_user,
+            &topic.status,
+            topic.will_be_presented_by_the_creator,
+            topic.presenter_user_id,
+        );
+
+        let presented_by_user = topic
+            .presenter_user_id
+            .map(|user_id| get_member(&all_members, user_id));
+
         let topic = TopicPersonalized {
             id: topic.id,
             guild_id: topic.guild_id,
@@ -317,13 +488,17 @@ impl TopicsService {
             will_be_presented_by_the_creator: topic
                 .will_be_presented_by_the_creator,
             created_by_user,
+            presented_by_user,
             can_change_status: is_current_user_created_guild,
             can_delete: is_current_user_created_guild
                 || is_created_by_current_user,
             can_edit: is_current_user_created_guild
                 || is_created_by_current_user,
+            can_create_gitlab_issue,
+            can_volunteer,
             is_upvoted_by_current_user,
-            upvoted_by_users: upvoted_by_users?,
+            upvoted_by_users,
+            gitlab_issue: topic.gitlab_issue,
             updated_at: topic.updated_at,
             created_at: topic.created_at,
         };
@@ -338,6 +513,7 @@ impl TopicsService {
         user_id: usize,
     ) -> Result<VoteTopicResult> {
         let guild_id = ObjectId::from_str(&guild.id)?;
+        let topic_id = parse_object_id(&id, "topic")?;
 
         let previously_voted = match self
             .repository
@@ -363,10 +539,7 @@ impl TopicsService {
             .remove_user_vote_by_guild_id(&guild_id, user_id)
             .await?;
 
-        let _ = self
-            .repository
-            .upvote_topic(ObjectId::from_str(&id)?, user_id)
-            .await?;
+        let _ = self.repository.upvote_topic(topic_id, user_id).await?;
 
         let topic = self.get_topic(&id, user_id, guild).await?;
 
@@ -380,17 +553,61 @@ impl TopicsService {
                     .0
                     .send(TopicEvent::Update(topic.clone().into()));
 
-                let _ = self
-                    .events_channel
-                    .0
-                    .send(TopicEvent::OrderChange(topic_ids));
+                let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+                    guild_id: guild_id.to_hex(),
+                    topic_ids,
+                });
+
+                self.notify_about_votes(&topic, user_id).await;
 
                 Ok(VoteTopicResult { previously_voted, topic })
             }
-            None => bail!("Failed to fetch topic"),
+            None => {
+                bail!(AppError::NotFound(format!("Failed to find topic {id}")))
+            }
         }
     }
 
+    async fn notify_about_votes(
+        &self,
+        topic: &TopicPersonalized,
+        voter_user_id: usize,
+    ) {
+        let threshold = self.notifications_service.get_votes_threshold();
+
+        if !reaches_votes_threshold(
+            topic.upvoted_by_users.len(),
+            threshold,
+            topic.created_by_user.id,
+            voter_user_id,
+        ) {
+            return;
+        }
+
+        let was_notified = self
+            .notifications_service
+            .has_topic_notification(
+                topic.created_by_user.id,
+                &topic.id,
+                NotificationKind::TopicVotesReached,
+            )
+            .await;
+
+        if !is_first_votes_notification(was_notified, &topic.id) {
+            return;
+        }
+
+        self.notify_topic_creator(
+            &topic.clone().into(),
+            NotificationKind::TopicVotesReached,
+            format!(
+                "Your topic {} got {threshold} votes",
+                quote_topic_text(&topic.text)
+            ),
+        )
+        .await;
+    }
+
     pub async fn remove_vote_from_topic(
         &self,
         guild: &Guild,
@@ -415,14 +632,16 @@ impl TopicsService {
                     .0
                     .send(TopicEvent::Update(topic.clone().into()));
 
-                let _ = self
-                    .events_channel
-                    .0
-                    .send(TopicEvent::OrderChange(topic_ids));
+                let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+                    guild_id: guild_id.to_hex(),
+                    topic_ids,
+                });
 
                 Ok(topic)
             }
-            None => bail!("Failed to fetch topic"),
+            None => {
+                bail!(AppError::NotFound(format!("Failed to find topic {id}")))
+            }
         }
     }
 
@@ -436,7 +655,7 @@ impl TopicsService {
         let result = self
             .repository
             .update_topic(
-                ObjectId::from_str(id)?,
+                parse_object_id(id, "topic")?,
                 PartialTopicDocument {
                     guild_id: None,
                     text: Some(form.text),
@@ -451,8 +670,8 @@ impl TopicsService {
             )
             .await?;
 
-        if result.modified_count != 1 {
-            bail!("Failed to update topic {id}")
+        if result.matched_count != 1 {
+            bail!(AppError::NotFound(format!("Failed to find topic {id}")))
         };
 
         let updated_topic = match self.get_topic(id, user_id, guild).await? {
@@ -476,7 +695,24 @@ impl TopicsService {
             )?)
             .await?;
 
-        let _ = self.events_channel.0.send(TopicEvent::OrderChange(topic_ids));
+        let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+            guild_id: updated_topic.guild_id.clone(),
+            topic_ids,
+        });
+
+        if updated_topic.created_by_user.id != user_id {
+            let username = self.get_username(user_id).await;
+
+            self.notify_topic_creator(
+                &updated_topic.clone().into(),
+                NotificationKind::TopicEdited,
+                format!(
+                    "@{username} edited your topic {}",
+                    quote_topic_text(&updated_topic.text)
+                ),
+            )
+            .await;
+        }
 
         Ok(updated_topic)
     }
@@ -489,11 +725,38 @@ impl TopicsService {
     ) -> Result<Topic> {
         let topic = match self.get_topic(id, user_id, guild).await? {
             Some(topic) => topic,
-            None => bail!("Failed to find this topic"),
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
         };
 
         self.repository
-            .delete_topic(ObjectId::from_str(id)?, Some(user_id))
+            .delete_topic(parse_object_id(id, "topic")?, Some(user_id))
+            .await?;
+
+        let _ = self
+            .events_channel
+            .0
+            .send(TopicEvent::Delete(topic.clone().into()));
+
+        Ok(topic.into())
+    }
+
+    pub async fn delete_topic_as_admin(
+        &self,
+        id: &str,
+        user_id: usize,
+        guild: &Guild,
+    ) -> Result<Topic> {
+        let topic = match self.get_topic(id, user_id, guild).await? {
+            Some(topic) => topic,
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
+        };
+
+        self.repository
+            .delete_topic(parse_object_id(id, "topic")?, None)
             .await?;
 
         let _ = self
@@ -513,7 +776,9 @@ impl TopicsService {
     ) -> Result<TopicPersonalized> {
         let topic = match self.get_topic(id, user_id, guild).await? {
             Some(topic) => topic,
-            None => bail!("Failed to find this topic"),
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
         };
 
         if topic.status == new_status {
@@ -528,7 +793,7 @@ impl TopicsService {
         let result = self
             .repository
             .update_topic(
-                ObjectId::from_str(id)?,
+                parse_object_id(id, "topic")?,
                 PartialTopicDocument {
                     guild_id: None,
                     updated_at: Some(DateTime::now()),
@@ -540,13 +805,15 @@ impl TopicsService {
             )
             .await?;
 
-        if result.modified_count != 1 {
-            bail!("Failed to update topic {id}")
+        if result.matched_count != 1 {
+            bail!(AppError::NotFound(format!("Failed to find topic {id}")))
         };
 
         let updated_topic = match self.get_topic(id, user_id, guild).await? {
             Some(topic) => topic,
-            None => bail!("Failed to find this topic"),
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
         };
 
         let _ = self
@@ -562,10 +829,481 @@ impl TopicsService {
                 )?)
                 .await?;
 
-            let _ =
-                self.events_channel.0.send(TopicEvent::OrderChange(topic_ids));
+            let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+                guild_id: updated_topic.guild_id.clone(),
+                topic_ids,
+            });
+        }
+
+        if updated_topic.status == TopicStatus::Archived
+            && updated_topic.created_by_user.id != user_id
+        {
+            let username = self.get_username(user_id).await;
+
+            self.notify_topic_creator(
+                &updated_topic.clone().into(),
+                NotificationKind::TopicArchived,
+                format!(
+                    "@{username} archived your topic {}",
+                    quote_topic_text(&updated_topic.text)
+                ),
+            )
+            .await;
+        }
+
+        Ok(updated_topic)
+    }
+
+    pub async fn volunteer_to_present(
+        &self,
+        id: &str,
+        user_id: usize,
+        guild: &Guild,
+    ) -> Result<TopicPersonalized> {
+        let topic = match self.get_topic(id, user_id, guild).await? {
+            Some(topic) => topic,
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
+        };
+
+        if !topic.can_volunteer {
+            bail!(AppError::Forbidden(format!(
+                "Topic {id} can't be presented by user {user_id}"
+            )))
         }
 
+        let result = self
+            .repository
+            .set_presenter(parse_object_id(id, "topic")?, user_id)
+            .await?;
+
+        if result.modified_count != 1 {
+            bail!(AppError::Conflict(format!(
+                "Topic {id} already has a presenter"
+            )))
+        };
+
+        let updated_topic = match self.get_topic(id, user_id, guild).await? {
+            Some(topic) => topic,
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
+        };
+
+        let _ = self
+            .events_channel
+            .0
+            .send(TopicEvent::Update(updated_topic.clone().into()));
+
+        let username = self.get_username(user_id).await;
+
+        self.notify_topic_creator(
+            &updated_topic.clone().into(),
+            NotificationKind::PresenterVolunteered,
+            format!(
+                "@{username} volunteered to present your topic {}",
+                quote_topic_text(&updated_topic.text)
+            ),
+        )
+        .await;
+
         Ok(updated_topic)
     }
+
+    /// Claims the topic for opening its gitlab issue, so concurrent requests
+    /// can't open one each. Fails with a conflict when it is already taken.
+    pub async fn claim_gitlab_issue(&self, id: &str) -> Result<()> {
+        let claimed_before =
+            DateTime::from_chrono(Utc::now() - GITLAB_ISSUE_CLAIM_TTL);
+
+        let result = self
+            .repository
+            .claim_gitlab_issue(parse_object_id(id, "topic")?, claimed_before)
+            .await?;
+
+        if result.modified_count != 1 {
+            bail!(AppError::Conflict(format!(
+                "Topic {id} already has a gitlab issue"
+            )))
+        };
+
+        Ok(())
+    }
+
+    /// Gives up the claim of `claim_gitlab_issue` when no issue was opened.
+    pub async fn release_gitlab_issue_claim(&self, id: &str) -> Result<()> {
+        self.repository
+            .release_gitlab_issue_claim(parse_object_id(id, "topic")?)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn link_gitlab_issue(
+        &self,
+        id: &str,
+        gitlab_issue: TopicGitlabIssue,
+        user_id: usize,
+        guild: &Guild,
+    ) -> Result<TopicPersonalized> {
+        let result = self
+            .repository
+            .set_gitlab_issue(parse_object_id(id, "topic")?, &gitlab_issue)
+            .await?;
+
+        if result.modified_count != 1 {
+            bail!(AppError::Conflict(format!(
+                "Topic {id} already has a gitlab issue"
+            )))
+        };
+
+        let updated_topic = match self.get_topic(id, user_id, guild).await? {
+            Some(topic) => topic,
+            None => bail!(AppError::NotFound(
+                "Failed to find this topic".to_string()
+            )),
+        };
+
+        let _ = self
+            .events_channel
+            .0
+            .send(TopicEvent::Update(updated_topic.clone().into()));
+
+        Ok(updated_topic)
+    }
+
+    /// Mirrors the state of a gitlab issue onto the topics linked to it,
+    /// archiving them on close when `archive_on_close` is set.
+    pub async fn handle_gitlab_issue_update(
+        &self,
+        project_id: u64,
+        iid: u64,
+        state: &str,
+        archive_on_close: bool,
+    ) -> Result<()> {
+        let documents = self
+            .repository
+            .get_topics_by_gitlab_issue(project_id, iid)
+            .await?;
+
+        for document in documents {
+            self.repository
+                .update_gitlab_issue_state(document._id, state)
+                .await?;
+
+            let should_archive = should_archive_on_issue_update(
+                archive_on_close,
+                state,
+                &document.status,
+            );
+
+            if should_archive {
+                self.repository
+                    .update_topic(
+                        document._id,
+                        PartialTopicDocument {
+                            guild_id: None,
+                            updated_at: Some(DateTime::now()),
+                            status: Some(TopicStatus::Archived),
+                            text: None,
+                            will_be_presented_by_the_creator: None,
+                            upvoted_by_users_ids: None,
+                        },
+                    )
+                    .await?;
+            }
+
+            let updated_document =
+                match self.repository.get_topic(document._id).await? {
+                    Some(document) => document,
+                    None => continue,
+                };
+
+            if should_archive {
+                info!(
+                    "Archived topic {} as gitlab issue {iid} was closed",
+                    document._id
+                );
+
+                let archived_topic: Topic = updated_document.into();
+
+                let _ = self
+                    .events_channel
+                    .0
+                    .send(TopicEvent::StatusChange(archived_topic.clone()));
+
+                let topic_ids = self
+                    .repository
+                    .get_topic_ids_sorted(&document.guild_id)
+                    .await?;
+
+                let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+                    guild_id: document.guild_id.to_hex(),
+                    topic_ids,
+                });
+
+                self.notify_topic_creator(
+                    &archived_topic,
+                    NotificationKind::TopicArchived,
+                    format!(
+                        "Your topic {} was archived as gitlab issue #{iid} was closed",
+                        quote_topic_text(&archived_topic.text)
+                    ),
+                )
+                .await;
+            } else {
+                let _ = self
+                    .events_channel
+                    .0
+                    .send(TopicEvent::Update(updated_document.into()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies `policy` to the open topics and votes of a member who left.
+    /// `guild_owners` maps guild ids to the ids of their owners.
+    pub async fn handle_departed_member(
+        &self,
+        user_id: usize,
+        policy: DepartedMemberPolicy,
+        guild_owners: &HashMap<ObjectId, usize>,
+    ) -> Result<()> {
+        if policy == DepartedMemberPolicy::Keep {
+            return Ok(());
+        }
+
+        let documents =
+            self.repository.get_open_topics_by_user(user_id).await?;
+
+        self.repository.remove_user_votes(user_id).await?;
+        self.repository.remove_presenter(user_id).await?;
+
+        let mut affected_guild_ids: Vec<ObjectId> = Vec::new();
+
+        for document in documents {
+            if !affected_guild_ids.contains(&document.guild_id) {
+                affected_guild_ids.push(document.guild_id)
+            }
+
+            if document.created_by_user_id != user_id {
+                if let Some(document) =
+                    self.repository.get_topic(document._id).await?
+                {
+                    let _ = self
+                        .events_channel
+                        .0
+                        .send(TopicEvent::Update(document.into()));
+                }
+
+                continue;
+            }
+
+            match policy {
+                DepartedMemberPolicy::Reassign => {
+                    match guild_owners.get(&document.guild_id) {
+                        Some(owner_id) if *owner_id != user_id => {
+                            self.repository
+                                .reassign_topic(document._id, *owner_id)
+                                .await?;
+                        }
+                        _ => continue,
+                    }
+                }
+                _ => {
+                    self.repository
+                        .update_topic(
+                            document._id,
+                            PartialTopicDocument {
+                                guild_id: None,
+                                updated_at: Some(DateTime::now()),
+                                status: Some(TopicStatus::Archived),
+                                text: None,
+                                will_be_presented_by_the_creator: None,
+                                upvoted_by_users_ids: None,
+                            },
+                        )
+                        .await?;
+                }
+            }
+
+            if let Some(document) =
+                self.repository.get_topic(document._id).await?
+            {
+                let event = match policy {
+                    DepartedMemberPolicy::Reassign => {
+                        TopicEvent::Update(document.into())
+                    }
+                    _ => TopicEvent::StatusChange(document.into()),
+                };
+
+                let _ = self.events_channel.0.send(event);
+            }
+        }
+
+        for guild_id in affected_guild_ids {
+            let topic_ids =
+                self.repository.get_topic_ids_sorted(&guild_id).await?;
+
+            let _ = self.events_channel.0.send(TopicEvent::OrderChange {
+                guild_id: guild_id.to_hex(),
+                topic_ids,
+            });
+        }
+
+        info!(
+            "Applied {policy:?} policy to topics of departed member {user_id}"
+        );
+
+        Ok(())
+    }
+}
+
+fn get_member(members: &HashMap<usize, Member>, user_id: usize) -> Member {
+    members
+        .get(&user_id)
+        .cloned()
+        .unwrap_or_else(|| MembersService::former_member(user_id))
+}
+
+/// Topics their creator won't present can be taken over by one member.
+fn can_volunteer(
+    is_created_by_current_user: bool,
+    status: &TopicStatus,
+    will_be_presented_by_the_creator: bool,
+    presenter_user_id: Option<usize>,
+) -> bool {
+    !is_created_by_current_user
+        && status == &TopicStatus::Created
+        && !will_be_presented_by_the_creator
+        && presenter_user_id.is_none()
+}
+
+pub fn quote_topic_text(text: &str) -> String {
+    let text = text.trim();
+
+    match text.char_indices().nth(60) {
+        Some((index, _)) => format!("\"{}…\"", &text[..index]),
+        None => format!("\"{text}\""),
+    }
+}
+
+fn can_create_gitlab_issue(
+    can_edit: bool,
+    status: &TopicStatus,
+    gitlab_issue: &Option<TopicGitlabIssue>,
+) -> bool {
+    can_edit && status == &TopicStatus::Created && gitlab_issue.is_none()
+}
+
+/// The creator hears about the vote that makes the topic reach `threshold`,
+/// unless it is their own.
+fn reaches_votes_threshold(
+    votes_count: usize,
+    threshold: usize,
+    created_by_user_id: usize,
+    voter_user_id: usize,
+) -> bool {
+    votes_count == threshold && created_by_user_id != voter_user_id
+}
+
+/// Votes come and go, the creator only hears about it once. When that can't
+/// be checked they rather miss the notification than get it twice.
+fn is_first_votes_notification(
+    was_notified: Result<bool>,
+    topic_id: &str,
+) -> bool {
+    match was_notified {
+        Ok(was_notified) => !was_notified,
+        Err(err) => {
+            error!("Failed to check notifications of topic {topic_id}: {err}");
+            false
+        }
+    }
+}
+
+/// Open topics are archived along with their issue when `archive_on_close`
+/// is set, topics that were archived already are left alone.
+fn should_archive_on_issue_update(
+    archive_on_close: bool,
+    state: &str,
+    status: &TopicStatus,
+) -> bool {
+    archive_on_close && state == "closed" && status == &TopicStatus::Created
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gitlab_issue() -> Option<TopicGitlabIssue> {
+        Some(TopicGitlabIssue {
+            project_id: 5,
+            iid: 3,
+            url: "https://gitlab.example.com/group/project/-/issues/3"
+                .to_string(),
+            state: "opened".to_string(),
+        })
+    }
+
+    #[test]
+    fn creators_hear_about_the_vote_reaching_the_threshold() {
+        assert!(reaches_votes_threshold(5, 5, 1, 2));
+
+        assert!(!reaches_votes_threshold(4, 5, 1, 2));
+        // only the vote reaching it, not the ones after
+        assert!(!reaches_votes_threshold(6, 5, 1, 2));
+        assert!(!reaches_votes_threshold(5, 5, 1, 1));
+    }
+
+    #[test]
+    fn creators_hear_about_votes_only_once() {
+        assert!(is_first_votes_notification(Ok(false), "topic"));
+
+        assert!(!is_first_votes_notification(Ok(true), "topic"));
+        assert!(!is_first_votes_notification(
+            Err(anyhow::anyhow!("database is down")),
+            "topic"
+        ));
+    }
+
+    #[test]
+    fn issues_are_only_created_once_for_open_topics() {
+        assert!(can_create_gitlab_issue(true, &TopicStatus::Created, &None));
+
+        assert!(!can_create_gitlab_issue(false, &TopicStatus::Created, &None));
+        assert!(!can_create_gitlab_issue(true, &TopicStatus::Archived, &None));
+        assert!(!can_create_gitlab_issue(
+            true,
+            &TopicStatus::Created,
+            &gitlab_issue()
+        ));
+    }
+
+    #[test]
+    fn open_topics_are_archived_when_their_issue_is_closed() {
+        assert!(should_archive_on_issue_update(
+            true,
+            "closed",
+            &TopicStatus::Created
+        ));
+
+        assert!(!should_archive_on_issue_update(
+            false,
+            "closed",
+            &TopicStatus::Created
+        ));
+        assert!(!should_archive_on_issue_update(
+            true,
+            "opened",
+            &TopicStatus::Created
+        ));
+        assert!(!should_archive_on_issue_update(
+            true,
+            "closed",
+            &TopicStatus::Archived
+        ));
+    }
 }
@@ -12,11 +12,12 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use std::str::FromStr;
+use utoipa::ToSchema;
 use validator::Validate;
 
-use super::TopicDocument;
+use super::{TopicDocument, TopicGitlabIssue};
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum TopicStatus {
     Created,
@@ -46,8 +47,12 @@ pub struct Topic {
     pub text: String,
     pub status: TopicStatus,
     pub will_be_presented_by_the_creator: bool,
+    #[serde(default)]
+    pub presenter_user_id: Option<usize>,
     pub created_by_user_id: usize,
     pub upvoted_by_users_ids: Vec<usize>,
+    #[serde(default)]
+    pub gitlab_issue: Option<TopicGitlabIssue>,
     #[serde(with = "serialization::chrono_date")]
     pub updated_at: DateTime<Utc>,
     #[serde(with = "serialization::chrono_date")]
@@ -65,8 +70,11 @@ impl TryFrom<Topic> for TopicDocument {
             status: topic.status,
             will_be_presented_by_the_creator: topic
                 .will_be_presented_by_the_creator,
+            presenter_user_id: topic.presenter_user_id,
             upvoted_by_users_ids: topic.upvoted_by_users_ids,
             created_by_user_id: topic.created_by_user_id,
+            gitlab_issue: topic.gitlab_issue,
+            gitlab_issue_claimed_at: None,
             updated_at: bson::DateTime::from_chrono(topic.updated_at),
             created_at: bson::DateTime::from_chrono(topic.created_at),
         })
@@ -82,8 +90,10 @@ impl From<TopicDocument> for Topic {
             status: document.status,
             will_be_presented_by_the_creator: document
                 .will_be_presented_by_the_creator,
+            presenter_user_id: document.presenter_user_id,
             upvoted_by_users_ids: document.upvoted_by_users_ids,
             created_by_user_id: document.created_by_user_id,
+            gitlab_issue: document.gitlab_issue,
             updated_at: document.updated_at.to_chrono(),
             created_at: document.created_at.to_chrono(),
         }
@@ -102,8 +112,12 @@ pub struct TopicPersonalized {
     pub can_change_status: bool,
     pub is_upvoted_by_current_user: bool,
     pub is_status_archived: bool,
+    pub can_create_gitlab_issue: bool,
+    pub can_volunteer: bool,
     pub created_by_user: Member,
+    pub presented_by_user: Option<Member>,
     pub upvoted_by_users: Vec<Member>,
+    pub gitlab_issue: Option<TopicGitlabIssue>,
     #[serde(with = "serialization::chrono_date")]
     pub updated_at: DateTime<Utc>,
     #[serde(with = "serialization::chrono_date")]
@@ -124,7 +138,9 @@ impl From<TopicPersonalized> for Topic {
                 .collect(),
             will_be_presented_by_the_creator: topic
                 .will_be_presented_by_the_creator,
+            presenter_user_id: topic.presented_by_user.map(|member| member.id),
             created_by_user_id: topic.created_by_user.id,
+            gitlab_issue: topic.gitlab_issue,
             updated_at: topic.updated_at,
             created_at: topic.created_at,
         }
@@ -209,7 +225,7 @@ pub enum TopicEvent {
     Create(Topic),
     Update(Topic),
     Delete(Topic),
-    OrderChange(Vec<String>),
+    OrderChange { guild_id: String, topic_ids: Vec<String> },
 }
 
 impl Into<Event> for TopicEvent {
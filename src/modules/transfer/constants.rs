@@ -0,0 +1,2 @@
+/// Bumped whenever the JSON export changes in a way older imports can't read.
+pub const GUILD_EXPORT_VERSION: u32 = 1;
@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::IntoResponse,
+};
+use std::sync::Arc;
+
+use crate::{
+    libs::{axum::Form, htmx::Location},
+    modules::{
+        app::{
+            app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
+            ToastLevel,
+        },
+        guild::GuildIdParameter,
+    },
+};
+
+use super::{
+    ExportParameters, ImportFormDTO, ImportGuildTemplate,
+    ImportPreviewTemplate,
+};
+
+/// File name of an export, e.g. `frontend-guild.json`.
+fn get_export_file_name(guild_name: &str, extension: &str) -> String {
+    let slug: String = guild_name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|char| if char.is_ascii_alphanumeric() { char } else { '-' })
+        .collect();
+
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+
+    match slug.is_empty() {
+        true => format!("guild.{extension}"),
+        false => format!("{slug}.{extension}"),
+    }
+}
+
+pub async fn export_guild(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildIdParameter>,
+    Query(ExportParameters { format }): Query<ExportParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user.clone(), &parameters.guild_id)
+        .await?;
+
+    if user.id != guild.created_by_user.id {
+        return Err(AppError::Forbidden(
+            "Only the guild owner can export the guild".to_string(),
+        ));
+    }
+
+    let export = app.transfer_service.export_guild(&guild).await?;
+
+    let content = app.transfer_service.render(&export, format)?;
+
+    let file_name = get_export_file_name(&guild.name, format.get_extension());
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(format.get_content_type()),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!(
+            "attachment; filename=\"{file_name}\""
+        ))?,
+    );
+
+    Ok((headers, content))
+}
+
+pub async fn get_import_guild_page(
+    Authenticated(user): Authenticated,
+) -> impl IntoResponse {
+    ImportGuildTemplate { user }
+}
+
+pub async fn import_guild(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    Form(form): Form<ImportFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    if form.dry_run {
+        let preview =
+            app.transfer_service.preview_import(&user, &form.content).await?;
+
+        return Ok(ImportPreviewTemplate { preview }.into_response());
+    }
+
+    let guild =
+        app.transfer_service.import_guild(&user, &form.content).await?;
+
+    let event = HxTriggerEvent::ShowToast {
+        level: ToastLevel::Info,
+        message: "Guild was imported".to_string(),
+    };
+
+    let event = event.to_header_value()?;
+
+    let location = Location {
+        path: format!("/guilds/{}", guild.id),
+        target: "#content".to_string(),
+        select: "#content".to_string(),
+        swap: "outerHTML".to_string(),
+    };
+
+    let location = HeaderValue::from_str(&serde_json::to_string(&location)?)?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Location", location);
+    headers.insert("HX-Trigger", event);
+
+    Ok(headers.into_response())
+}
@@ -0,0 +1,488 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use askama::Template;
+use bson::oid::ObjectId;
+use chrono::Utc;
+use tracing::error;
+use validator::Validate;
+
+use crate::{
+    libs::gitlab_api::gitlab_api::Member,
+    modules::{
+        app::{parse_object_id, AppError},
+        guild::{
+            Guild, GuildDocument, GuildEvent, GuildFormDTO, GuildsRepository,
+            GuildsService,
+        },
+        member::{MemberDirectory, MembersService},
+        topic::{
+            types::{TopicFormDTO, TopicStatus},
+            TopicDocument, TopicsRepository,
+        },
+    },
+};
+
+use super::{
+    constants::GUILD_EXPORT_VERSION, ExportFormat, ExportedGuild,
+    ExportedMember, ExportedTopic, GuildExport, GuildExportMarkdownTemplate,
+    ImportPreview,
+};
+
+/// Documents an import writes, along with their preview.
+struct ImportPlan {
+    preview: ImportPreview,
+    guild_document: GuildDocument,
+    topic_documents: Vec<TopicDocument>,
+}
+
+/// Exports guilds with their topics and recreates them from an export.
+pub struct TransferService {
+    guilds_repository: Arc<GuildsRepository>,
+    guilds_service: Arc<GuildsService>,
+    topics_repository: Arc<TopicsRepository>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+}
+
+impl TransferService {
+    pub fn new(
+        guilds_repository: Arc<GuildsRepository>,
+        guilds_service: Arc<GuildsService>,
+        topics_repository: Arc<TopicsRepository>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    ) -> Self {
+        Self {
+            guilds_repository,
+            guilds_service,
+            topics_repository,
+            member_directory,
+        }
+    }
+
+    pub async fn export_guild(&self, guild: &Guild) -> Result<GuildExport> {
+        let documents = self
+            .topics_repository
+            .get_all_guild_topics(parse_object_id(&guild.id, "guild")?)
+            .await?;
+
+        let members: HashMap<usize, Member> = self
+            .member_directory
+            .get_all_members()
+            .await
+            .into_iter()
+            .map(|member| (member.id, member))
+            .collect();
+
+        let get_member = |user_id: usize| -> ExportedMember {
+            members
+                .get(&user_id)
+                .cloned()
+                .unwrap_or_else(|| MembersService::former_member(user_id))
+                .into()
+        };
+
+        let topics = documents
+            .into_iter()
+            .map(|document| ExportedTopic {
+                text: document.text,
+                status: document.status,
+                will_be_presented_by_the_creator: document
+                    .will_be_presented_by_the_creator,
+                author: get_member(document.created_by_user_id),
+                presenter: document.presenter_user_id.map(get_member),
+                voters: document
+                    .upvoted_by_users_ids
+                    .into_iter()
+                    .map(get_member)
+                    .collect(),
+                gitlab_issue: document.gitlab_issue,
+                updated_at: document.updated_at.to_chrono(),
+                created_at: document.created_at.to_chrono(),
+            })
+            .collect();
+
+        Ok(GuildExport {
+            version: GUILD_EXPORT_VERSION,
+            exported_at: Utc::now(),
+            guild: ExportedGuild {
+                name: guild.name.clone(),
+                owner: guild.created_by_user.clone().into(),
+                members: guild
+                    .members
+                    .iter()
+                    .cloned()
+                    .map(ExportedMember::from)
+                    .collect(),
+                updated_at: guild.updated_at,
+                created_at: guild.created_at,
+            },
+            topics,
+        })
+    }
+
+    pub fn render(
+        &self,
+        export: &GuildExport,
+        format: ExportFormat,
+    ) -> Result<String> {
+        let content = match format {
+            ExportFormat::Json => serde_json::to_string_pretty(export)?,
+            ExportFormat::Csv => render_csv(export),
+            ExportFormat::Markdown => {
+                GuildExportMarkdownTemplate { export }.render()?
+            }
+        };
+
+        Ok(content)
+    }
+
+    pub async fn preview_import(
+        &self,
+        user: &Member,
+        content: &str,
+    ) -> Result<ImportPreview> {
+        Ok(self.plan_import(user, content).await?.preview)
+    }
+
+    /// Creates a guild owned by the importing member from a JSON export.
+    pub async fn import_guild(
+        &self,
+        user: &Member,
+        content: &str,
+    ) -> Result<Guild> {
+        let ImportPlan { guild_document, topic_documents, .. } =
+            self.plan_import(user, content).await?;
+
+        let guild_id = guild_document._id;
+
+        self.guilds_repository.insert_guild_document(guild_document).await?;
+
+        if !topic_documents.is_empty() {
+            if let Err(err) = self
+                .topics_repository
+                .insert_topic_documents(topic_documents)
+                .await
+            {
+                // a guild without its topics is not worth keeping
+                if let Err(err) =
+                    self.guilds_repository.delete_guild(guild_id, None).await
+                {
+                    error!("Failed to remove partially imported guild {guild_id}: {err}");
+                }
+
+                return Err(err);
+            }
+        }
+
+        let guild = self
+            .guilds_service
+            .get_guild_or_error(user.clone(), &guild_id.to_hex())
+            .await?;
+
+        let _ = self
+            .guilds_service
+            .events_channel
+            .0
+            .send(GuildEvent::Create(guild.clone()));
+
+        Ok(guild)
+    }
+
+    async fn plan_import(
+        &self,
+        user: &Member,
+        content: &str,
+    ) -> Result<ImportPlan> {
+        let export: GuildExport = match serde_json::from_str(content) {
+            Ok(export) => export,
+            Err(err) => bail!(AppError::Validation(format!(
+                "Not a guild export: {err}"
+            ))),
+        };
+
+        if export.version != GUILD_EXPORT_VERSION {
+            bail!(AppError::Validation(format!(
+                "Unsupported export version {}, expected {GUILD_EXPORT_VERSION}",
+                export.version
+            )));
+        }
+
+        let form = GuildFormDTO {
+            name: export.guild.name.clone(),
+            member_ids: Vec::new(),
+            member_search_term: String::new(),
+        };
+
+        if form.validate().is_err() {
+            bail!(AppError::Validation(
+                "Guild name length must be between 2 and 200 characters"
+                    .to_string()
+            ));
+        }
+
+        // held to the same rules as topics created from the guild page
+        for (index, topic) in export.topics.iter().enumerate() {
+            let form = TopicFormDTO {
+                text: topic.text.clone(),
+                will_be_presented_by_the_creator: None,
+            };
+
+            if form.validate().is_err() {
+                bail!(AppError::Validation(format!(
+                    "Topic {} length must be between 2 and 200 characters",
+                    index + 1
+                )));
+            }
+        }
+
+        let members_by_username: HashMap<String, Member> = self
+            .member_directory
+            .get_all_members()
+            .await
+            .into_iter()
+            .filter(|member| !member.is_former_member)
+            .map(|member| (member.username.to_lowercase(), member))
+            .collect();
+
+        let get_member_id = |member: &ExportedMember| -> Option<usize> {
+            members_by_username
+                .get(&member.username.to_lowercase())
+                .map(|member| member.id)
+        };
+
+        let mut members: Vec<Member> = Vec::new();
+        let mut unmatched_usernames: Vec<String> = Vec::new();
+
+        // the former owner stays in the guild as a member
+        let exported_members = export
+            .guild
+            .members
+            .iter()
+            .chain(std::iter::once(&export.guild.owner));
+
+        for exported_member in exported_members {
+            match members_by_username
+                .get(&exported_member.username.to_lowercase())
+            {
+                Some(member) if member.id == user.id => {}
+                Some(member) => {
+                    if !members.iter().any(|added| added.id == member.id) {
+                        members.push(member.clone());
+                    }
+                }
+                None => {
+                    if !unmatched_usernames.contains(&exported_member.username)
+                    {
+                        unmatched_usernames
+                            .push(exported_member.username.clone());
+                    }
+                }
+            }
+        }
+
+        let guild_id = ObjectId::new();
+
+        let mut votes_count = 0;
+        let mut dropped_votes_count = 0;
+        let mut extra_votes_count = 0;
+        let mut reassigned_topics_count = 0;
+
+        // members vote for one open topic per guild, later votes are dropped
+        let mut open_topic_voter_ids: HashSet<usize> = HashSet::new();
+
+        let topic_documents: Vec<TopicDocument> = export
+            .topics
+            .iter()
+            .map(|topic| {
+                let created_by_user_id = match get_member_id(&topic.author) {
+                    Some(user_id) => user_id,
+                    None => {
+                        reassigned_topics_count += 1;
+                        user.id
+                    }
+                };
+
+                let mut upvoted_by_users_ids: Vec<usize> = Vec::new();
+
+                for voter in topic.voters.iter() {
+                    let voter_id = match get_member_id(voter) {
+                        Some(voter_id) => voter_id,
+                        None => {
+                            dropped_votes_count += 1;
+                            continue;
+                        }
+                    };
+
+                    let is_extra_vote = upvoted_by_users_ids
+                        .contains(&voter_id)
+                        || (topic.status == TopicStatus::Created
+                            && !open_topic_voter_ids.insert(voter_id));
+
+                    if is_extra_vote {
+                        extra_votes_count += 1;
+                        continue;
+                    }
+
+                    upvoted_by_users_ids.push(voter_id);
+                }
+
+                votes_count += upvoted_by_users_ids.len();
+
+                TopicDocument {
+                    _id: ObjectId::new(),
+                    guild_id,
+                    text: topic.text.clone(),
+                    status: topic.status.clone(),
+                    will_be_presented_by_the_creator: topic
+                        .will_be_presented_by_the_creator,
+                    presenter_user_id: topic
+                        .presenter
+                        .as_ref()
+                        .and_then(get_member_id),
+                    created_by_user_id,
+                    upvoted_by_users_ids,
+                    // the issue belongs to the exported guild, linking it
+                    // again would let both guilds react to its events
+                    gitlab_issue: None,
+                    gitlab_issue_claimed_at: None,
+                    updated_at: bson::DateTime::from_chrono(topic.updated_at),
+                    created_at: bson::DateTime::from_chrono(topic.created_at),
+                }
+            })
+            .collect();
+
+        let open_topics_count = topic_documents
+            .iter()
+            .filter(|topic| topic.status == TopicStatus::Created)
+            .count();
+
+        let guild_document = GuildDocument {
+            _id: guild_id,
+            name: export.guild.name.trim().to_string(),
+            created_by_user_id: user.id,
+            member_ids: members.iter().map(|member| member.id).collect(),
+            updated_at: bson::DateTime::from_chrono(export.guild.updated_at),
+            created_at: bson::DateTime::from_chrono(export.guild.created_at),
+        };
+
+        Ok(ImportPlan {
+            preview: ImportPreview {
+                guild_name: guild_document.name.clone(),
+                members,
+                unmatched_usernames,
+                open_topics_count,
+                archived_topics_count: topic_documents.len()
+                    - open_topics_count,
+                votes_count,
+                dropped_votes_count,
+                extra_votes_count,
+                reassigned_topics_count,
+            },
+            guild_document,
+            topic_documents,
+        })
+    }
+}
+
+fn escape_csv_field(value: &str) -> String {
+    // spreadsheets run fields starting with these as formulas, the quote
+    // makes them plain text
+    let value = match value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        true => format!("'{value}"),
+        false => value.to_string(),
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// One row per topic, voters being a space separated list of usernames.
+fn render_csv(export: &GuildExport) -> String {
+    let mut rows = vec![[
+        "text",
+        "status",
+        "author",
+        "presenter",
+        "will_be_presented_by_the_creator",
+        "votes_count",
+        "voters",
+        "gitlab_issue",
+        "created_at",
+        "updated_at",
+    ]
+    .join(",")];
+
+    for topic in export.topics.iter() {
+        let voters: Vec<&str> =
+            topic.voters.iter().map(|voter| voter.username.as_str()).collect();
+
+        let row = [
+            topic.text.trim().to_string(),
+            topic.status.to_string(),
+            topic.author.username.clone(),
+            topic
+                .presenter
+                .as_ref()
+                .map(|presenter| presenter.username.clone())
+                .unwrap_or_default(),
+            topic.will_be_presented_by_the_creator.to_string(),
+            topic.voters.len().to_string(),
+            voters.join(" "),
+            topic
+                .gitlab_issue
+                .as_ref()
+                .map(|issue| issue.url.clone())
+                .unwrap_or_default(),
+            topic.created_at.to_rfc3339(),
+            topic.updated_at.to_rfc3339(),
+        ];
+
+        rows.push(
+            row.iter()
+                .map(|field| escape_csv_field(field))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+    }
+
+    rows.join("\r\n") + "\r\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_csv_field_keeps_plain_values() {
+        assert_eq!(escape_csv_field("Rust lifetimes"), "Rust lifetimes");
+        assert_eq!(
+            escape_csv_field("2024-03-04T08:00:00+00:00"),
+            "2024-03-04T08:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn escape_csv_field_quotes_separators() {
+        assert_eq!(escape_csv_field("a, b"), "\"a, b\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn escape_csv_field_defuses_formulas() {
+        assert_eq!(escape_csv_field("=1+1"), "'=1+1");
+        assert_eq!(escape_csv_field("+1"), "'+1");
+        assert_eq!(escape_csv_field("-1"), "'-1");
+        assert_eq!(escape_csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(
+            escape_csv_field("=HYPERLINK(\"http://a\", \"b\")"),
+            "\"'=HYPERLINK(\"\"http://a\"\", \"\"b\"\")\""
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    libs::{gitlab_api::gitlab_api::Member, serialization},
+    modules::topic::{types::TopicStatus, TopicGitlabIssue},
+};
+
+/// Member referenced by an export, matched by username on import.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ExportedMember {
+    pub id: usize,
+    pub username: String,
+    pub name: String,
+}
+
+impl From<Member> for ExportedMember {
+    fn from(member: Member) -> ExportedMember {
+        ExportedMember {
+            id: member.id,
+            username: member.username,
+            name: member.name,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ExportedGuild {
+    pub name: String,
+    pub owner: ExportedMember,
+    pub members: Vec<ExportedMember>,
+    #[serde(with = "serialization::chrono_date")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(with = "serialization::chrono_date")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ExportedTopic {
+    pub text: String,
+    pub status: TopicStatus,
+    pub will_be_presented_by_the_creator: bool,
+    pub author: ExportedMember,
+    pub presenter: Option<ExportedMember>,
+    pub voters: Vec<ExportedMember>,
+    pub gitlab_issue: Option<TopicGitlabIssue>,
+    #[serde(with = "serialization::chrono_date")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(with = "serialization::chrono_date")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Everything needed to recreate a guild, the JSON export format.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GuildExport {
+    pub version: u32,
+    #[serde(with = "serialization::chrono_date")]
+    pub exported_at: DateTime<Utc>,
+    pub guild: ExportedGuild,
+    pub topics: Vec<ExportedTopic>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn get_content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportParameters {
+    pub format: ExportFormat,
+}
+
+#[derive(Template)]
+#[template(path = "export/guild.md")]
+pub struct GuildExportMarkdownTemplate<'a> {
+    pub export: &'a GuildExport,
+}
+
+impl GuildExportMarkdownTemplate<'_> {
+    pub fn get_open_topics(&self) -> Vec<&ExportedTopic> {
+        self.get_topics(TopicStatus::Created)
+    }
+
+    pub fn get_archived_topics(&self) -> Vec<&ExportedTopic> {
+        self.get_topics(TopicStatus::Archived)
+    }
+
+    fn get_topics(&self, status: TopicStatus) -> Vec<&ExportedTopic> {
+        self.export
+            .topics
+            .iter()
+            .filter(|topic| topic.status == status)
+            .collect()
+    }
+
+    /// Keeps topic texts from breaking out of their table cell.
+    pub fn escape_table_cell(&self, text: &str) -> String {
+        text.trim().replace('|', "\\|").replace('\n', " ")
+    }
+}
+
+/// What an import would create, shown before anything is written.
+pub struct ImportPreview {
+    pub guild_name: String,
+    pub members: Vec<Member>,
+    /// Exported members missing from the member directory.
+    pub unmatched_usernames: Vec<String>,
+    pub open_topics_count: usize,
+    pub archived_topics_count: usize,
+    pub votes_count: usize,
+    /// Votes of unmatched members, they are not imported.
+    pub dropped_votes_count: usize,
+    /// Repeated votes and votes for a second open topic, they are not
+    /// imported.
+    pub extra_votes_count: usize,
+    /// Topics of unmatched members, they are attributed to the importer.
+    pub reassigned_topics_count: usize,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ImportFormDTO {
+    pub content: String,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Template)]
+#[template(path = "pages/transfer/import-guild.html")]
+pub struct ImportGuildTemplate {
+    pub user: Member,
+}
+
+#[derive(Template)]
+#[template(path = "components/transfer/import-preview.html")]
+pub struct ImportPreviewTemplate {
+    pub preview: ImportPreview,
+}
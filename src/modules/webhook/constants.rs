@@ -0,0 +1,11 @@
+use std::time::Duration;
+
+/// Deliveries are attempted this many times before they are given up on.
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+/// Delay before the first retry, doubled on every following one.
+pub const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(10);
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+pub const WEBHOOK_QUEUE_CAPACITY: usize = 500;
+pub const WEBHOOK_DELIVERIES_LIMIT: i64 = 20;
+pub const WEBHOOK_DELIVERIES_TTL: Duration =
+    Duration::from_secs(60 * 60 * 24 * 7);
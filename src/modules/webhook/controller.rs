@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use std::sync::Arc;
+use validator::Validate;
+
+use crate::{
+    libs::{axum::Form, gitlab_api::gitlab_api::Member},
+    modules::{
+        app::{
+            app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
+            ToastLevel,
+        },
+        guild::{Guild, GuildIdParameter},
+    },
+};
+
+use super::{
+    GuildWebhooksTemplate, WebhookDeliveriesTemplate, WebhookFormDTO,
+    WebhookIdParameter,
+};
+
+async fn get_owned_guild(
+    app: &App,
+    user: &Member,
+    guild_id: &str,
+) -> Result<Guild, AppError> {
+    let guild =
+        app.guilds_service.get_guild_or_error(user.clone(), guild_id).await?;
+
+    if user.id != guild.created_by_user.id {
+        return Err(AppError::Forbidden(
+            "Only the guild owner can manage webhooks".to_string(),
+        ));
+    }
+
+    Ok(guild)
+}
+
+async fn render_guild_webhooks(
+    app: &App,
+    guild: &Guild,
+    error_message: String,
+) -> Result<GuildWebhooksTemplate, AppError> {
+    let webhooks = app.webhooks_service.get_guild_webhooks(&guild.id).await?;
+
+    Ok(GuildWebhooksTemplate {
+        guild_id: guild.id.clone(),
+        webhooks,
+        error_message,
+    })
+}
+
+pub async fn get_guild_webhooks(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    render_guild_webhooks(&app, &guild, String::new()).await
+}
+
+pub async fn create_webhook(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<GuildIdParameter>,
+    State(app): State<Arc<App>>,
+    Form(form): Form<WebhookFormDTO>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    if let Err(errors) = form.validate() {
+        let error_message = errors
+            .field_errors()
+            .into_values()
+            .flatten()
+            .filter_map(|error| error.message.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        return Ok(render_guild_webhooks(&app, &guild, error_message)
+            .await?
+            .into_response());
+    }
+
+    if let Err(err) =
+        app.webhooks_service.create_webhook(&guild.id, user.id, form).await
+    {
+        let error_message = match AppError::from(err) {
+            AppError::Internal(err) => return Err(AppError::Internal(err)),
+            err => err.get_message(),
+        };
+
+        return Ok(render_guild_webhooks(&app, &guild, error_message)
+            .await?
+            .into_response());
+    }
+
+    let event = HxTriggerEvent::ShowToast {
+        level: ToastLevel::Info,
+        message: "Webhook was added".to_string(),
+    };
+
+    let event = event.to_header_value()?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Trigger", event);
+
+    Ok((headers, render_guild_webhooks(&app, &guild, String::new()).await?)
+        .into_response())
+}
+
+pub async fn delete_webhook(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<WebhookIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    app.webhooks_service
+        .delete_webhook(&guild.id, &parameters.webhook_id)
+        .await?;
+
+    render_guild_webhooks(&app, &guild, String::new()).await
+}
+
+pub async fn get_webhook_deliveries(
+    Authenticated(user): Authenticated,
+    Path(parameters): Path<WebhookIdParameter>,
+    State(app): State<Arc<App>>,
+) -> Result<impl IntoResponse, AppError> {
+    let guild = get_owned_guild(&app, &user, &parameters.guild_id).await?;
+
+    let deliveries = app
+        .webhooks_service
+        .get_deliveries(&guild.id, &parameters.webhook_id)
+        .await?;
+
+    Ok(WebhookDeliveriesTemplate {
+        webhook_id: parameters.webhook_id,
+        deliveries,
+    })
+}
@@ -0,0 +1,33 @@
+use bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::webhook::types::{WebhookEventKind, WebhookFormat};
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WebhookDocument {
+    pub _id: ObjectId,
+    pub guild_id: ObjectId,
+    pub url: String,
+    pub format: WebhookFormat,
+    pub event_kinds: Vec<WebhookEventKind>,
+    /// Key the payloads are signed with.
+    pub secret: String,
+    pub created_by_user_id: usize,
+    pub updated_at: DateTime,
+    pub created_at: DateTime,
+}
+
+/// A single attempt to deliver an event to a webhook.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WebhookDeliveryDocument {
+    pub _id: ObjectId,
+    pub webhook_id: ObjectId,
+    /// Shared by all attempts to deliver the same event.
+    pub delivery_id: String,
+    pub event_kind: WebhookEventKind,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: DateTime,
+}
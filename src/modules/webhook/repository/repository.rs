@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use crate::libs::mongo::MongoDatabase;
+use anyhow::{Context, Result};
+use bson::{doc, oid::ObjectId};
+use futures_util::TryStreamExt;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    results::{DeleteResult, InsertOneResult},
+    Collection, IndexModel,
+};
+
+use crate::modules::webhook::constants::WEBHOOK_DELIVERIES_TTL;
+
+use super::{WebhookDeliveryDocument, WebhookDocument};
+
+pub struct WebhooksRepository {
+    database: Arc<MongoDatabase>,
+    collection_name: String,
+    deliveries_collection_name: String,
+}
+
+impl WebhooksRepository {
+    pub async fn new(database: Arc<MongoDatabase>) -> Self {
+        let repo = WebhooksRepository {
+            database,
+            collection_name: String::from("webhooks"),
+            deliveries_collection_name: String::from("webhook_deliveries"),
+        };
+
+        let _ = repo.set_indexes().await;
+
+        repo
+    }
+
+    pub async fn set_indexes(&self) -> Result<()> {
+        let options =
+            IndexOptions::builder().name("guild_id".to_string()).build();
+
+        let indexes = vec![IndexModel::builder()
+            .keys(doc! {"guild_id":1})
+            .options(options)
+            .build()];
+
+        self.database
+            .create_indexes::<WebhookDocument>(&self.collection_name, indexes)
+            .await?;
+
+        let deliveries_indexes = vec![
+            IndexModel::builder()
+                .keys(doc! {"webhook_id":1, "created_at":-1})
+                .options(
+                    IndexOptions::builder()
+                        .name("webhook_id".to_string())
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! {"created_at":1})
+                .options(
+                    IndexOptions::builder()
+                        .name("created_at".to_string())
+                        .expire_after(WEBHOOK_DELIVERIES_TTL)
+                        .build(),
+                )
+                .build(),
+        ];
+
+        self.database
+            .create_indexes::<WebhookDeliveryDocument>(
+                &self.deliveries_collection_name,
+                deliveries_indexes,
+            )
+            .await
+    }
+
+    pub async fn insert_webhook(
+        &self,
+        document: WebhookDocument,
+    ) -> Result<InsertOneResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .insert_one(document, None)
+            .await
+            .with_context(|| "Failed to insert webhook document")?;
+
+        Ok(result)
+    }
+
+    pub async fn get_webhook(
+        &self,
+        id: ObjectId,
+    ) -> Result<Option<WebhookDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDocument> =
+            database.collection(&self.collection_name);
+
+        let document = collection.find_one(doc! {"_id": id}, None).await?;
+
+        Ok(document)
+    }
+
+    pub async fn get_guild_webhooks(
+        &self,
+        guild_id: ObjectId,
+    ) -> Result<Vec<WebhookDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDocument> =
+            database.collection(&self.collection_name);
+
+        let options =
+            FindOptions::builder().sort(doc! {"created_at": 1}).build();
+
+        let documents = collection
+            .find(doc! {"guild_id": guild_id}, options)
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        id: ObjectId,
+        guild_id: ObjectId,
+    ) -> Result<DeleteResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDocument> =
+            database.collection(&self.collection_name);
+
+        let result = collection
+            .delete_one(doc! {"_id": id, "guild_id": guild_id}, None)
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn delete_guild_webhooks(
+        &self,
+        guild_id: ObjectId,
+    ) -> Result<DeleteResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDocument> =
+            database.collection(&self.collection_name);
+
+        let result =
+            collection.delete_many(doc! {"guild_id": guild_id}, None).await?;
+
+        Ok(result)
+    }
+
+    pub async fn insert_delivery(
+        &self,
+        document: WebhookDeliveryDocument,
+    ) -> Result<InsertOneResult> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDeliveryDocument> =
+            database.collection(&self.deliveries_collection_name);
+
+        let result = collection
+            .insert_one(document, None)
+            .await
+            .with_context(|| "Failed to insert webhook delivery document")?;
+
+        Ok(result)
+    }
+
+    pub async fn get_deliveries(
+        &self,
+        webhook_id: ObjectId,
+        limit: i64,
+    ) -> Result<Vec<WebhookDeliveryDocument>> {
+        let database = self.database.get_database_client()?;
+
+        let collection: Collection<WebhookDeliveryDocument> =
+            database.collection(&self.deliveries_collection_name);
+
+        let options = FindOptions::builder()
+            .sort(doc! {"created_at": -1})
+            .limit(limit)
+            .build();
+
+        let documents = collection
+            .find(doc! {"webhook_id": webhook_id}, options)
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(documents)
+    }
+}
@@ -0,0 +1,690 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use bson::oid::ObjectId;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::{
+    select,
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc::{self, error::TrySendError},
+        Mutex,
+    },
+    time::sleep,
+};
+use tracing::{error, info, warn};
+use url::{Host, Url};
+
+use crate::{
+    libs::utils::{escape_slack_text, format_slack_link},
+    modules::{
+        app::{parse_object_id, AppError},
+        guild::{Guild, GuildEvent, GuildsService},
+        member::MemberDirectory,
+        session::SessionEvent,
+        topic::types::{TopicEvent, TopicStatus},
+    },
+};
+
+use super::{
+    constants::{
+        WEBHOOK_DELIVERIES_LIMIT, WEBHOOK_MAX_ATTEMPTS,
+        WEBHOOK_QUEUE_CAPACITY, WEBHOOK_RETRY_DELAY, WEBHOOK_TIMEOUT,
+    },
+    Webhook, WebhookDelivery, WebhookDeliveryDocument, WebhookDocument,
+    WebhookEvent, WebhookFormDTO, WebhookFormat, WebhookJob,
+    WebhooksRepository,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Posts guild activity to outgoing webhooks configured by guild owners.
+pub struct WebhooksService {
+    repository: Arc<WebhooksRepository>,
+    guilds_service: Arc<GuildsService>,
+    member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+    app_url: String,
+    queue_sender: mpsc::Sender<WebhookJob>,
+    queue_receiver: Mutex<mpsc::Receiver<WebhookJob>>,
+}
+
+impl WebhooksService {
+    pub fn new(
+        repository: Arc<WebhooksRepository>,
+        guilds_service: Arc<GuildsService>,
+        member_directory: Arc<dyn MemberDirectory + Send + Sync>,
+        app_url: String,
+    ) -> Self {
+        let (queue_sender, queue_receiver) =
+            mpsc::channel::<WebhookJob>(WEBHOOK_QUEUE_CAPACITY);
+
+        Self {
+            repository,
+            guilds_service,
+            member_directory,
+            app_url,
+            queue_sender,
+            queue_receiver: Mutex::new(queue_receiver),
+        }
+    }
+
+    pub async fn create_webhook(
+        &self,
+        guild_id: &str,
+        user_id: usize,
+        form: WebhookFormDTO,
+    ) -> Result<Webhook> {
+        let url = match Url::parse(form.url.trim()) {
+            Ok(url) => url,
+            Err(_) => bail!(AppError::Validation(
+                "Webhook url is not a valid url".to_string()
+            )),
+        };
+
+        if !["http", "https"].contains(&url.scheme()) {
+            bail!(AppError::Validation(
+                "Webhook url has to use http or https".to_string()
+            ));
+        }
+
+        resolve_public_addresses(&url).await?;
+
+        let guild_id = parse_object_id(guild_id, "guild")?;
+
+        let now = bson::DateTime::now();
+
+        let document = WebhookDocument {
+            _id: ObjectId::new(),
+            guild_id,
+            url: url.to_string(),
+            format: form.format,
+            event_kinds: form.event_kinds,
+            secret: uuid::Uuid::new_v4().simple().to_string(),
+            created_by_user_id: user_id,
+            updated_at: now,
+            created_at: now,
+        };
+
+        self.repository.insert_webhook(document.clone()).await?;
+
+        Ok(document.into())
+    }
+
+    pub async fn get_guild_webhooks(
+        &self,
+        guild_id: &str,
+    ) -> Result<Vec<Webhook>> {
+        let guild_id = parse_object_id(guild_id, "guild")?;
+
+        let documents = self.repository.get_guild_webhooks(guild_id).await?;
+
+        Ok(documents.into_iter().map(Webhook::from).collect())
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        guild_id: &str,
+        webhook_id: &str,
+    ) -> Result<()> {
+        let result = self
+            .repository
+            .delete_webhook(
+                parse_object_id(webhook_id, "webhook")?,
+                parse_object_id(guild_id, "guild")?,
+            )
+            .await?;
+
+        if result.deleted_count == 0 {
+            bail!(AppError::NotFound(format!(
+                "Failed to find webhook {webhook_id}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_deliveries(
+        &self,
+        guild_id: &str,
+        webhook_id: &str,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let webhook_id = parse_object_id(webhook_id, "webhook")?;
+
+        match self.repository.get_webhook(webhook_id).await? {
+            Some(webhook) if webhook.guild_id.to_hex() == guild_id => {}
+            _ => bail!(AppError::NotFound(format!(
+                "Failed to find webhook {webhook_id}"
+            ))),
+        }
+
+        let documents = self
+            .repository
+            .get_deliveries(webhook_id, WEBHOOK_DELIVERIES_LIMIT)
+            .await?;
+
+        Ok(documents.into_iter().map(WebhookDelivery::from).collect())
+    }
+
+    /// Turns topic, guild and session events into webhook deliveries.
+    pub async fn listen(
+        &self,
+        mut topic_events: broadcast::Receiver<TopicEvent>,
+        mut guild_events: broadcast::Receiver<GuildEvent>,
+        mut session_events: broadcast::Receiver<SessionEvent>,
+    ) {
+        loop {
+            let event = select! {
+                event = topic_events.recv() => match event {
+                    Ok(TopicEvent::Create(topic)) => {
+                        Some(WebhookEvent::TopicCreated(topic))
+                    }
+                    Ok(TopicEvent::StatusChange(topic))
+                        if topic.status == TopicStatus::Archived =>
+                    {
+                        Some(WebhookEvent::TopicArchived(topic))
+                    }
+                    Ok(_) => None,
+                    Err(err) => {
+                        if self.handle_receive_error(err) { break; }
+                        None
+                    }
+                },
+                event = guild_events.recv() => match event {
+                    Ok(GuildEvent::Delete(guild_id)) => {
+                        self.remove_guild_webhooks(&guild_id).await;
+                        None
+                    }
+                    Ok(_) => None,
+                    Err(err) => {
+                        if self.handle_receive_error(err) { break; }
+                        None
+                    }
+                },
+                event = session_events.recv() => match event {
+                    Ok(SessionEvent::Schedule(session)) => {
+                        Some(WebhookEvent::SessionScheduled(session))
+                    }
+                    Err(err) => {
+                        if self.handle_receive_error(err) { break; }
+                        None
+                    }
+                },
+            };
+
+            if let Some(event) = event {
+                if let Err(err) = self.enqueue(&event).await {
+                    error!(
+                        "Failed to queue webhooks of guild {}: {err}",
+                        event.get_guild_id()
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns true when the channel is closed and listening should stop.
+    fn handle_receive_error(&self, err: RecvError) -> bool {
+        match err {
+            RecvError::Lagged(count) => {
+                warn!("Webhooks missed {count} events");
+                false
+            }
+            RecvError::Closed => true,
+        }
+    }
+
+    async fn remove_guild_webhooks(&self, guild_id: &str) {
+        let result = match ObjectId::from_str(guild_id) {
+            Ok(guild_id) => {
+                self.repository.delete_guild_webhooks(guild_id).await
+            }
+            Err(err) => Err(err.into()),
+        };
+
+        if let Err(err) = result {
+            error!("Failed to remove webhooks of guild {guild_id}: {err}");
+        }
+    }
+
+    async fn enqueue(&self, event: &WebhookEvent) -> Result<()> {
+        let webhooks = self
+            .repository
+            .get_guild_webhooks(ObjectId::from_str(event.get_guild_id())?)
+            .await?
+            .into_iter()
+            .filter(|webhook| webhook.event_kinds.contains(&event.get_kind()))
+            .collect::<Vec<_>>();
+
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+
+        let guild = match self
+            .guilds_service
+            .get_guild_by_id(event.get_guild_id())
+            .await?
+        {
+            Some(guild) => guild,
+            None => bail!("Failed to find guild {}", event.get_guild_id()),
+        };
+
+        for webhook in webhooks {
+            let delivery_id = uuid::Uuid::new_v4().to_string();
+
+            let body = match webhook.format {
+                WebhookFormat::Generic => {
+                    self.get_generic_payload(event, &guild, &delivery_id).await
+                }
+                WebhookFormat::Slack => {
+                    self.get_slack_payload(event, &guild).await
+                }
+            };
+
+            let job = WebhookJob {
+                webhook_id: webhook._id.to_hex(),
+                delivery_id,
+                event_kind: event.get_kind(),
+                body: body.to_string(),
+                attempt: 1,
+            };
+
+            match self.queue_sender.try_send(job) {
+                Ok(()) => {}
+                Err(TrySendError::Full(job)) => {
+                    warn!(
+                        "Webhook queue is full, dropping delivery {} to webhook {}",
+                        job.delivery_id, job.webhook_id
+                    );
+                }
+                Err(TrySendError::Closed(_)) => {
+                    bail!("Webhook queue is closed")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delivers queued payloads, each one in its own task so a slow endpoint
+    /// doesn't hold up the others.
+    pub async fn run_queue(self: Arc<Self>) {
+        let mut receiver = self.queue_receiver.lock().await;
+
+        while let Some(job) = receiver.recv().await {
+            let service = self.clone();
+
+            tokio::spawn(async move { service.deliver(job).await });
+        }
+    }
+
+    async fn deliver(&self, job: WebhookJob) {
+        let webhook = match ObjectId::from_str(&job.webhook_id) {
+            Ok(webhook_id) => self.repository.get_webhook(webhook_id).await,
+            Err(err) => Err(err.into()),
+        };
+
+        let webhook = match webhook {
+            Ok(Some(webhook)) => webhook,
+            // removed while the delivery was waiting
+            Ok(None) => return,
+            Err(err) => {
+                error!("Failed to fetch webhook {}: {err}", job.webhook_id);
+                return;
+            }
+        };
+
+        // set on every attempt so recipients can refuse old or replayed
+        // requests
+        let timestamp = Utc::now().timestamp();
+
+        let signature = sign(&webhook.secret, timestamp, &job.body);
+
+        // resolved again on every attempt, the host may have been pointed
+        // somewhere else since the webhook was created
+        let http_client = match Url::parse(&webhook.url) {
+            Ok(url) => build_http_client(&url).await,
+            Err(err) => Err(err.into()),
+        };
+
+        let response = match http_client {
+            Ok(http_client) => http_client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Guildhub-Event", job.event_kind.get_id())
+                .header("X-Guildhub-Delivery", &job.delivery_id)
+                .header("X-Guildhub-Timestamp", timestamp)
+                .header("X-Guildhub-Signature", format!("sha256={signature}"))
+                .body(job.body.clone())
+                .send()
+                .await
+                .map_err(anyhow::Error::from),
+            Err(err) => Err(err),
+        };
+
+        // only the status is kept, response bodies may carry anything the
+        // endpoint decides to send back
+        let (status_code, error) = match response {
+            Ok(response) => (Some(response.status().as_u16()), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        let is_success = status_code
+            .is_some_and(|status_code| (200..300).contains(&status_code));
+
+        let delivery = WebhookDeliveryDocument {
+            _id: ObjectId::new(),
+            webhook_id: webhook._id,
+            delivery_id: job.delivery_id.clone(),
+            event_kind: job.event_kind,
+            attempt: job.attempt,
+            status_code,
+            error,
+            created_at: bson::DateTime::now(),
+        };
+
+        if let Err(err) = self.repository.insert_delivery(delivery).await {
+            error!(
+                "Failed to log webhook delivery {}: {err}",
+                job.delivery_id
+            );
+        }
+
+        if is_success {
+            return;
+        }
+
+        if job.attempt >= WEBHOOK_MAX_ATTEMPTS {
+            warn!(
+                "Giving up on delivery {} to webhook {} after {} attempts",
+                job.delivery_id, job.webhook_id, job.attempt
+            );
+            return;
+        }
+
+        let delay = WEBHOOK_RETRY_DELAY * 2u32.pow(job.attempt - 1);
+
+        info!(
+            "Retrying delivery {} to webhook {} in {}s",
+            job.delivery_id,
+            job.webhook_id,
+            delay.as_secs()
+        );
+
+        let queue_sender = self.queue_sender.clone();
+
+        tokio::spawn(async move {
+            sleep(delay).await;
+
+            let _ = queue_sender
+                .send(WebhookJob { attempt: job.attempt + 1, ..job })
+                .await;
+        });
+    }
+
+    async fn get_username(&self, user_id: usize) -> String {
+        self.member_directory
+            .get_member(&user_id)
+            .await
+            .map(|member| member.username)
+            .unwrap_or_else(|| "former member".to_string())
+    }
+
+    fn get_guild_url(&self, guild: &Guild) -> String {
+        format!("{}/guilds/{}", self.app_url, guild.id)
+    }
+
+    async fn get_generic_payload(
+        &self,
+        event: &WebhookEvent,
+        guild: &Guild,
+        delivery_id: &str,
+    ) -> Value {
+        let mut payload = json!({
+            "event": event.get_kind().get_id(),
+            "delivery_id": delivery_id,
+            "guild": {
+                "id": guild.id,
+                "name": guild.name,
+                "url": self.get_guild_url(guild),
+            },
+        });
+
+        match event {
+            WebhookEvent::TopicCreated(topic)
+            | WebhookEvent::TopicArchived(topic) => {
+                payload["topic"] = json!({
+                    "id": topic.id,
+                    "text": topic.text,
+                    "status": topic.status.to_string(),
+                    "author": {
+                        "id": topic.created_by_user_id,
+                        "username": self.get_username(topic.created_by_user_id).await,
+                    },
+                    "votes_count": topic.upvoted_by_users_ids.len(),
+                    "created_at": topic.created_at.to_rfc3339(),
+                    "updated_at": topic.updated_at.to_rfc3339(),
+                });
+            }
+            WebhookEvent::SessionScheduled(session) => {
+                payload["session"] = json!({
+                    "id": session.id,
+                    "starts_at": session.starts_at.to_rfc3339(),
+                    "ends_at": session.get_ends_at().to_rfc3339(),
+                    "duration_minutes": session.duration_minutes,
+                });
+            }
+        }
+
+        payload
+    }
+
+    async fn get_slack_payload(
+        &self,
+        event: &WebhookEvent,
+        guild: &Guild,
+    ) -> Value {
+        let guild_link =
+            format_slack_link(&self.get_guild_url(guild), &guild.name);
+
+        let text = match event {
+            WebhookEvent::TopicCreated(topic) => format!(
+                "@{} proposed a new topic in {guild_link}: {}",
+                escape_slack_text(
+                    &self.get_username(topic.created_by_user_id).await
+                ),
+                escape_slack_text(&quote(&topic.text)),
+            ),
+            WebhookEvent::TopicArchived(topic) => format!(
+                "{} was archived in {guild_link} with {} votes",
+                escape_slack_text(&quote(&topic.text)),
+                topic.upvoted_by_users_ids.len(),
+            ),
+            WebhookEvent::SessionScheduled(session) => format!(
+                "{guild_link} meets on {} for {} minutes",
+                session.get_starts_at_label(),
+                session.duration_minutes,
+            ),
+        };
+
+        json!({
+            "username": "guildhub",
+            "text": text,
+        })
+    }
+}
+
+/// Addresses the host of the url resolves to. Fails when any of them is not
+/// public, so webhooks can't be used to reach the network of the instance.
+async fn resolve_public_addresses(url: &Url) -> Result<Vec<SocketAddr>> {
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<SocketAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Domain(domain)) => {
+            match tokio::net::lookup_host((domain, port)).await {
+                Ok(addresses) => addresses.collect(),
+                Err(_) => bail!(AppError::Validation(format!(
+                    "Failed to resolve webhook host {domain}"
+                ))),
+            }
+        }
+        None => {
+            bail!(AppError::Validation("Webhook url has no host".to_string()))
+        }
+    };
+
+    if addresses.is_empty()
+        || !addresses.iter().all(|address| is_public_address(address.ip()))
+    {
+        bail!(AppError::Validation(
+            "Webhook url has to point to a public address".to_string()
+        ));
+    }
+
+    Ok(addresses)
+}
+
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast())
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(ip.into()),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Client connecting only to the checked addresses, so the host can't
+/// resolve to another one between the check and the request. Redirects are
+/// not followed as they could lead anywhere.
+async fn build_http_client(url: &Url) -> Result<reqwest::Client> {
+    let addresses = resolve_public_addresses(url).await?;
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+
+    if let Some(Host::Domain(domain)) = url.host() {
+        builder = builder.resolve_to_addrs(domain, &addresses);
+    }
+
+    Ok(builder.build()?)
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.trim())
+}
+
+/// HMAC of `<timestamp>.<body>`, the timestamp being the one sent in
+/// `X-Guildhub-Timestamp`.
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    // hmac accepts keys of any length
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC key of any length");
+
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_public_address_refuses_internal_addresses() {
+        let addresses = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ];
+
+        for address in addresses {
+            assert!(
+                !is_public_address(address.parse().unwrap()),
+                "{address} should be refused"
+            );
+        }
+    }
+
+    #[test]
+    fn is_public_address_accepts_public_addresses() {
+        for address in ["93.184.216.34", "1.1.1.1", "2606:4700::1111"] {
+            assert!(
+                is_public_address(address.parse().unwrap()),
+                "{address} should be accepted"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_public_addresses_refuses_internal_hosts() {
+        let urls = [
+            "http://127.0.0.1:8080/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://localhost/hook",
+        ];
+
+        for url in urls {
+            let url = Url::parse(url).unwrap();
+
+            assert!(
+                resolve_public_addresses(&url).await.is_err(),
+                "{url} should be refused"
+            );
+        }
+    }
+
+    #[test]
+    fn sign_covers_the_timestamp_and_the_body() {
+        let signature = sign("secret", 1700000000, "{}");
+
+        assert_eq!(signature.len(), 64);
+        assert_eq!(signature, sign("secret", 1700000000, "{}"));
+        assert_ne!(signature, sign("secret", 1700000001, "{}"));
+        assert_ne!(signature, sign("secret", 1700000000, "{ }"));
+        assert_ne!(signature, sign("other secret", 1700000000, "{}"));
+    }
+
+    #[test]
+    fn sign_matches_a_plain_hmac_of_the_signed_payload() {
+        let mut mac = HmacSha256::new_from_slice(b"secret").unwrap();
+
+        mac.update(b"1700000000.{\"event\":\"topic_created\"}");
+
+        assert_eq!(
+            sign("secret", 1700000000, "{\"event\":\"topic_created\"}"),
+            hex::encode(mac.finalize().into_bytes())
+        );
+    }
+}
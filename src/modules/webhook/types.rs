@@ -0,0 +1,220 @@
+use askama_axum::Template;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    libs::serialization,
+    modules::{session::Session, topic::types::Topic},
+};
+
+use super::{WebhookDeliveryDocument, WebhookDocument};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    TopicCreated,
+    TopicArchived,
+    SessionScheduled,
+}
+
+impl WebhookEventKind {
+    pub fn all() -> Vec<WebhookEventKind> {
+        vec![
+            WebhookEventKind::TopicCreated,
+            WebhookEventKind::TopicArchived,
+            WebhookEventKind::SessionScheduled,
+        ]
+    }
+
+    pub fn get_id(&self) -> &'static str {
+        match self {
+            WebhookEventKind::TopicCreated => "topic_created",
+            WebhookEventKind::TopicArchived => "topic_archived",
+            WebhookEventKind::SessionScheduled => "session_scheduled",
+        }
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            WebhookEventKind::TopicCreated => "Topic created",
+            WebhookEventKind::TopicArchived => "Topic archived",
+            WebhookEventKind::SessionScheduled => "Session scheduled",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The event as JSON, for custom integrations.
+    Generic,
+    /// Incoming webhook message understood by Slack and Mattermost.
+    Slack,
+}
+
+impl WebhookFormat {
+    pub fn all() -> Vec<WebhookFormat> {
+        vec![WebhookFormat::Generic, WebhookFormat::Slack]
+    }
+
+    pub fn get_id(&self) -> &'static str {
+        match self {
+            WebhookFormat::Generic => "generic",
+            WebhookFormat::Slack => "slack",
+        }
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            WebhookFormat::Generic => "Generic JSON",
+            WebhookFormat::Slack => "Slack / Mattermost",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub guild_id: String,
+    pub url: String,
+    pub format: WebhookFormat,
+    pub event_kinds: Vec<WebhookEventKind>,
+    pub secret: String,
+    #[serde(with = "serialization::chrono_date")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<WebhookDocument> for Webhook {
+    fn from(document: WebhookDocument) -> Webhook {
+        Webhook {
+            id: document._id.to_hex(),
+            guild_id: document.guild_id.to_hex(),
+            url: document.url,
+            format: document.format,
+            event_kinds: document.event_kinds,
+            secret: document.secret,
+            created_at: document.created_at.to_chrono(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub delivery_id: String,
+    pub event_kind: WebhookEventKind,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    #[serde(with = "serialization::chrono_date")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    pub fn is_success(&self) -> bool {
+        self.status_code
+            .is_some_and(|status_code| (200..300).contains(&status_code))
+    }
+
+    pub fn get_status_label(&self) -> String {
+        match (self.status_code, &self.error) {
+            (Some(status_code), _) => status_code.to_string(),
+            (None, Some(error)) => error.clone(),
+            (None, None) => "No response".to_string(),
+        }
+    }
+}
+
+impl From<WebhookDeliveryDocument> for WebhookDelivery {
+    fn from(document: WebhookDeliveryDocument) -> WebhookDelivery {
+        WebhookDelivery {
+            id: document._id.to_hex(),
+            delivery_id: document.delivery_id,
+            event_kind: document.event_kind,
+            attempt: document.attempt,
+            status_code: document.status_code,
+            error: document.error,
+            created_at: document.created_at.to_chrono(),
+        }
+    }
+}
+
+/// Guild activity webhooks are notified about.
+#[derive(Clone, Debug)]
+pub enum WebhookEvent {
+    TopicCreated(Topic),
+    TopicArchived(Topic),
+    SessionScheduled(Session),
+}
+
+impl WebhookEvent {
+    pub fn get_kind(&self) -> WebhookEventKind {
+        match self {
+            WebhookEvent::TopicCreated(_) => WebhookEventKind::TopicCreated,
+            WebhookEvent::TopicArchived(_) => WebhookEventKind::TopicArchived,
+            WebhookEvent::SessionScheduled(_) => {
+                WebhookEventKind::SessionScheduled
+            }
+        }
+    }
+
+    pub fn get_guild_id(&self) -> &str {
+        match self {
+            WebhookEvent::TopicCreated(topic)
+            | WebhookEvent::TopicArchived(topic) => &topic.guild_id,
+            WebhookEvent::SessionScheduled(session) => &session.guild_id,
+        }
+    }
+}
+
+/// A payload waiting in the delivery queue.
+#[derive(Clone, Debug)]
+pub struct WebhookJob {
+    pub webhook_id: String,
+    pub delivery_id: String,
+    pub event_kind: WebhookEventKind,
+    pub body: String,
+    pub attempt: u32,
+}
+
+#[derive(Deserialize, Debug, Validate)]
+pub struct WebhookFormDTO {
+    #[validate(url(message = "Url must be a valid http(s) address"))]
+    pub url: String,
+    pub format: WebhookFormat,
+    #[serde(default)]
+    #[validate(length(min = 1, message = "Pick at least one event"))]
+    pub event_kinds: Vec<WebhookEventKind>,
+}
+
+#[derive(Deserialize)]
+pub struct WebhookIdParameter {
+    pub guild_id: String,
+    pub webhook_id: String,
+}
+
+#[derive(Template)]
+#[template(path = "components/webhook/guild-webhooks.html")]
+pub struct GuildWebhooksTemplate {
+    pub guild_id: String,
+    pub webhooks: Vec<Webhook>,
+    pub error_message: String,
+}
+
+impl GuildWebhooksTemplate {
+    pub fn get_event_kinds(&self) -> Vec<WebhookEventKind> {
+        WebhookEventKind::all()
+    }
+
+    pub fn get_formats(&self) -> Vec<WebhookFormat> {
+        WebhookFormat::all()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "components/webhook/webhook-deliveries.html")]
+pub struct WebhookDeliveriesTemplate {
+    pub webhook_id: String,
+    pub deliveries: Vec<WebhookDelivery>,
+}
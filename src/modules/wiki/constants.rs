@@ -0,0 +1,2 @@
+pub const AGENDA_TOPICS_LIMIT: usize = 10;
+pub const ARCHIVED_TOPICS_LIMIT: i64 = 100;
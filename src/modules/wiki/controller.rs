@@ -0,0 +1,52 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use std::sync::Arc;
+
+use crate::modules::{
+    app::{
+        app::App, user_extractor::Authenticated, AppError, HxTriggerEvent,
+        ToastLevel,
+    },
+    guild::GuildIdParameter,
+};
+
+use super::WikiExportParameters;
+
+pub async fn export_guild_to_wiki(
+    Authenticated(user): Authenticated,
+    State(app): State<Arc<App>>,
+    Path(parameters): Path<GuildIdParameter>,
+    Query(WikiExportParameters { kind }): Query<WikiExportParameters>,
+) -> Result<impl IntoResponse, AppError> {
+    let wiki_service = match &app.wiki_service {
+        Some(wiki_service) => wiki_service,
+        None => {
+            return Err(AppError::Unavailable(
+                "Wiki export is disabled".to_string(),
+            ))
+        }
+    };
+
+    let guild = app
+        .guilds_service
+        .get_guild_or_error(user, parameters.guild_id.as_str())
+        .await?;
+
+    let page = wiki_service.export(&guild, kind).await?;
+
+    let event = HxTriggerEvent::ShowToast {
+        level: ToastLevel::Info,
+        message: format!("Published to wiki page {}", page.title),
+    };
+
+    let event = event.to_header_value()?;
+
+    let mut headers = HeaderMap::new();
+
+    headers.insert("HX-Trigger", event);
+
+    Ok((StatusCode::OK, headers))
+}
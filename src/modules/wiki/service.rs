@@ -0,0 +1,143 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use askama::Template;
+use chrono::Utc;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+use crate::{
+    libs::gitlab_api::gitlab_api::WikiPage,
+    modules::{
+        gitlab::GitlabService,
+        guild::{Guild, GuildsService},
+        topic::{
+            types::{PaginationParameters, TopicStatus},
+            TopicsService,
+        },
+    },
+};
+
+use super::{
+    constants::{AGENDA_TOPICS_LIMIT, ARCHIVED_TOPICS_LIMIT},
+    AgendaWikiTemplate, ArchivedTopicsWikiTemplate, WikiExportKind,
+};
+
+/// Publishes guild agendas and archived topics to a gitlab wiki.
+pub struct WikiService {
+    gitlab_service: Arc<GitlabService>,
+    guilds_service: Arc<GuildsService>,
+    topics_service: Arc<TopicsService>,
+}
+
+impl WikiService {
+    pub fn new(
+        gitlab_service: Arc<GitlabService>,
+        guilds_service: Arc<GuildsService>,
+        topics_service: Arc<TopicsService>,
+    ) -> Self {
+        Self { gitlab_service, guilds_service, topics_service }
+    }
+
+    pub async fn render(
+        &self,
+        guild: &Guild,
+        kind: WikiExportKind,
+    ) -> Result<String> {
+        let published_at = Utc::now();
+
+        // topics are rendered as seen by the guild owner
+        let content = match kind {
+            WikiExportKind::Agenda => {
+                let topics = self
+                    .topics_service
+                    .get_topics_by_guild_id(
+                        guild.created_by_user.id,
+                        &guild.id,
+                        PaginationParameters {
+                            skip: 0,
+                            limit: AGENDA_TOPICS_LIMIT,
+                        },
+                        guild.clone(),
+                        TopicStatus::Created,
+                    )
+                    .await?;
+
+                AgendaWikiTemplate { guild, topics, published_at }.render()?
+            }
+            WikiExportKind::ArchivedTopics => {
+                let archived_topics = self
+                    .topics_service
+                    .get_latest_archived_topics(
+                        &guild.id,
+                        ARCHIVED_TOPICS_LIMIT,
+                    )
+                    .await?;
+
+                let mut topics = Vec::with_capacity(archived_topics.len());
+
+                for topic in archived_topics {
+                    topics.push(
+                        self.topics_service
+                            .map_topic_with_user(
+                                guild,
+                                topic,
+                                guild.created_by_user.id,
+                            )
+                            .await?,
+                    );
+                }
+
+                ArchivedTopicsWikiTemplate { guild, topics, published_at }
+                    .render()?
+            }
+        };
+
+        Ok(content)
+    }
+
+    pub async fn export(
+        &self,
+        guild: &Guild,
+        kind: WikiExportKind,
+    ) -> Result<WikiPage> {
+        let content = self.render(guild, kind).await?;
+
+        let page = self
+            .gitlab_service
+            .publish_wiki_page(&kind.get_page_title(guild), content)
+            .await?;
+
+        info!(
+            "Published {kind:?} of guild {} to wiki page {}",
+            guild.id, page.slug
+        );
+
+        Ok(page)
+    }
+
+    pub async fn export_agendas_in_loop(&self, interval: Duration) {
+        loop {
+            sleep(interval).await;
+
+            let guilds = match self.guilds_service.get_all_guilds().await {
+                Ok(guilds) => guilds,
+                Err(err) => {
+                    error!("Failed to fetch guilds for wiki export: {err}");
+                    continue;
+                }
+            };
+
+            for guild in guilds {
+                if let Err(err) =
+                    self.export(&guild, WikiExportKind::Agenda).await
+                {
+                    error!(
+                        "Failed to export guild {} to wiki: {err}",
+                        guild.id
+                    );
+                }
+            }
+        }
+    }
+}
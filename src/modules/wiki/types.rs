@@ -0,0 +1,103 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::modules::{guild::Guild, topic::types::TopicPersonalized};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WikiExportKind {
+    Agenda,
+    ArchivedTopics,
+}
+
+impl WikiExportKind {
+    /// Title of the page, which gitlab derives the slug from. Guild names
+    /// aren't unique, so the guild id keeps guilds from sharing pages.
+    pub fn get_page_title(&self, guild: &Guild) -> String {
+        // slashes would nest the page one level deeper
+        let guild_name = guild.name.trim().replace('/', "-");
+        let guild_directory = format!("Guilds/{guild_name} ({})", guild.id);
+
+        match self {
+            WikiExportKind::Agenda => format!("{guild_directory}/Agenda"),
+            WikiExportKind::ArchivedTopics => {
+                format!("{guild_directory}/Archived topics")
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WikiExportParameters {
+    pub kind: WikiExportKind,
+}
+
+#[derive(Template)]
+#[template(path = "wiki/agenda.md")]
+pub struct AgendaWikiTemplate<'a> {
+    pub guild: &'a Guild,
+    pub topics: Vec<TopicPersonalized>,
+    pub published_at: DateTime<Utc>,
+}
+
+impl AgendaWikiTemplate<'_> {
+    /// Keeps topic texts from breaking out of their table cell.
+    pub fn escape_table_cell(&self, text: &str) -> String {
+        text.trim().replace('|', "\\|").replace('\n', " ")
+    }
+}
+
+#[derive(Template)]
+#[template(path = "wiki/archived-topics.md")]
+pub struct ArchivedTopicsWikiTemplate<'a> {
+    pub guild: &'a Guild,
+    pub topics: Vec<TopicPersonalized>,
+    pub published_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::libs::gitlab_api::gitlab_api::Member;
+
+    use super::*;
+
+    fn guild(id: &str, name: &str) -> Guild {
+        Guild {
+            id: id.to_string(),
+            name: name.to_string(),
+            topics_count: 0,
+            members: vec![],
+            created_by_user: Member::default(),
+            updated_at: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn page_titles_contain_the_guild_id() {
+        let guild = guild("65f1c0ffee", " Rust / Go ");
+
+        assert_eq!(
+            WikiExportKind::Agenda.get_page_title(&guild),
+            "Guilds/Rust - Go (65f1c0ffee)/Agenda"
+        );
+        assert_eq!(
+            WikiExportKind::ArchivedTopics.get_page_title(&guild),
+            "Guilds/Rust - Go (65f1c0ffee)/Archived topics"
+        );
+    }
+
+    #[test]
+    fn guilds_with_the_same_name_get_different_pages() {
+        let first = guild("65f1c0ffee", "Backend");
+        let second = guild("65f1decade", "Backend");
+
+        assert_ne!(
+            WikiExportKind::Agenda.get_page_title(&first),
+            WikiExportKind::Agenda.get_page_title(&second)
+        );
+    }
+}